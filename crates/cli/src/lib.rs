@@ -58,6 +58,8 @@ pub const CMD_PREPROCESS_HOST: &str = "preprocess-host";
 
 pub const FLAG_EMIT_LLVM_IR: &str = "emit-llvm-ir";
 pub const FLAG_PROFILING: &str = "profiling";
+pub const FLAG_TRAP_UNREACHABLE: &str = "trap-unreachable";
+pub const FLAG_STACK_PROTECTOR: &str = "stack-protector";
 pub const FLAG_BUNDLE: &str = "bundle";
 pub const FLAG_DEV: &str = "dev";
 pub const FLAG_OPTIMIZE: &str = "optimize";
@@ -65,6 +67,7 @@ pub const FLAG_MAX_THREADS: &str = "max-threads";
 pub const FLAG_OPT_SIZE: &str = "opt-size";
 pub const FLAG_LIB: &str = "lib";
 pub const FLAG_NO_LINK: &str = "no-link";
+pub const FLAG_STANDALONE_MAIN: &str = "standalone-main";
 pub const FLAG_TARGET: &str = "target";
 pub const FLAG_TIME: &str = "time";
 pub const FLAG_VERBOSE: &str = "verbose";
@@ -126,10 +129,22 @@ pub fn build_app() -> Command {
 
     let flag_profiling = Arg::new(FLAG_PROFILING)
         .long(FLAG_PROFILING)
-        .help("Keep debug info in the final generated program even in optimized builds")
+        .help("Keep debug info in the final generated program even in optimized builds\n(With --dev, this also calls `__fentry__` on entry to every function, which only works on x86_64 Linux.)")
         .action(ArgAction::SetTrue)
         .required(false);
 
+    let flag_trap_unreachable = Arg::new(FLAG_TRAP_UNREACHABLE)
+        .long(FLAG_TRAP_UNREACHABLE)
+        .help("Make code that should never run crash right away if it does, instead of running whatever comes next\n(This only applies when --dev also provided.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
+    let flag_stack_protector = Arg::new(FLAG_STACK_PROTECTOR)
+        .long(FLAG_STACK_PROTECTOR)
+        .help("Make functions check for stack smashing before they return, either those that keep buffers on the stack or all of them\n(This only applies when --dev also provided, and only on x86_64 Linux.)")
+        .value_parser(["buffers", "all"])
+        .required(false);
+
     let flag_time = Arg::new(FLAG_TIME)
         .long(FLAG_TIME)
         .help("Print detailed compilation time information")
@@ -205,6 +220,8 @@ pub fn build_app() -> Command {
             .arg(flag_dev.clone())
             .arg(flag_emit_llvm_ir.clone())
             .arg(flag_profiling.clone())
+            .arg(flag_trap_unreachable.clone())
+            .arg(flag_stack_protector.clone())
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_build_host.clone())
@@ -241,6 +258,13 @@ pub fn build_app() -> Command {
                     .action(ArgAction::SetTrue)
                     .required(false),
             )
+            .arg(
+                Arg::new(FLAG_STANDALONE_MAIN)
+                    .long(FLAG_STANDALONE_MAIN)
+                    .help("Add a `_start` to the `.o` file that calls the given exposed function with argc and argv and exits with what it returns, so the program runs without a host\n(This only applies when --dev also provided, and only on x86_64 Linux.)")
+                    .requires(FLAG_NO_LINK)
+                    .required(false),
+            )
             .arg(
                 Arg::new(ROC_FILE)
                     .help("The .roc file to build")
@@ -305,6 +329,8 @@ pub fn build_app() -> Command {
             .arg(flag_dev.clone())
             .arg(flag_emit_llvm_ir.clone())
             .arg(flag_profiling.clone())
+            .arg(flag_trap_unreachable.clone())
+            .arg(flag_stack_protector.clone())
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_build_host.clone())
@@ -321,6 +347,8 @@ pub fn build_app() -> Command {
             .arg(flag_dev.clone())
             .arg(flag_emit_llvm_ir.clone())
             .arg(flag_profiling.clone())
+            .arg(flag_trap_unreachable.clone())
+            .arg(flag_stack_protector.clone())
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_build_host.clone())
@@ -464,6 +492,8 @@ pub fn build_app() -> Command {
         .arg(flag_dev)
         .arg(flag_emit_llvm_ir)
         .arg(flag_profiling)
+        .arg(flag_trap_unreachable)
+        .arg(flag_stack_protector)
         .arg(flag_time)
         .arg(flag_linker)
         .arg(flag_build_host)
@@ -903,6 +933,27 @@ pub fn build(
         || matches!(opt_level, OptLevel::Development | OptLevel::Normal);
     let emit_timings = matches.get_flag(FLAG_TIME);
 
+    let stack_protector = match matches
+        .get_one::<String>(FLAG_STACK_PROTECTOR)
+        .map(|s| s.as_str())
+    {
+        Some("buffers") => roc_gen_dev::StackProtector::Buffers,
+        Some("all") => roc_gen_dev::StackProtector::All,
+        _ => roc_gen_dev::StackProtector::Off,
+    };
+    let standalone_main = if config == BuildOnly {
+        matches.get_one::<String>(FLAG_STANDALONE_MAIN).cloned()
+    } else {
+        None
+    };
+    let dev_options = roc_gen_dev::DevOptions {
+        profiling: matches.get_flag(FLAG_PROFILING),
+        trap_unreachable: matches.get_flag(FLAG_TRAP_UNREACHABLE),
+        stack_protector,
+        standalone_main,
+        ..Default::default()
+    };
+
     let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
         None => Threading::AllAvailable,
         Some(0) => user_error!("cannot build with at most 0 threads"),
@@ -949,6 +1000,7 @@ pub fn build(
         emit_debug_info,
         emit_llvm_ir,
         fuzz,
+        dev_options,
     };
 
     let load_config = standard_load_config(target, build_ordering, threading);
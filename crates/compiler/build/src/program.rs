@@ -78,13 +78,15 @@ pub enum CodeGenBackend {
     Wasm,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct CodeGenOptions {
     pub backend: CodeGenBackend,
     pub opt_level: OptLevel,
     pub emit_debug_info: bool,
     pub emit_llvm_ir: bool,
     pub fuzz: bool,
+    /// Only used by the assembly backend.
+    pub dev_options: roc_gen_dev::DevOptions,
 }
 
 type GenFromMono<'a> = (CodeObject, CodeGenTiming, ExpectMetadata<'a>);
@@ -95,7 +97,7 @@ pub fn gen_from_mono_module<'a>(
     loaded: MonomorphizedModule<'a>,
     roc_file_path: &Path,
     target: Target,
-    code_gen_options: CodeGenOptions,
+    code_gen_options: &CodeGenOptions,
     built_host_opt: &BuiltHostOpt,
     wasm_dev_stack_bytes: Option<u32>,
 ) -> GenFromMono<'a> {
@@ -120,6 +122,7 @@ pub fn gen_from_mono_module<'a>(
                 built_host_opt,
                 wasm_dev_stack_bytes,
                 AssemblyBackendMode::Binary, // dummy value, unused in practice
+                &code_gen_options.dev_options,
             )
         }
         CodeGenBackend::Assembly(backend_mode) => gen_from_mono_module_dev(
@@ -129,6 +132,7 @@ pub fn gen_from_mono_module<'a>(
             built_host_opt,
             wasm_dev_stack_bytes,
             backend_mode,
+            &code_gen_options.dev_options,
         ),
         CodeGenBackend::Llvm(backend_mode) => gen_from_mono_module_llvm(
             arena,
@@ -444,6 +448,7 @@ fn gen_from_mono_module_dev<'a>(
     built_host_opt: &BuiltHostOpt,
     wasm_dev_stack_bytes: Option<u32>,
     #[allow(unused_variables)] backend_mode: AssemblyBackendMode,
+    #[allow(unused_variables)] dev_options: &roc_gen_dev::DevOptions,
 ) -> GenFromMono<'a> {
     match (built_host_opt, target.architecture()) {
         (BuiltHostOpt::Additive(host_path), Architecture::Wasm32) => {
@@ -476,7 +481,7 @@ fn gen_from_mono_module_dev<'a>(
         (_, Architecture::X86_64 | Architecture::Aarch64) => {
             #[cfg(not(feature = "target-wasm32"))]
             {
-                gen_from_mono_module_dev_assembly(arena, loaded, target, backend_mode)
+                gen_from_mono_module_dev_assembly(arena, loaded, target, backend_mode, dev_options)
             }
 
             #[cfg(feature = "target-wasm32")]
@@ -573,6 +578,7 @@ fn gen_from_mono_module_dev_assembly<'a>(
     loaded: MonomorphizedModule<'a>,
     target: Target,
     backend_mode: AssemblyBackendMode,
+    dev_options: &roc_gen_dev::DevOptions,
 ) -> GenFromMono<'a> {
     let all_code_gen_start = Instant::now();
 
@@ -593,6 +599,7 @@ fn gen_from_mono_module_dev_assembly<'a>(
         exposed_to_host: exposed_to_host.top_level_values.keys().copied().collect(),
         lazy_literals,
         mode: backend_mode,
+        options: dev_options.clone(),
     };
 
     let module_object =
@@ -791,7 +798,7 @@ pub enum BuiltHostOpt {
 }
 
 fn build_and_preprocess_host(
-    code_gen_options: CodeGenOptions,
+    code_gen_options: &CodeGenOptions,
     dll_stub_symbols: Vec<String>,
     emit_timings: bool,
     linking_strategy: LinkingStrategy,
@@ -880,7 +887,7 @@ fn build_loaded_file<'a>(
             match prebuilt_host {
                 BuiltHostOpt::None => {
                     build_and_preprocess_host(
-                        code_gen_options,
+                        &code_gen_options,
                         dll_stub_symbols,
                         emit_timings,
                         linking_strategy,
@@ -935,7 +942,7 @@ fn build_loaded_file<'a>(
         loaded,
         &app_module_path,
         target,
-        code_gen_options,
+        &code_gen_options,
         &built_host_opt,
         wasm_dev_stack_bytes,
     );
@@ -1456,6 +1463,7 @@ pub fn build_str_test<'a>(
         emit_debug_info: false,
         emit_llvm_ir: false,
         fuzz: false,
+        dev_options: Default::default(),
     };
 
     let emit_timings = false;
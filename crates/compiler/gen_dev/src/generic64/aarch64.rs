@@ -253,6 +253,8 @@ pub struct AArch64Call {}
 const STACK_ALIGNMENT: u8 = 16;
 
 impl CallConv<AArch64GeneralReg, AArch64FloatReg, AArch64Assembler> for AArch64Call {
+    const NAME: &'static str = "AAPCS64";
+
     const BASE_PTR_REG: AArch64GeneralReg = AArch64GeneralReg::FP;
     const STACK_PTR_REG: AArch64GeneralReg = AArch64GeneralReg::ZRSP;
 
@@ -367,6 +369,7 @@ impl CallConv<AArch64GeneralReg, AArch64FloatReg, AArch64Assembler> for AArch64C
     ///  213564:       f90037fe        str     x30, [sp, #104]
     ///  213568:       f90033fd        str     x29, [sp, #96]
     const SHADOW_SPACE_SIZE: u8 = 16;
+    const RED_ZONE_SIZE: u8 = 0;
 
     // These are registers that a called function must save and restore if it wants to use them.
     #[inline(always)]
@@ -408,6 +411,7 @@ impl CallConv<AArch64GeneralReg, AArch64FloatReg, AArch64Assembler> for AArch64C
         saved_float_regs: &[AArch64FloatReg],
         requested_stack_size: i32,
         fn_call_stack_size: i32,
+        _requires_stack_modification: bool,
     ) -> i32 {
         let frame_pointer_link_register = 16;
 
@@ -476,6 +480,7 @@ impl CallConv<AArch64GeneralReg, AArch64FloatReg, AArch64Assembler> for AArch64C
         saved_float_regs: &[AArch64FloatReg],
         aligned_stack_size: i32,
         fn_call_stack_size: i32,
+        _requires_stack_modification: bool,
     ) {
         let frame_pointer_link_register = 16;
 
@@ -547,6 +552,7 @@ impl CallConv<AArch64GeneralReg, AArch64FloatReg, AArch64Assembler> for AArch64C
                 storage_manager.claim_stack_area_layout(layout_interner, *dst, *ret_layout);
 
             // Set the xr (x8) register to the address base + offset.
+            storage_manager.evict_general_reg(buf, AArch64GeneralReg::XR);
             AArch64Assembler::add_reg64_reg64_imm32(
                 buf,
                 AArch64GeneralReg::XR,
@@ -778,7 +784,7 @@ impl CallConv<AArch64GeneralReg, AArch64FloatReg, AArch64Assembler> for AArch64C
         ASM::add_reg64_reg64_imm32(buf, X10, X1, 1);
 
         // the setlongjmp_buffer
-        ASM::data_pointer(buf, relocs, String::from("setlongjmp_buffer"), X0);
+        ASM::data_pointer(buf, relocs, String::from("setlongjmp_buffer"), 0, X0);
 
         // the value to return from the longjmp. It is a pointer to the last 3 words of the setlongjmp_buffer
         // they represent the error message. (168 + 8) which is after V15 register.
@@ -792,6 +798,10 @@ impl CallConv<AArch64GeneralReg, AArch64FloatReg, AArch64Assembler> for AArch64C
 
         Self::longjmp(buf)
     }
+
+    fn entry_shim(_buf: &mut Vec<'_, u8>, _relocs: &mut Vec<'_, Relocation>, _main: String) {
+        internal_error!("entry shims are only generated for x86_64 Linux");
+    }
 }
 
 fn copy_symbol_to_stack_offset<'a, CC>(
@@ -1080,6 +1090,7 @@ impl AArch64CallStoreArgs {
                         // if there is a general purpose register available, use it to store a pointer to the value
                         let (base_offset, _size) = storage_manager.stack_offset_and_size(&sym);
 
+                        storage_manager.evict_general_reg(buf, *reg);
                         ASM::add_reg64_reg64_imm32(buf, *reg, AArch64GeneralReg::FP, base_offset);
 
                         self.general_i += 1;
@@ -1138,6 +1149,7 @@ impl AArch64CallStoreArgs {
 
         match Self::GENERAL_PARAM_REGS.get(self.general_i) {
             Some(reg) => {
+                storage_manager.evict_general_reg(buf, *reg);
                 ASM::mov_reg64_base32(buf, *reg, offset);
 
                 self.general_i += 1;
@@ -1166,6 +1178,9 @@ impl AArch64CallStoreArgs {
             let reg1 = Self::GENERAL_PARAM_REGS[self.general_i];
             let reg2 = Self::GENERAL_PARAM_REGS[self.general_i + 1];
 
+            storage_manager.evict_general_reg(buf, reg1);
+            storage_manager.evict_general_reg(buf, reg2);
+
             ASM::mov_reg64_base32(buf, reg1, offset);
             ASM::mov_reg64_base32(buf, reg2, offset + 8);
 
@@ -1190,13 +1205,13 @@ impl AArch64CallStoreArgs {
     ) {
         match Self::GENERAL_PARAM_REGS.get(self.general_i) {
             Some(reg) => {
-                storage_manager.load_to_specified_general_reg(buf, &sym, *reg);
+                storage_manager.load_to_param_general_reg(buf, &sym, *reg);
                 self.general_i += 1;
             }
             None => {
                 let tmp = AArch64GeneralReg::X15;
 
-                storage_manager.load_to_specified_general_reg(buf, &sym, tmp);
+                storage_manager.load_to_param_general_reg(buf, &sym, tmp);
                 AArch64Assembler::mov_stack32_reg64(buf, self.tmp_stack_offset, tmp);
 
                 self.tmp_stack_offset += 8;
@@ -1212,14 +1227,14 @@ impl AArch64CallStoreArgs {
     ) {
         match Self::FLOAT_PARAM_REGS.get(self.float_i) {
             Some(reg) => {
-                storage_manager.load_to_specified_float_reg(buf, &sym, *reg);
+                storage_manager.load_to_param_float_reg(buf, &sym, *reg);
                 self.float_i += 1;
             }
             None => {
                 // Copy to stack using return reg as buffer.
                 let tmp = Self::FLOAT_RETURN_REGS[0];
 
-                storage_manager.load_to_specified_float_reg(buf, &sym, tmp);
+                storage_manager.load_to_param_float_reg(buf, &sym, tmp);
                 AArch64Assembler::mov_stack32_freg64(buf, self.tmp_stack_offset, tmp);
 
                 self.tmp_stack_offset += 8;
@@ -1229,6 +1244,9 @@ impl AArch64CallStoreArgs {
 }
 
 impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
+    const SHIFT_COUNT_REG: Option<AArch64GeneralReg> = None;
+    const JMP_IMM32_SIZE: u64 = 4;
+
     #[inline(always)]
     fn abs_reg64_reg64(buf: &mut Vec<'_, u8>, dst: AArch64GeneralReg, src: AArch64GeneralReg) {
         cmp_reg64_imm12(buf, src, 0);
@@ -1279,6 +1297,15 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
     ) {
         add_reg64_reg64_reg64(buf, dst, src1, src2);
     }
+    #[inline(always)]
+    fn adds_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        src1: AArch64GeneralReg,
+        src2: AArch64GeneralReg,
+    ) {
+        adds_reg64_reg64_reg64(buf, dst, src1, src2);
+    }
 
     #[inline(always)]
     fn add_freg32_freg32_freg32(
@@ -1336,7 +1363,7 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         dst: AArch64GeneralReg,
     ) {
         // a function pointer is the same as a data pointer on AArch64
-        Self::data_pointer(buf, relocs, fn_name, dst)
+        Self::data_pointer(buf, relocs, fn_name, 0, dst)
     }
 
     #[inline(always)]
@@ -1344,6 +1371,7 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         buf: &mut Vec<'_, u8>,
         relocs: &mut Vec<'_, Relocation>,
         fn_name: String,
+        addend: i64,
         dst: AArch64GeneralReg,
     ) {
         // an `adrp` instruction and an addition to add in the lower bits
@@ -1367,9 +1395,27 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         relocs.push(Relocation::LinkedData {
             offset: buf.len() as u64 - 8,
             name: fn_name,
+            addend,
         });
     }
 
+    #[inline(always)]
+    fn load_stack_guard(_buf: &mut Vec<'_, u8>, _dst: AArch64GeneralReg) {
+        todo!("the stack protector for AArch64");
+    }
+
+    #[inline(always)]
+    fn add_scaled_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        base: AArch64GeneralReg,
+        index: AArch64GeneralReg,
+        scale: u8,
+    ) {
+        debug_assert!(matches!(scale, 1 | 2 | 4 | 8));
+        add_reg64_reg64_reg64_lsl(buf, dst, base, index, scale.trailing_zeros() as u8);
+    }
+
     #[inline(always)]
     fn imul_reg64_reg64_reg64(
         buf: &mut Vec<'_, u8>,
@@ -1499,12 +1545,67 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         buf.len() - 4
     }
 
+    #[inline(always)]
+    fn jmp_to_offset(buf: &mut Vec<'_, u8>, target: u64) {
+        // on aarch64, jumps are calculated from the start of the jmp instruction
+        let offset = target as i32 - buf.len() as i32;
+        Self::jmp_imm32(buf, offset);
+    }
+
     #[inline(always)]
     fn tail_call(buf: &mut Vec<'_, u8>) -> u64 {
         Self::jmp_imm32(buf, 0);
         buf.len() as u64 - 4 // TODO is 4 the correct offset in ARM?
     }
 
+    #[inline(always)]
+    fn jo_imm32(buf: &mut Vec<'_, u8>, offset: i32) -> usize {
+        if (-(1 << 20)..(1 << 20)).contains(&offset) {
+            b_cond_imm19(buf, ConditionCode::VS, offset);
+        } else {
+            todo!("jump offsets over 20 bits for AArch64: {:#x}", offset);
+        }
+
+        // on aarch64, jumps are calculated from the start of the jmp instruction
+        buf.len() - 4
+    }
+
+    #[inline(always)]
+    fn jno_imm32(buf: &mut Vec<'_, u8>, offset: i32) -> usize {
+        if (-(1 << 20)..(1 << 20)).contains(&offset) {
+            b_cond_imm19(buf, ConditionCode::VC, offset);
+        } else {
+            todo!("jump offsets over 20 bits for AArch64: {:#x}", offset);
+        }
+
+        // on aarch64, jumps are calculated from the start of the jmp instruction
+        buf.len() - 4
+    }
+
+    #[inline(always)]
+    fn jz_reg64_imm32(buf: &mut Vec<'_, u8>, reg: AArch64GeneralReg, offset: i32) -> usize {
+        if (-(1 << 20)..(1 << 20)).contains(&offset) {
+            cbz_reg64_imm19(buf, reg, offset);
+        } else {
+            todo!("jump offsets over 20 bits for AArch64: {:#x}", offset);
+        }
+
+        // on aarch64, jumps are calculated from the start of the jmp instruction
+        buf.len() - 4
+    }
+
+    #[inline(always)]
+    fn jnz_reg64_imm32(buf: &mut Vec<'_, u8>, reg: AArch64GeneralReg, offset: i32) -> usize {
+        if (-(1 << 20)..(1 << 20)).contains(&offset) {
+            cbnz_reg64_imm19(buf, reg, offset);
+        } else {
+            todo!("jump offsets over 20 bits for AArch64: {:#x}", offset);
+        }
+
+        // on aarch64, jumps are calculated from the start of the jmp instruction
+        buf.len() - 4
+    }
+
     #[inline(always)]
     fn jne_reg64_imm64_imm32<'a, ASM, CC>(
         buf: &mut Vec<'a, u8>,
@@ -1600,6 +1701,11 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
             movk_reg64_imm16(buf, dst, remaining as u16, 3);
         }
     }
+    #[inline(always)]
+    fn movzx_reg64_imm8(buf: &mut Vec<'_, u8>, dst: AArch64GeneralReg, imm: u8) {
+        // movz zeroes every bit outside of the immediate.
+        movz_reg64_imm16(buf, dst, imm as u16, 0);
+    }
 
     #[inline(always)]
     fn mov_freg64_freg64(buf: &mut Vec<'_, u8>, dst: AArch64FloatReg, src: AArch64FloatReg) {
@@ -1715,6 +1821,19 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
     fn mov_base32_freg32(buf: &mut Vec<'_, u8>, offset: i32, src: AArch64FloatReg) {
         Self::mov_mem64_offset32_freg64(buf, AArch64GeneralReg::FP, offset, src)
     }
+
+    #[inline(always)]
+    fn mov_base32_base32_128(
+        buf: &mut Vec<'_, u8>,
+        tmp: AArch64FloatReg,
+        to_offset: i32,
+        from_offset: i32,
+    ) {
+        for half in [0, 8] {
+            Self::mov_freg64_base32(buf, tmp, from_offset + half);
+            Self::mov_base32_freg64(buf, to_offset + half, tmp);
+        }
+    }
     #[inline(always)]
     fn movesd_mem64_offset32_freg64(
         buf: &mut Vec<'_, u8>,
@@ -1725,6 +1844,17 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         Self::mov_mem64_offset32_freg64(buf, ptr, offset, src)
     }
 
+    #[inline(always)]
+    fn movnti_mem64_offset32_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        offset: i32,
+        src: AArch64GeneralReg,
+    ) {
+        // There is no single register non-temporal store, so this is a regular store.
+        Self::mov_mem64_offset32_reg64(buf, dst, offset, src)
+    }
+
     #[inline(always)]
     fn mov_base32_reg(
         buf: &mut Vec<'_, u8>,
@@ -1841,6 +1971,16 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
     ) {
         Self::mov_mem64_offset32_reg64(buf, AArch64GeneralReg::ZRSP, offset, src)
     }
+    #[inline(always)]
+    fn inc_reg64_reg64(buf: &mut Vec<'_, u8>, dst: AArch64GeneralReg, src: AArch64GeneralReg) {
+        Self::add_reg64_reg64_imm32(buf, dst, src, 1);
+    }
+
+    #[inline(always)]
+    fn dec_reg64_reg64(buf: &mut Vec<'_, u8>, dst: AArch64GeneralReg, src: AArch64GeneralReg) {
+        Self::sub_reg64_reg64_imm32(buf, dst, src, 1);
+    }
+
     #[inline(always)]
     fn neg_reg64_reg64(buf: &mut Vec<'_, u8>, dst: AArch64GeneralReg, src: AArch64GeneralReg) {
         neg_reg64_reg64(buf, dst, src);
@@ -1890,6 +2030,15 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
     ) {
         sub_reg64_reg64_reg64(buf, dst, src1, src2);
     }
+    #[inline(always)]
+    fn subs_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        src1: AArch64GeneralReg,
+        src2: AArch64GeneralReg,
+    ) {
+        subs_reg64_reg64_reg64(buf, dst, src1, src2);
+    }
 
     #[inline(always)]
     fn eq_reg_reg_reg(
@@ -1988,16 +2137,67 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         fcvt_freg64_freg32(buf, dst, src);
     }
 
+    #[inline(always)]
+    fn count_leading_zeros_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        src: AArch64GeneralReg,
+    ) {
+        clz_reg64_reg64(buf, dst, src);
+    }
+
+    #[inline(always)]
+    fn count_trailing_zeros_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        src: AArch64GeneralReg,
+    ) {
+        // The trailing zeros are the leading zeros of the reversed bits.
+        rbit_reg64_reg64(buf, dst, src);
+        clz_reg64_reg64(buf, dst, dst);
+    }
+
     #[inline(always)]
     fn set_if_overflow(buf: &mut Vec<'_, u8>, dst: AArch64GeneralReg) {
         cset_reg64_cond(buf, dst, ConditionCode::VS)
     }
 
+    #[inline(always)]
+    fn set_carry_if_nonzero_reg8(
+        buf: &mut Vec<'_, u8>,
+        tmp: AArch64GeneralReg,
+        src: AArch64GeneralReg,
+    ) {
+        movz_reg64_imm16(buf, tmp, 0xFF, 0);
+        and_reg64_reg64_reg64(buf, tmp, src, tmp);
+        // Subtracting 1 only borrows from zero, and the carry flag is set when it doesn't borrow.
+        cmp_reg64_imm12(buf, tmp, 1);
+    }
+
+    #[inline(always)]
+    fn add_carry_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        src: AArch64GeneralReg,
+    ) {
+        adcs_reg64_reg64_reg64(buf, dst, src, AArch64GeneralReg::ZRSP);
+    }
+
     #[inline(always)]
     fn ret(buf: &mut Vec<'_, u8>) {
         ret_reg64(buf, AArch64GeneralReg::LR)
     }
 
+    #[inline(always)]
+    fn unreachable(buf: &mut Vec<'_, u8>) {
+        brk_imm16(buf, 1)
+    }
+
+    #[inline(always)]
+    fn store_fence(buf: &mut Vec<'_, u8>) {
+        dmb_ishst(buf)
+    }
+
     fn and_reg64_reg64_reg64(
         buf: &mut Vec<'_, u8>,
         dst: AArch64GeneralReg,
@@ -2025,42 +2225,50 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         eor_reg64_reg64_reg64(buf, dst, src1, src2);
     }
 
-    fn shl_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        _storage_manager: &mut StorageManager<'a, '_, AArch64GeneralReg, AArch64FloatReg, ASM, CC>,
+    fn shl_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
         dst: AArch64GeneralReg,
         src1: AArch64GeneralReg,
         src2: AArch64GeneralReg,
-    ) where
-        ASM: Assembler<AArch64GeneralReg, AArch64FloatReg>,
-        CC: CallConv<AArch64GeneralReg, AArch64FloatReg, ASM>,
-    {
+    ) {
         lsl_reg64_reg64_reg64(buf, dst, src1, src2);
     }
 
-    fn shr_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        _storage_manager: &mut StorageManager<'a, '_, AArch64GeneralReg, AArch64FloatReg, ASM, CC>,
+    fn shr_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
         dst: AArch64GeneralReg,
         src1: AArch64GeneralReg,
         src2: AArch64GeneralReg,
-    ) where
-        ASM: Assembler<AArch64GeneralReg, AArch64FloatReg>,
-        CC: CallConv<AArch64GeneralReg, AArch64FloatReg, ASM>,
-    {
+    ) {
         lsr_reg64_reg64_reg64(buf, dst, src1, src2);
     }
 
-    fn sar_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        _storage_manager: &mut StorageManager<'a, '_, AArch64GeneralReg, AArch64FloatReg, ASM, CC>,
+    #[inline(always)]
+    fn shl_reg64_reg64_imm8(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        src: AArch64GeneralReg,
+        imm: u8,
+    ) {
+        lsl_reg64_reg64_imm6(buf, dst, src, imm);
+    }
+
+    #[inline(always)]
+    fn shr_reg64_reg64_imm8(
+        buf: &mut Vec<'_, u8>,
+        dst: AArch64GeneralReg,
+        src: AArch64GeneralReg,
+        imm: u8,
+    ) {
+        lsr_reg64_reg64_imm6(buf, dst, src, imm);
+    }
+
+    fn sar_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
         dst: AArch64GeneralReg,
         src1: AArch64GeneralReg,
         src2: AArch64GeneralReg,
-    ) where
-        ASM: Assembler<AArch64GeneralReg, AArch64FloatReg>,
-        CC: CallConv<AArch64GeneralReg, AArch64FloatReg, ASM>,
-    {
+    ) {
         asr_reg64_reg64_reg64(buf, dst, src1, src2);
     }
 
@@ -2108,6 +2316,22 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         cset_reg64_cond(buf, dst, cond);
     }
 
+    fn is_negative_reg64(
+        buf: &mut Vec<'_, u8>,
+        register_width: RegisterWidth,
+        dst: AArch64GeneralReg,
+        src: AArch64GeneralReg,
+    ) {
+        Self::signed_compare_reg64(
+            buf,
+            register_width,
+            CompareOperation::LessThan,
+            dst,
+            src,
+            AArch64GeneralReg::ZRSP,
+        );
+    }
+
     fn mov_freg64_mem64_offset32(
         buf: &mut Vec<'_, u8>,
         dst: AArch64FloatReg,
@@ -3236,6 +3460,20 @@ fn add_reg64_reg64_imm12(
     buf.extend(inst.bytes());
 }
 
+/// `ADCS Xd, Xn, Xm` -> Add Xn, Xm and the carry flag and place the result into Xd. Set condition flags.
+#[inline(always)]
+fn adcs_reg64_reg64_reg64(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src1: AArch64GeneralReg,
+    src2: AArch64GeneralReg,
+) {
+    let inst =
+        0xBA00_0000u32 | ((src2.id() as u32) << 16) | ((src1.id() as u32) << 5) | dst.id() as u32;
+
+    buf.extend(inst.to_le_bytes());
+}
+
 /// `ADD Xd, Xm, Xn` -> Add Xm and Xn and place the result into Xd.
 #[inline(always)]
 fn add_reg64_reg64_reg64(
@@ -3257,6 +3495,49 @@ fn add_reg64_reg64_reg64(
     buf.extend(inst.bytes());
 }
 
+/// `ADD Xd, Xn, Xm, LSL #shift` -> Add Xn and Xm shifted left by `shift`, and place it in Xd.
+#[inline(always)]
+fn add_reg64_reg64_reg64_lsl(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src1: AArch64GeneralReg,
+    src2: AArch64GeneralReg,
+    shift: u8,
+) {
+    let inst = ArithmeticShifted::new(ArithmeticShiftedParams {
+        op: false,
+        s: false,
+        shift: ShiftType::LSL,
+        imm6: shift,
+        rm: src2,
+        rn: src1,
+        rd: dst,
+    });
+
+    buf.extend(inst.bytes());
+}
+
+/// `ADDS Xd, Xm, Xn` -> Add Xm and Xn and place the result into Xd. Set condition flags.
+#[inline(always)]
+fn adds_reg64_reg64_reg64(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src1: AArch64GeneralReg,
+    src2: AArch64GeneralReg,
+) {
+    let inst = ArithmeticShifted::new(ArithmeticShiftedParams {
+        op: false,
+        s: true,
+        shift: ShiftType::LSL,
+        imm6: 0,
+        rm: src2,
+        rn: src1,
+        rd: dst,
+    });
+
+    buf.extend(inst.bytes());
+}
+
 /// `AND Xd, Xn, Xm` -> Bitwise AND Xn and Xm and place the result into Xd.
 #[inline(always)]
 fn and_reg64_reg64_reg64(
@@ -3321,6 +3602,35 @@ fn b_cond_imm19(buf: &mut Vec<'_, u8>, cond: ConditionCode, imm19: i32) {
     buf.extend(inst.bytes());
 }
 
+/// `CBZ Xt, imm19` -> Jump to PC + imm19 if Xt is zero.
+#[inline(always)]
+fn cbz_reg64_imm19(buf: &mut Vec<'_, u8>, reg: AArch64GeneralReg, imm19: i32) {
+    compare_and_branch_reg64_imm19(buf, 0xB400_0000, reg, imm19);
+}
+
+/// `CBNZ Xt, imm19` -> Jump to PC + imm19 if Xt is not zero.
+#[inline(always)]
+fn cbnz_reg64_imm19(buf: &mut Vec<'_, u8>, reg: AArch64GeneralReg, imm19: i32) {
+    compare_and_branch_reg64_imm19(buf, 0xB500_0000, reg, imm19);
+}
+
+#[inline(always)]
+fn compare_and_branch_reg64_imm19(
+    buf: &mut Vec<'_, u8>,
+    opcode: u32,
+    reg: AArch64GeneralReg,
+    imm19: i32,
+) {
+    // Since instructions are 4 bytes, the branch instructions assume the last 2 bits are 0
+    debug_assert!(imm19 & 0b11 == 0, "branch location must be 4-byte aligned");
+    debug_assert!((-(1 << 20)..(1 << 20)).contains(&imm19));
+    let offset = ((imm19 >> 2) as u32) & 0x7_FFFF;
+
+    let inst = opcode | (offset << 5) | reg.id() as u32;
+
+    buf.extend(inst.to_le_bytes());
+}
+
 /// `B imm26` -> Jump to PC + imm26.
 #[inline(always)]
 fn b_imm26(buf: &mut Vec<'_, u8>, imm26: i32) {
@@ -3347,6 +3657,26 @@ fn b_imm26(buf: &mut Vec<'_, u8>, imm26: i32) {
     buf.extend(inst.bytes());
 }
 
+/// `BRK imm16` -> Raise a breakpoint exception with the given immediate.
+#[inline(always)]
+fn brk_imm16(buf: &mut Vec<'_, u8>, imm16: u16) {
+    buf.extend((0xD420_0000u32 | ((imm16 as u32) << 5)).to_le_bytes());
+}
+
+/// `CLZ Xd, Xn` -> Count the leading zero bits of Xn, which is 64 if Xn is zero.
+#[inline(always)]
+fn clz_reg64_reg64(buf: &mut Vec<'_, u8>, dst: AArch64GeneralReg, src: AArch64GeneralReg) {
+    let inst = 0xDAC0_1000u32 | ((src.id() as u32) << 5) | dst.id() as u32;
+
+    buf.extend(inst.to_le_bytes());
+}
+
+/// `DMB ISHST` -> Data memory barrier ordering stores within the inner shareable domain.
+#[inline(always)]
+fn dmb_ishst(buf: &mut Vec<'_, u8>) {
+    buf.extend(0xD503_3ABFu32.to_le_bytes());
+}
+
 /// `CMP Xn, imm12` -> Compare Xn and imm12, setting condition flags.
 #[inline(always)]
 fn cmp_reg64_imm12(buf: &mut Vec<'_, u8>, src: AArch64GeneralReg, imm12: u16) {
@@ -3581,6 +3911,45 @@ fn lsr_reg64_reg64_reg64(
     buf.extend(inst.bytes());
 }
 
+/// `LSL Xd, Xn, #shift` -> Logical shift Xn left by `shift` and place the result into Xd.
+/// This is an alias of `UBFM Xd, Xn, #(-shift MOD 64), #(63 - shift)`.
+#[inline(always)]
+fn lsl_reg64_reg64_imm6(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src: AArch64GeneralReg,
+    shift: u8,
+) {
+    debug_assert!(shift < 64);
+    let immr = (64 - shift as u32) % 64;
+    let imms = 63 - shift as u32;
+    let inst = 0b1101_0011_0100_0000_0000_0000_0000_0000u32
+        | (immr << 16)
+        | (imms << 10)
+        | ((src.id() as u32) << 5)
+        | dst.id() as u32;
+
+    buf.extend(inst.to_le_bytes());
+}
+
+/// `LSR Xd, Xn, #shift` -> Logical shift Xn right by `shift` and place the result into Xd.
+/// This is an alias of `UBFM Xd, Xn, #shift, #63`.
+#[inline(always)]
+fn lsr_reg64_reg64_imm6(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src: AArch64GeneralReg,
+    shift: u8,
+) {
+    debug_assert!(shift < 64);
+    let inst = 0b1101_0011_0100_0000_1111_1100_0000_0000u32
+        | ((shift as u32) << 16)
+        | ((src.id() as u32) << 5)
+        | dst.id() as u32;
+
+    buf.extend(inst.to_le_bytes());
+}
+
 /// `MADD Xd, Xn, Xm, Xa` -> Multiply Xn and Xm, add Xa, and place the result into Xd.
 #[inline(always)]
 fn madd_reg64_reg64_reg64_reg64(
@@ -3881,6 +4250,14 @@ fn subs_reg64_reg64_reg64_with_shift(
     buf.extend(inst.bytes());
 }
 
+/// `RBIT Xd, Xn` -> Reverse the bit order of Xn.
+#[inline(always)]
+fn rbit_reg64_reg64(buf: &mut Vec<'_, u8>, dst: AArch64GeneralReg, src: AArch64GeneralReg) {
+    let inst = 0xDAC0_0000u32 | ((src.id() as u32) << 5) | dst.id() as u32;
+
+    buf.extend(inst.to_le_bytes());
+}
+
 /// `RET Xn` -> Return to the address stored in Xn.
 #[inline(always)]
 fn ret_reg64(buf: &mut Vec<'_, u8>, xn: AArch64GeneralReg) {
@@ -4531,6 +4908,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_reg64_reg64_reg64_lsl() {
+        disassembler_test!(
+            add_reg64_reg64_reg64_lsl,
+            |reg1: AArch64GeneralReg,
+             reg2: AArch64GeneralReg,
+             reg3: AArch64GeneralReg,
+             shift: u8| format!(
+                "add {}, {}, {}, lsl #{}",
+                reg1.capstone_string(UsesZR),
+                reg2.capstone_string(UsesZR),
+                reg3.capstone_string(UsesZR),
+                shift
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            [1u8, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_adcs_reg64_reg64_reg64() {
+        disassembler_test!(
+            adcs_reg64_reg64_reg64,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, reg3: AArch64GeneralReg| format!(
+                "adcs {}, {}, {}",
+                reg1.capstone_string(UsesZR),
+                reg2.capstone_string(UsesZR),
+                reg3.capstone_string(UsesZR)
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
+    #[test]
+    fn test_adds_reg64_reg64_reg64() {
+        disassembler_test!(
+            adds_reg64_reg64_reg64,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, reg3: AArch64GeneralReg| {
+                if reg1 == AArch64GeneralReg::ZRSP {
+                    // When the first register is ZR, it gets disassembled as cmn,
+                    // which is an alias for adds.
+                    format!(
+                        "cmn {}, {}",
+                        reg2.capstone_string(UsesZR),
+                        reg3.capstone_string(UsesZR)
+                    )
+                } else {
+                    format!(
+                        "adds {}, {}, {}",
+                        reg1.capstone_string(UsesZR),
+                        reg2.capstone_string(UsesZR),
+                        reg3.capstone_string(UsesZR)
+                    )
+                }
+            },
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
     #[test]
     fn test_add_reg64_reg64_imm12() {
         disassembler_test!(
@@ -4598,6 +5040,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cbz_reg64_imm19() {
+        disassembler_test!(
+            cbz_reg64_imm19,
+            |reg: AArch64GeneralReg, imm: i32| format!(
+                "cbz {}, #0x{:x}",
+                reg.capstone_string(UsesZR),
+                imm as i64
+            ),
+            ALL_GENERAL_REGS,
+            [0x120, -0x120, (1 << 20) - 4, -(1 << 20)]
+        );
+    }
+
+    #[test]
+    fn test_cbnz_reg64_imm19() {
+        disassembler_test!(
+            cbnz_reg64_imm19,
+            |reg: AArch64GeneralReg, imm: i32| format!(
+                "cbnz {}, #0x{:x}",
+                reg.capstone_string(UsesZR),
+                imm as i64
+            ),
+            ALL_GENERAL_REGS,
+            [0x120, -0x120, (1 << 20) - 4, -(1 << 20)]
+        );
+    }
+
+    #[test]
+    fn test_brk_imm16() {
+        disassembler_test!(brk_imm16, |imm| format!("brk #0x{imm:x}"), [0x1, 0x3e8]);
+    }
+
+    #[test]
+    fn test_clz_reg64_reg64() {
+        disassembler_test!(
+            clz_reg64_reg64,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg| format!(
+                "clz {}, {}",
+                reg1.capstone_string(UsesZR),
+                reg2.capstone_string(UsesZR)
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
+    #[test]
+    fn test_dmb_ishst() {
+        disassembler_test!(dmb_ishst, || "dmb ishst");
+    }
+
     #[test]
     fn test_cmp_reg64_imm12() {
         disassembler_test!(
@@ -4861,6 +5355,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lsl_reg64_reg64_imm6() {
+        disassembler_test!(
+            lsl_reg64_reg64_imm6,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, shift: u8| format!(
+                "lsl {}, {}, #{}",
+                reg1.capstone_string(UsesZR),
+                reg2.capstone_string(UsesZR),
+                shift
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            [1u8, 8, 56]
+        );
+    }
+
+    #[test]
+    fn test_lsr_reg64_reg64_imm6() {
+        disassembler_test!(
+            lsr_reg64_reg64_imm6,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, shift: u8| format!(
+                "lsr {}, {}, #{}",
+                reg1.capstone_string(UsesZR),
+                reg2.capstone_string(UsesZR),
+                shift
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            [1u8, 8]
+        );
+    }
+
     #[test]
     fn test_madd_reg64_reg64_reg64_reg64() {
         disassembler_test!(
@@ -4999,6 +5525,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rbit_reg64_reg64() {
+        disassembler_test!(
+            rbit_reg64_reg64,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg| format!(
+                "rbit {}, {}",
+                reg1.capstone_string(UsesZR),
+                reg2.capstone_string(UsesZR)
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
     #[test]
     fn test_sdiv_reg64_reg64_reg64() {
         disassembler_test!(
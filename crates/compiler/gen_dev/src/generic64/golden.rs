@@ -0,0 +1,412 @@
+//! Golden-file tests for the bytes `finalize` emits for small IR programs.
+//!
+//! Each program's output is stored as hex in `golden/<arch>/<name>.hex`.
+//! When the output legitimately changes, regenerate the files with:
+//!
+//! ```sh
+//! ROC_UPDATE_GOLDEN_FILES=1 cargo test -p roc_gen_dev golden
+//! ```
+//!
+//! Then review the diff of the fixtures before committing them.
+
+use super::x86_64::{X86_64Assembler, X86_64FloatReg, X86_64GeneralReg, X86_64SystemV};
+use super::{new_backend_64bit, Assembler, Backend64Bit, CallConv, RegTrait};
+use crate::{AssemblyBackendMode, Backend, DevOptions, Env, Relocation, StackProtector};
+use bumpalo::Bump;
+use roc_collections::all::MutSet;
+use roc_module::low_level::LowLevel;
+use roc_module::symbol::{Interns, ModuleId, Symbol};
+use roc_mono::ir::{Call, CallType, Expr, Literal, SelfRecursive, Stmt, UpdateModeId};
+use roc_mono::layout::{InLayout, Layout, LayoutIds, STLayoutInterner};
+use roc_target::Target;
+use std::path::PathBuf;
+
+const UPDATE_VAR: &str = "ROC_UPDATE_GOLDEN_FILES";
+
+/// Bytes per line in a golden file, to keep diffs readable.
+const BYTES_PER_LINE: usize = 16;
+
+/// A small procedure to build: its arguments, body, and return layout.
+struct Program<'a> {
+    args: &'a [(InLayout<'a>, Symbol)],
+    body: &'a Stmt<'a>,
+    ret_layout: InLayout<'a>,
+}
+
+fn test_env(arena: &Bump) -> Env<'_> {
+    Env {
+        arena,
+        module_id: ModuleId::ATTR,
+        exposed_to_host: MutSet::default(),
+        lazy_literals: true,
+        mode: AssemblyBackendMode::Test,
+        options: DevOptions {
+            trap_unreachable: true,
+            ..DevOptions::default()
+        },
+    }
+}
+
+/// Builds `program` like `build_proc` does and returns the output of `finalize`.
+fn finalize_program<
+    'a,
+    GeneralReg: RegTrait,
+    FloatReg: RegTrait,
+    ASM: Assembler<GeneralReg, FloatReg>,
+    CC: CallConv<GeneralReg, FloatReg, ASM>,
+>(
+    env: &Env<'a>,
+    target: Target,
+    program: &Program<'a>,
+) -> std::vec::Vec<u8> {
+    build_program::<GeneralReg, FloatReg, ASM, CC, _>(env, target, program, |backend| {
+        let (out, _relocs) = backend.finalize();
+        out.to_vec()
+    })
+}
+
+/// Builds `program` like `build_proc` does and hands the backend to `finish` to get the output.
+fn build_program<
+    'a,
+    GeneralReg: RegTrait,
+    FloatReg: RegTrait,
+    ASM: Assembler<GeneralReg, FloatReg>,
+    CC: CallConv<GeneralReg, FloatReg, ASM>,
+    T,
+>(
+    env: &Env<'a>,
+    target: Target,
+    program: &Program<'a>,
+    finish: impl FnOnce(&mut Backend64Bit<'a, '_, GeneralReg, FloatReg, ASM, CC>) -> T,
+) -> T {
+    let mut interns = Interns::default();
+    interns.all_ident_ids.get_or_insert(env.module_id);
+    let mut layout_interner = STLayoutInterner::with_capacity(4, target);
+
+    let mut backend = new_backend_64bit::<GeneralReg, FloatReg, ASM, CC>(
+        env,
+        target,
+        &mut interns,
+        &mut layout_interner,
+    );
+    backend.reset("golden".to_string(), SelfRecursive::NotSelfRecursive);
+    backend.claim_canary_slot(program.args, program.body);
+    backend.load_args(program.args, &program.ret_layout);
+    for (layout, sym) in program.args {
+        backend.set_layout_map(*sym, layout);
+    }
+    backend.scan_ast(program.body);
+    backend.build_stmt(&mut LayoutIds::default(), program.body, &program.ret_layout);
+
+    finish(&mut backend)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for line in bytes.chunks(BYTES_PER_LINE) {
+        let line: std::vec::Vec<_> = line.iter().map(|byte| format!("{byte:02x}")).collect();
+        out.push_str(&line.join(" "));
+        out.push('\n');
+    }
+    out
+}
+
+/// Compares `bytes` against the golden file `golden/<arch>/<name>.hex`,
+/// or overwrites the file if `ROC_UPDATE_GOLDEN_FILES` is set.
+fn assert_golden(arch: &str, name: &str, bytes: &[u8]) {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "golden",
+        arch,
+        &format!("{name}.hex"),
+    ]
+    .iter()
+    .collect();
+    let actual = to_hex(bytes);
+
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "Could not read the golden file {path:?}: {err}. Run with {UPDATE_VAR}=1 to create it."
+        )
+    });
+    assert_eq!(
+        actual, expected,
+        "The output does not match the golden file {path:?}. \
+        If the change is intended, run with {UPDATE_VAR}=1 to update it."
+    );
+}
+
+/// Disassembles `bytes` and returns how many instructions they hold, or an error if they do not
+/// decode into whole instructions from start to end. This does not notice an instruction that
+/// was encoded wrong but still decodes, but it does catch garbage.
+fn x86_64_decode(bytes: &[u8]) -> Result<usize, String> {
+    use capstone::prelude::*;
+
+    let cs = Capstone::new()
+        .x86()
+        .mode(arch::x86::ArchMode::Mode64)
+        .syntax(arch::x86::ArchSyntax::Intel)
+        .build()
+        .expect("Failed to create Capstone object");
+
+    let instructions = cs.disasm_all(bytes, 0).map_err(|err| err.to_string())?;
+    let decoded: usize = instructions.iter().map(|inst| inst.bytes().len()).sum();
+    if decoded == bytes.len() {
+        Ok(instructions.len())
+    } else {
+        Err(format!(
+            "The bytes at offset {decoded:#x} do not decode as an instruction"
+        ))
+    }
+}
+
+fn assert_x86_64_decodes(bytes: &[u8]) {
+    if let Err(err) = x86_64_decode(bytes) {
+        panic!("{err}:\n{}", to_hex(bytes));
+    }
+}
+
+fn finalize_x86_64<'a>(env: &Env<'a>, program: &Program<'a>) -> std::vec::Vec<u8> {
+    let finalize =
+        finalize_program::<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler, X86_64SystemV>;
+    finalize(env, Target::LinuxX64, program)
+}
+
+fn assert_x86_64_golden<'a>(env: &Env<'a>, name: &str, program: &Program<'a>) {
+    let bytes = finalize_x86_64(env, program);
+    assert_x86_64_decodes(&bytes);
+    assert_golden("x86_64", name, &bytes);
+}
+
+fn low_level<'a>(arena: &'a Bump, op: LowLevel, arguments: &[Symbol]) -> Expr<'a> {
+    Expr::Call(Call {
+        call_type: CallType::LowLevel {
+            op,
+            update_mode: UpdateModeId::BACKEND_DUMMY,
+        },
+        arguments: arena.alloc_slice_copy(arguments),
+    })
+}
+
+#[test]
+fn return_arg() {
+    let arena = Bump::new();
+    let env = test_env(&arena);
+    let a = Symbol::DEV_TMP;
+
+    assert_x86_64_golden(
+        &env,
+        "return_arg",
+        &Program {
+            args: arena.alloc([(Layout::I64, a)]),
+            body: arena.alloc(Stmt::Ret(a)),
+            ret_layout: Layout::I64,
+        },
+    );
+}
+
+#[test]
+fn return_literal() {
+    let arena = Bump::new();
+    let env = test_env(&arena);
+    let x = Symbol::DEV_TMP;
+
+    assert_x86_64_golden(
+        &env,
+        "return_literal",
+        &Program {
+            args: &[],
+            body: arena.alloc(Stmt::Let(
+                x,
+                Expr::Literal(Literal::Int(42i128.to_ne_bytes())),
+                Layout::I64,
+                arena.alloc(Stmt::Ret(x)),
+            )),
+            ret_layout: Layout::I64,
+        },
+    );
+}
+
+#[test]
+fn profiled_return_arg() {
+    let arena = Bump::new();
+    let mut env = test_env(&arena);
+    env.options.profiling = true;
+    let a = Symbol::DEV_TMP;
+
+    // `return_arg`, after a `call __fentry__` whose displacement is left to the linker.
+    assert_x86_64_golden(
+        &env,
+        "profiled_return_arg",
+        &Program {
+            args: arena.alloc([(Layout::I64, a)]),
+            body: arena.alloc(Stmt::Ret(a)),
+            ret_layout: Layout::I64,
+        },
+    );
+}
+
+#[test]
+fn profiled_return_arg_into_a_caller_buffer() {
+    let arena = Bump::new();
+    let mut env = test_env(&arena);
+    env.options.profiling = true;
+
+    let build =
+        build_program::<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler, X86_64SystemV, _>;
+    let (out, relocs) = build(
+        &env,
+        Target::LinuxX64,
+        &return_arg_program(&arena),
+        |backend| {
+            let mut out: std::vec::Vec<u8> = vec![0x90, 0x90];
+            let relocs = backend.finalize_into(&mut out);
+            (out, relocs)
+        },
+    );
+
+    // Code already in the buffer is kept, and the relocation is from the start of the proc.
+    assert_eq!(&out[..2], [0x90, 0x90]);
+    assert_golden("x86_64", "profiled_return_arg", &out[2..]);
+    match &relocs[..] {
+        [Relocation::LinkedFunction { offset: 1, name }] => assert_eq!(name, "__fentry__"),
+        other => panic!("expected the call to __fentry__, got {other:?}"),
+    }
+}
+
+fn return_arg_program(arena: &Bump) -> Program<'_> {
+    let a = Symbol::DEV_TMP;
+
+    Program {
+        args: arena.alloc([(Layout::I64, a)]),
+        body: arena.alloc(Stmt::Ret(a)),
+        ret_layout: Layout::I64,
+    }
+}
+
+#[test]
+fn protected_return_arg() {
+    let arena = Bump::new();
+    let mut env = test_env(&arena);
+    env.options.stack_protector = StackProtector::All;
+
+    // The prologue copies the guard at fs:0x28 to [rbp - 8]. On the way out it is xored with
+    // the guard again, and `__stack_chk_fail` is only called if they differ.
+    assert_x86_64_golden(&env, "protected_return_arg", &return_arg_program(&arena));
+}
+
+#[test]
+fn buffer_stack_protector_leaves_procs_without_buffers_alone() {
+    let arena = Bump::new();
+    let mut env = test_env(&arena);
+    env.options.stack_protector = StackProtector::Buffers;
+
+    assert_x86_64_golden(&env, "return_arg", &return_arg_program(&arena));
+}
+
+#[test]
+fn stack_protector_is_ignored_off_linux() {
+    let arena = Bump::new();
+    let mut env = test_env(&arena);
+    env.options.stack_protector = StackProtector::All;
+
+    let finalize =
+        finalize_program::<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler, X86_64SystemV>;
+    let bytes = finalize(&env, Target::MacX64, &return_arg_program(&arena));
+    assert_golden("x86_64", "return_arg", &bytes);
+}
+
+fn bitwise_and_program(arena: &Bump) -> Program<'_> {
+    let a = Symbol::DEV_TMP;
+    let b = Symbol::DEV_TMP2;
+    let c = Symbol::DEV_TMP3;
+
+    Program {
+        args: arena.alloc([(Layout::I64, a), (Layout::I64, b)]),
+        body: arena.alloc(Stmt::Let(
+            c,
+            low_level(arena, LowLevel::NumBitwiseAnd, &[a, b]),
+            Layout::I64,
+            arena.alloc(Stmt::Ret(c)),
+        )),
+        ret_layout: Layout::I64,
+    }
+}
+
+#[test]
+fn bitwise_and() {
+    let arena = Bump::new();
+    let env = test_env(&arena);
+
+    assert_x86_64_golden(&env, "bitwise_and", &bitwise_and_program(&arena));
+}
+
+#[test]
+fn finalize_output_decodes_fully() {
+    let arena = Bump::new();
+    let env = test_env(&arena);
+
+    let bytes = finalize_x86_64(&env, &bitwise_and_program(&arena));
+
+    // push, mov, mov, and, mov, pop, ret, ud2
+    assert_eq!(x86_64_decode(&bytes), Ok(8));
+}
+
+#[test]
+fn corrupted_output_does_not_decode() {
+    let arena = Bump::new();
+    let env = test_env(&arena);
+    let bytes = finalize_x86_64(&env, &bitwise_and_program(&arena));
+
+    // 0x06 (`push es`) is not a valid instruction in 64-bit mode.
+    let mut invalid = bytes.clone();
+    invalid.insert(4, 0x06);
+    assert!(x86_64_decode(&invalid).is_err());
+
+    // An instruction cut off by the end of the output: the `mov rbp, rsp` after `push rbp`.
+    assert!(x86_64_decode(&bytes[..3]).is_err());
+}
+
+fn int_to_f64_program(arena: &Bump) -> Program<'_> {
+    let a = Symbol::DEV_TMP;
+    let x = Symbol::DEV_TMP2;
+
+    Program {
+        args: arena.alloc([(Layout::I64, a)]),
+        body: arena.alloc(Stmt::Let(
+            x,
+            low_level(arena, LowLevel::NumToFrac, &[a]),
+            Layout::F64,
+            arena.alloc(Stmt::Ret(x)),
+        )),
+        ret_layout: Layout::F64,
+    }
+}
+
+#[test]
+fn int_to_f64() {
+    let arena = Bump::new();
+    let env = test_env(&arena);
+
+    assert_x86_64_golden(&env, "int_to_f64", &int_to_f64_program(&arena));
+}
+
+#[test]
+fn int_to_f64_breaking_false_dependency() {
+    let arena = Bump::new();
+    let mut env = test_env(&arena);
+    env.options.break_false_dependencies = true;
+
+    // `int_to_f64`, with an `xorps xmm0, xmm0` before the `cvtsi2sd xmm0, rdi`.
+    assert_x86_64_golden(
+        &env,
+        "int_to_f64_breaking_false_dependency",
+        &int_to_f64_program(&arena),
+    );
+}
@@ -1,16 +1,19 @@
 use crate::{
-    pointer_layouts, single_register_floats, single_register_int_builtins,
-    single_register_integers, Backend, Env, Relocation,
+    pointer_layouts, sign_extended_int_builtins, single_register_floats,
+    single_register_int_builtins, single_register_integers, single_register_layouts, Backend,
+    CodeSink, Env, Feature, OverflowMode, Relocation, StackProtector, UsePositions, PROFILING_HOOK,
+    STACK_CHK_FAIL,
 };
 use bumpalo::collections::{CollectIn, Vec};
 use roc_builtins::bitcode::{self, FloatWidth, IntWidth};
-use roc_collections::all::MutMap;
-use roc_error_macros::{internal_error, todo_lambda_erasure};
+use roc_collections::all::{MutMap, MutSet};
+use roc_error_macros::{internal_error, todo_lambda_erasure, user_error};
+use roc_module::low_level::LowLevel;
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_mono::code_gen_help::{CallerProc, CodeGenHelp, HelperOp};
 use roc_mono::ir::{
-    BranchInfo, HigherOrderLowLevel, JoinPointId, ListLiteralElement, Literal, Param, ProcLayout,
-    SelfRecursive, Stmt,
+    BranchInfo, CrashTag, HigherOrderLowLevel, JoinPointId, ListLiteralElement, Literal, Param,
+    ProcLayout, SelfRecursive, Stmt,
 };
 use roc_mono::layout::{
     Builtin, InLayout, LambdaName, Layout, LayoutIds, LayoutInterner, LayoutRepr, STLayoutInterner,
@@ -23,6 +26,8 @@ use std::marker::PhantomData;
 pub(crate) mod aarch64;
 #[cfg(test)]
 mod disassembler_test_macro;
+#[cfg(test)]
+mod golden;
 pub(crate) mod storage;
 pub(crate) mod x86_64;
 
@@ -56,6 +61,9 @@ impl RegisterWidth {
 pub trait CallConv<GeneralReg: RegTrait, FloatReg: RegTrait, ASM: Assembler<GeneralReg, FloatReg>>:
     Sized + Copy
 {
+    /// The name of the calling convention, for diagnostics.
+    const NAME: &'static str;
+
     const BASE_PTR_REG: GeneralReg;
     const STACK_PTR_REG: GeneralReg;
 
@@ -67,8 +75,20 @@ pub trait CallConv<GeneralReg: RegTrait, FloatReg: RegTrait, ASM: Assembler<Gene
     const FLOAT_RETURN_REGS: &'static [FloatReg];
     const FLOAT_DEFAULT_FREE_REGS: &'static [FloatReg];
 
+    /// Space the caller reserves at the bottom of the stack for the callee to spill its register args.
+    /// It is part of the call area of the frame, so it is allocated once in the prologue
+    /// and shared by every call in the function.
     const SHADOW_SPACE_SIZE: u8;
 
+    /// Space below the stack pointer that signal and interrupt handlers leave alone.
+    /// A leaf function whose frame fits in it can use it without moving the stack pointer.
+    const RED_ZONE_SIZE: u8;
+
+    /// Whether the callee pops the arguments the caller passed on the stack when it returns
+    /// (stdcall), instead of leaving that to the caller. A convention that sets this also
+    /// overrides `emit_return` to do the popping.
+    const CALLEE_POPS_STACK_ARGS: bool = false;
+
     fn general_callee_saved(reg: &GeneralReg) -> bool;
     #[inline(always)]
     fn general_caller_saved(reg: &GeneralReg) -> bool {
@@ -80,12 +100,15 @@ pub trait CallConv<GeneralReg: RegTrait, FloatReg: RegTrait, ASM: Assembler<Gene
         !Self::float_callee_saved(reg)
     }
 
+    /// Sets up the frame, returning its size. Without `requires_stack_modification` the frame
+    /// is laid out in the red zone and the stack pointer is left where it is.
     fn setup_stack(
         buf: &mut Vec<'_, u8>,
         saved_general_regs: &[GeneralReg],
         saved_float_regs: &[FloatReg],
         requested_stack_size: i32,
         fn_call_stack_size: i32,
+        requires_stack_modification: bool,
     ) -> i32;
     fn cleanup_stack(
         buf: &mut Vec<'_, u8>,
@@ -93,8 +116,72 @@ pub trait CallConv<GeneralReg: RegTrait, FloatReg: RegTrait, ASM: Assembler<Gene
         float_saved_regs: &[FloatReg],
         aligned_stack_size: i32,
         fn_call_stack_size: i32,
+        requires_stack_modification: bool,
     );
 
+    /// Writes the entry sequence of a proc and returns the size of its frame.
+    /// By default this is just `setup_stack`. A convention that needs more on entry overrides it.
+    fn emit_prologue(
+        buf: &mut Vec<'_, u8>,
+        saved_general_regs: &[GeneralReg],
+        saved_float_regs: &[FloatReg],
+        requested_stack_size: i32,
+        fn_call_stack_size: i32,
+        requires_stack_modification: bool,
+    ) -> i32 {
+        Self::setup_stack(
+            buf,
+            saved_general_regs,
+            saved_float_regs,
+            requested_stack_size,
+            fn_call_stack_size,
+            requires_stack_modification,
+        )
+    }
+
+    /// Writes the exit sequence that undoes `emit_prologue`, up to and including the return.
+    /// `stack_args_size` is how much the caller passed on the stack.
+    fn emit_epilogue(
+        buf: &mut Vec<'_, u8>,
+        saved_general_regs: &[GeneralReg],
+        saved_float_regs: &[FloatReg],
+        aligned_stack_size: i32,
+        fn_call_stack_size: i32,
+        requires_stack_modification: bool,
+        stack_args_size: u32,
+    ) {
+        Self::cleanup_stack(
+            buf,
+            saved_general_regs,
+            saved_float_regs,
+            aligned_stack_size,
+            fn_call_stack_size,
+            requires_stack_modification,
+        );
+        Self::emit_return(buf, stack_args_size);
+    }
+
+    /// Writes the return at the end of `emit_epilogue`. `stack_args_size` is how much the caller
+    /// passed on the stack, for conventions that set `CALLEE_POPS_STACK_ARGS`.
+    fn emit_return(buf: &mut Vec<'_, u8>, _stack_args_size: u32) {
+        ASM::ret(buf);
+    }
+
+    /// The unwind info for the frame `setup_stack` set up, for conventions whose unwinder reads
+    /// it from a table of its own instead of from the code (e.g. `.xdata` on Windows).
+    /// `prologue` is everything the proc runs before its body, and the frame is set up starting
+    /// at `frame_start`, after anything that comes first, like the profiling hook.
+    fn unwind_info(
+        _prologue: &[u8],
+        _frame_start: usize,
+        _saved_general_regs: &[GeneralReg],
+        _saved_float_regs: &[FloatReg],
+        _aligned_stack_size: i32,
+        _fn_call_stack_size: i32,
+    ) -> Option<std::vec::Vec<u8>> {
+        None
+    }
+
     /// load_args updates the storage manager to know where every arg is stored.
     fn load_args<'a>(
         buf: &mut Vec<'a, u8>,
@@ -138,9 +225,21 @@ pub trait CallConv<GeneralReg: RegTrait, FloatReg: RegTrait, ASM: Assembler<Gene
         layout: &InLayout<'a>,
     );
 
+    /// Whether part of a struct returned in registers comes back in the float return registers.
+    /// If so, its fields can't be read from the general return registers by position.
+    fn returns_floats_in_float_regs<'a>(
+        _layout_interner: &STLayoutInterner<'a>,
+        _layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
     fn setjmp(buf: &mut Vec<'_, u8>);
     fn longjmp(buf: &mut Vec<'_, u8>);
     fn roc_panic(buf: &mut Vec<'_, u8>, relocs: &mut Vec<'_, Relocation>);
+
+    /// A `_start` that calls `main` with argc and argv and exits with what it returns.
+    fn entry_shim(buf: &mut Vec<'_, u8>, relocs: &mut Vec<'_, Relocation>, main: String);
 }
 
 pub enum CompareOperation {
@@ -157,6 +256,12 @@ pub enum CompareOperation {
 /// Generally, I prefer explicit sources, as opposed to dst being one of the sources. Ex: `x = x + y` would be `add x, x, y` instead of `add x, y`.
 /// dst should always come before sources.
 pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
+    /// The register a variable shift count has to be in, for architectures that fix one.
+    const SHIFT_COUNT_REG: Option<GeneralReg>;
+
+    /// How many bytes `jmp_imm32` emits.
+    const JMP_IMM32_SIZE: u64;
+
     fn abs_reg64_reg64(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: GeneralReg);
     fn abs_freg64_freg64(
         buf: &mut Vec<'_, u8>,
@@ -172,12 +277,22 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
     );
 
     fn add_reg64_reg64_imm32(buf: &mut Vec<'_, u8>, dst: GeneralReg, src1: GeneralReg, imm32: i32);
+    /// dst = src + 1. On x86 this is `inc`, which is shorter than `add` but leaves the carry flag
+    /// as it was, so nothing may read the carry out of it.
+    fn inc_reg64_reg64(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: GeneralReg);
     fn add_reg64_reg64_reg64(
         buf: &mut Vec<'_, u8>,
         dst: GeneralReg,
         src1: GeneralReg,
         src2: GeneralReg,
     );
+    /// Like `add_reg64_reg64_reg64`, but guaranteed to set the overflow flag for a signed result.
+    fn adds_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: GeneralReg,
+        src1: GeneralReg,
+        src2: GeneralReg,
+    );
 
     fn add_freg32_freg32_freg32(
         buf: &mut Vec<'_, u8>,
@@ -213,35 +328,35 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
         src2: GeneralReg,
     );
 
-    fn shl_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        storage_manager: &mut StorageManager<'a, '_, GeneralReg, FloatReg, ASM, CC>,
+    /// The shift count `src2` must be in `SHIFT_COUNT_REG`, if the architecture has one.
+    fn shl_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
         dst: GeneralReg,
         src1: GeneralReg,
         src2: GeneralReg,
-    ) where
-        ASM: Assembler<GeneralReg, FloatReg>,
-        CC: CallConv<GeneralReg, FloatReg, ASM>;
+    );
 
-    fn shr_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        storage_manager: &mut StorageManager<'a, '_, GeneralReg, FloatReg, ASM, CC>,
+    /// The shift count `src2` must be in `SHIFT_COUNT_REG`, if the architecture has one.
+    fn shr_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
         dst: GeneralReg,
         src1: GeneralReg,
         src2: GeneralReg,
-    ) where
-        ASM: Assembler<GeneralReg, FloatReg>,
-        CC: CallConv<GeneralReg, FloatReg, ASM>;
+    );
 
-    fn sar_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        storage_manager: &mut StorageManager<'a, '_, GeneralReg, FloatReg, ASM, CC>,
+    /// The shift count `src2` must be in `SHIFT_COUNT_REG`, if the architecture has one.
+    fn sar_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
         dst: GeneralReg,
         src1: GeneralReg,
         src2: GeneralReg,
-    ) where
-        ASM: Assembler<GeneralReg, FloatReg>,
-        CC: CallConv<GeneralReg, FloatReg, ASM>;
+    );
+
+    /// Shifts `src` left by the constant `imm` bits, which must be less than 64.
+    fn shl_reg64_reg64_imm8(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: GeneralReg, imm: u8);
+
+    /// Logically shifts `src` right by the constant `imm` bits, which must be less than 64.
+    fn shr_reg64_reg64_imm8(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: GeneralReg, imm: u8);
 
     fn call(buf: &mut Vec<'_, u8>, relocs: &mut Vec<'_, Relocation>, fn_name: String);
 
@@ -252,18 +367,27 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
         dst: GeneralReg,
     );
 
+    /// Loads the address of `fn_name` plus `addend` into `dst`.
     fn data_pointer(
         buf: &mut Vec<'_, u8>,
         relocs: &mut Vec<'_, Relocation>,
         fn_name: String,
+        addend: i64,
         dst: GeneralReg,
     );
 
+    /// Loads the stack-protector guard value that the C runtime keeps in thread-local storage.
+    fn load_stack_guard(buf: &mut Vec<'_, u8>, dst: GeneralReg);
+
     /// Jumps by an offset of offset bytes unconditionally.
     /// It should always generate the same number of bytes to enable replacement if offset changes.
     /// It returns the base offset to calculate the jump from (generally the instruction after the jump).
     fn jmp_imm32(buf: &mut Vec<'_, u8>, offset: i32) -> usize;
 
+    /// Jumps unconditionally to `target`, an offset in `buf` that has already been emitted.
+    /// The distance is known, so this uses the shortest encoding that reaches it.
+    fn jmp_to_offset(buf: &mut Vec<'_, u8>, target: u64);
+
     /// Updates a jump instruction to a new offset and returns the number of bytes written.
     fn update_jmp_imm32_offset(
         buf: &mut Vec<'_, u8>,
@@ -286,6 +410,26 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
 
     fn tail_call(buf: &mut Vec<'_, u8>) -> u64;
 
+    /// Jumps by an offset of offset bytes if the overflow flag is set.
+    /// It should always generate the same number of bytes to enable replacement if offset changes.
+    /// It returns the base offset to calculate the jump from (generally the instruction after the jump).
+    fn jo_imm32(buf: &mut Vec<'_, u8>, offset: i32) -> usize;
+
+    /// Jumps by an offset of offset bytes if the overflow flag is not set.
+    /// It should always generate the same number of bytes to enable replacement if offset changes.
+    /// It returns the base offset to calculate the jump from (generally the instruction after the jump).
+    fn jno_imm32(buf: &mut Vec<'_, u8>, offset: i32) -> usize;
+
+    /// Jumps by an offset of offset bytes if reg is zero.
+    /// It should always generate the same number of bytes to enable replacement if offset changes.
+    /// It returns the base offset to calculate the jump from (generally the instruction after the jump).
+    fn jz_reg64_imm32(buf: &mut Vec<'_, u8>, reg: GeneralReg, offset: i32) -> usize;
+
+    /// Jumps by an offset of offset bytes if reg is not zero.
+    /// It should always generate the same number of bytes to enable replacement if offset changes.
+    /// It returns the base offset to calculate the jump from (generally the instruction after the jump).
+    fn jnz_reg64_imm32(buf: &mut Vec<'_, u8>, reg: GeneralReg, offset: i32) -> usize;
+
     /// Jumps by an offset of offset bytes if reg is not equal to imm.
     /// It should always generate the same number of bytes to enable replacement if offset changes.
     /// It returns the base offset to calculate the jump from (generally the instruction after the jump).
@@ -313,6 +457,8 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
         imm: f64,
     );
     fn mov_reg64_imm64(buf: &mut Vec<'_, u8>, dst: GeneralReg, imm: i64);
+    /// Sets `dst` to the byte `imm`, with all the bits above it zeroed.
+    fn movzx_reg64_imm8(buf: &mut Vec<'_, u8>, dst: GeneralReg, imm: u8);
     fn mov_freg64_freg64(buf: &mut Vec<'_, u8>, dst: FloatReg, src: FloatReg);
 
     fn mov_reg32_freg32(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: FloatReg);
@@ -383,6 +529,15 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
     fn mov_base32_freg64(buf: &mut Vec<'_, u8>, offset: i32, src: FloatReg);
     fn mov_base32_freg32(buf: &mut Vec<'_, u8>, offset: i32, src: FloatReg);
 
+    /// Copies the 16 bytes at `from_offset` to `to_offset`, both relative to the base pointer,
+    /// through the float register `tmp`.
+    fn mov_base32_base32_128(
+        buf: &mut Vec<'_, u8>,
+        tmp: FloatReg,
+        to_offset: i32,
+        from_offset: i32,
+    );
+
     fn mov_base32_reg(
         buf: &mut Vec<'_, u8>,
         register_width: RegisterWidth,
@@ -475,6 +630,15 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
     ) {
         Self::mov_mem_offset32_reg(buf, RegisterWidth::W64, dst, offset, src)
     }
+
+    /// Like `mov_mem64_offset32_reg64`, but hints that the data will not be read again soon.
+    /// The store may bypass the cache, so it must be followed by `store_fence`.
+    fn movnti_mem64_offset32_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: GeneralReg,
+        offset: i32,
+        src: GeneralReg,
+    );
     fn mov_mem32_offset32_reg32(
         buf: &mut Vec<'_, u8>,
         dst: GeneralReg,
@@ -593,6 +757,15 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
         src1: FloatReg,
         src2: FloatReg,
     );
+    /// Computes `dst = base + index * scale` in a single instruction.
+    /// `scale` must be 1, 2, 4, or 8.
+    fn add_scaled_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: GeneralReg,
+        base: GeneralReg,
+        index: GeneralReg,
+        scale: u8,
+    );
     fn imul_reg64_reg64_reg64(
         buf: &mut Vec<'_, u8>,
         dst: GeneralReg,
@@ -663,12 +836,21 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
     );
 
     fn sub_reg64_reg64_imm32(buf: &mut Vec<'_, u8>, dst: GeneralReg, src1: GeneralReg, imm32: i32);
+    /// dst = src - 1. Like `inc_reg64_reg64`, nothing may read the carry out of it.
+    fn dec_reg64_reg64(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: GeneralReg);
     fn sub_reg64_reg64_reg64(
         buf: &mut Vec<'_, u8>,
         dst: GeneralReg,
         src1: GeneralReg,
         src2: GeneralReg,
     );
+    /// Like `sub_reg64_reg64_reg64`, but guaranteed to set the overflow flag for a signed result.
+    fn subs_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: GeneralReg,
+        src1: GeneralReg,
+        src2: GeneralReg,
+    );
 
     fn eq_reg_reg_reg(
         buf: &mut Vec<'_, u8>,
@@ -713,6 +895,14 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
         src2: GeneralReg,
     );
 
+    /// Sets `dst` to whether the signed integer in `src` is negative, i.e. `src < 0`.
+    fn is_negative_reg64(
+        buf: &mut Vec<'_, u8>,
+        register_width: RegisterWidth,
+        dst: GeneralReg,
+        src: GeneralReg,
+    );
+
     fn eq_freg_freg_reg64(
         buf: &mut Vec<'_, u8>,
         dst: GeneralReg,
@@ -744,13 +934,36 @@ pub trait Assembler<GeneralReg: RegTrait, FloatReg: RegTrait>: Sized + Copy {
 
     fn to_float_freg64_reg64(buf: &mut Vec<'_, u8>, dst: FloatReg, src: GeneralReg);
 
+    /// Makes the next write to `dst` independent of its current value. Conversions that only
+    /// write part of the register depend on the rest of it otherwise.
+    fn break_freg_dependency(_buf: &mut Vec<'_, u8>, _dst: FloatReg) {}
+
     fn to_float_freg32_freg64(buf: &mut Vec<'_, u8>, dst: FloatReg, src: FloatReg);
 
     fn to_float_freg64_freg32(buf: &mut Vec<'_, u8>, dst: FloatReg, src: FloatReg);
 
+    /// Counts the zero bits above the highest set bit of `src`, which is all 64 when `src` is zero.
+    fn count_leading_zeros_reg64_reg64(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: GeneralReg);
+
+    /// Counts the zero bits below the lowest set bit of `src`, which is all 64 when `src` is zero.
+    fn count_trailing_zeros_reg64_reg64(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: GeneralReg);
+
     fn set_if_overflow(buf: &mut Vec<'_, u8>, dst: GeneralReg);
 
+    /// Sets the carry flag exactly when the low byte of `src`, such as a Bool, is nonzero,
+    /// for `add_carry_reg64_reg64`. The rest of `src` is ignored. `tmp` may be clobbered.
+    fn set_carry_if_nonzero_reg8(buf: &mut Vec<'_, u8>, tmp: GeneralReg, src: GeneralReg);
+
+    /// dst = src + the carry flag, setting the flags like `adds_reg64_reg64_reg64`.
+    fn add_carry_reg64_reg64(buf: &mut Vec<'_, u8>, dst: GeneralReg, src: GeneralReg);
+
     fn ret(buf: &mut Vec<'_, u8>);
+
+    /// Emits an instruction that traps if it is ever executed.
+    fn unreachable(buf: &mut Vec<'_, u8>);
+
+    /// Orders all previous stores, including non-temporal ones, before any later store.
+    fn store_fence(buf: &mut Vec<'_, u8>);
 }
 
 pub trait RegTrait:
@@ -780,18 +993,79 @@ pub struct Backend64Bit<
     buf: Vec<'a, u8>,
     relocs: Vec<'a, Relocation>,
     proc_name: Option<String>,
+    proc_lambda_name: Option<LambdaName<'a>>,
     is_self_recursive: Option<SelfRecursive>,
 
-    last_seen_map: MutMap<Symbol, *const Stmt<'a>>,
     layout_map: MutMap<Symbol, InLayout<'a>>,
     free_map: MutMap<*const Stmt<'a>, Vec<'a, Symbol>>,
+    entry_free_map: MutMap<*const Stmt<'a>, Vec<'a, Symbol>>,
+    unused_results: MutSet<Symbol>,
 
     literal_map: MutMap<Symbol, (*const Literal<'a>, *const InLayout<'a>)>,
+    /// Literals that were loaded into storage, so they can be loaded again instead of spilled.
+    loaded_literals: MutMap<Symbol, (Literal<'a>, InLayout<'a>)>,
     join_map: MutMap<JoinPointId, Vec<'a, (u64, u64)>>,
+    /// The offset of each join point whose body is being built.
+    /// Jumps to these go backwards to a known location, so they need no fixup.
+    join_locations: MutMap<JoinPointId, u64>,
+    /// The flag symbol of a checked arithmetic op whose overflow is still in the flags register,
+    /// and the stack offset of the flag's field in the result struct.
+    overflow_flag: Option<(Symbol, i32)>,
+    /// False right after an unconditional control transfer, until the next jump target.
+    /// Anything emitted in between would be dead code.
+    reachable: bool,
+    /// Whether the proc calls anything. A call pushes a return address over the red zone.
+    makes_calls: bool,
+    red_zone_allowed: bool,
+    /// A struct that was just returned in the general return registers, and the length of `buf`
+    /// at that point. While nothing else has been emitted, the registers still hold its fields.
+    struct_in_return_regs: Option<(Symbol, usize)>,
+    /// The unwind info of the last emitted prologue, if the calling convention has any.
+    unwind_info: Option<std::vec::Vec<u8>>,
+    /// The location and offset of the jump of each call in tail position, and the proc it calls.
+    /// Each jumps to its own copy of the epilogue, which ends in a jump to the proc.
+    tail_calls: Vec<'a, (u64, u64, String)>,
+    /// The base offset of the stack canary, if the stack protector covers this proc.
+    /// It is claimed before anything else, so it sits between the locals and the saved registers.
+    canary_slot: Option<i32>,
+    /// The statement being built, to look up which symbols it uses for the last time.
+    current_stmt: Option<*const Stmt<'a>>,
+    /// Arguments of the next call that are dead after it.
+    /// They are passed straight from their registers instead of being spilled first.
+    last_use_args: Vec<'a, Symbol>,
 
     storage_manager: StorageManager<'a, 'r, GeneralReg, FloatReg, ASM, CC>,
 }
 
+/// Whether the 64 bit backends can build programs using `feature`.
+/// This is the same for every target they build for.
+pub fn supports_feature(feature: Feature) -> bool {
+    match feature {
+        Feature::Floats
+        | Feature::Decimals
+        | Feature::Calls
+        | Feature::TagUnions
+        | Feature::Lists
+        | Feature::Strings
+        | Feature::Dbg => true,
+        Feature::Expect | Feature::ErasedLambdas => false,
+    }
+}
+
+/// Narrows a float literal, which is always stored as an F64, to an F32.
+/// A cast leaves the payload of a NaN unspecified, so a NaN is narrowed bit by bit instead, the
+/// way `cvtsd2ss` does it: the sign and the top of the payload are kept, and the NaN is quiet.
+fn f32_literal(x: f64) -> f32 {
+    if !x.is_nan() {
+        return x as f32;
+    }
+
+    let bits = x.to_bits();
+    let sign = ((bits >> 63) as u32) << 31;
+    let payload = ((bits >> 29) as u32) & 0x007F_FFFF;
+    f32::from_bits(sign | 0x7FC0_0000 | payload)
+}
+
 /// new creates a new backend that will output to the specific Object.
 pub fn new_backend_64bit<
     'a,
@@ -816,18 +1090,84 @@ pub fn new_backend_64bit<
         helper_proc_symbols: bumpalo::vec![in env.arena],
         caller_procs: bumpalo::vec![in env.arena],
         proc_name: None,
+        proc_lambda_name: None,
         is_self_recursive: None,
         buf: bumpalo::vec![in env.arena],
         relocs: bumpalo::vec![in env.arena],
-        last_seen_map: MutMap::default(),
         layout_map: MutMap::default(),
         free_map: MutMap::default(),
+        entry_free_map: MutMap::default(),
+        unused_results: MutSet::default(),
         literal_map: MutMap::default(),
+        loaded_literals: MutMap::default(),
         join_map: MutMap::default(),
+        join_locations: MutMap::default(),
+        overflow_flag: None,
+        reachable: true,
+        makes_calls: false,
+        red_zone_allowed: true,
+        struct_in_return_regs: None,
+        unwind_info: None,
+        tail_calls: bumpalo::vec![in env.arena],
+        canary_slot: None,
+        current_stmt: None,
+        last_use_args: bumpalo::vec![in env.arena],
         storage_manager: storage::new_storage_manager(env, target),
     }
 }
 
+/// Whether building a proc may give an aggregate (e.g. a struct or a string) space on the stack.
+/// This has to be known before the body is built, so any layout that isn't kept in a register counts.
+fn may_have_stack_buffers<'a>(
+    layout_interner: &STLayoutInterner<'a>,
+    args: &[(InLayout<'a>, Symbol)],
+    body: &Stmt<'a>,
+) -> bool {
+    let is_buffer = |layout: &InLayout<'a>| {
+        !matches!(
+            layout_interner.get_repr(*layout),
+            single_register_layouts!() | pointer_layouts!()
+        ) && layout_interner.stack_size(*layout) > 0
+    };
+    args.iter().any(|(layout, _)| is_buffer(layout)) || stmt_has_layout(body, &is_buffer)
+}
+
+fn stmt_has_layout<'a>(stmt: &Stmt<'a>, pred: &impl Fn(&InLayout<'a>) -> bool) -> bool {
+    match stmt {
+        Stmt::Let(_, _, layout, following) => pred(layout) || stmt_has_layout(following, pred),
+        Stmt::Switch {
+            branches,
+            default_branch,
+            ..
+        } => {
+            branches
+                .iter()
+                .any(|(_, _, branch)| stmt_has_layout(branch, pred))
+                || stmt_has_layout(default_branch.1, pred)
+        }
+        Stmt::Join {
+            parameters,
+            body,
+            remainder,
+            ..
+        } => {
+            parameters.iter().any(|param| pred(&param.layout))
+                || stmt_has_layout(body, pred)
+                || stmt_has_layout(remainder, pred)
+        }
+        Stmt::Refcounting(_, following)
+        | Stmt::Expect {
+            remainder: following,
+            ..
+        }
+        | Stmt::Dbg {
+            remainder: following,
+            ..
+        } => stmt_has_layout(following, pred),
+        Stmt::Ret(_) | Stmt::Jump(..) | Stmt::Crash(..) => false,
+    }
+}
+
 macro_rules! quadword_and_smaller {
     () => {
         IntWidth::I64
@@ -868,6 +1208,12 @@ impl<
     fn target(&self) -> Target {
         self.storage_manager.target
     }
+    fn calling_convention(&self) -> &'static str {
+        CC::NAME
+    }
+    fn supports(&self, feature: Feature) -> bool {
+        supports_feature(feature)
+    }
     fn module_interns_helpers_mut(
         &mut self,
     ) -> (
@@ -900,21 +1246,40 @@ impl<
 
     fn reset(&mut self, name: String, is_self_recursive: SelfRecursive) {
         self.proc_name = Some(name);
+        self.proc_lambda_name = None;
         self.is_self_recursive = Some(is_self_recursive);
-        self.last_seen_map.clear();
         self.layout_map.clear();
         self.join_map.clear();
+        self.join_locations.clear();
         self.free_map.clear();
+        self.entry_free_map.clear();
+        self.unused_results.clear();
+        self.literal_map.clear();
+        self.loaded_literals.clear();
+        self.overflow_flag = None;
+        self.reachable = true;
+        self.makes_calls = false;
+        self.red_zone_allowed = true;
+        self.struct_in_return_regs = None;
+        self.unwind_info = None;
+        self.tail_calls.clear();
+        self.current_stmt = None;
+        self.last_use_args.clear();
         self.buf.clear();
         self.storage_manager.reset();
+        self.canary_slot = None;
     }
 
-    fn literal_map(&mut self) -> &mut MutMap<Symbol, (*const Literal<'a>, *const InLayout<'a>)> {
-        &mut self.literal_map
+    fn proc_lambda_name(&self) -> Option<LambdaName<'a>> {
+        self.proc_lambda_name
+    }
+
+    fn set_proc_lambda_name(&mut self, lambda_name: LambdaName<'a>) {
+        self.proc_lambda_name = Some(lambda_name);
     }
 
-    fn last_seen_map(&mut self) -> &mut MutMap<Symbol, *const Stmt<'a>> {
-        &mut self.last_seen_map
+    fn literal_map(&mut self) -> &mut MutMap<Symbol, (*const Literal<'a>, *const InLayout<'a>)> {
+        &mut self.literal_map
     }
 
     fn layout_map(&mut self) -> &mut MutMap<Symbol, InLayout<'a>> {
@@ -929,105 +1294,65 @@ impl<
         &mut self.free_map
     }
 
-    fn finalize(&mut self) -> (Vec<u8>, Vec<Relocation>) {
-        let mut out = bumpalo::vec![in self.env.arena];
+    fn set_entry_free_map(&mut self, map: MutMap<*const Stmt<'a>, Vec<'a, Symbol>>) {
+        self.entry_free_map = map;
+    }
 
-        // Setup stack.
-        let (used_general_regs, used_float_regs) = self
-            .storage_manager
-            .used_callee_saved_regs
-            .as_vecs(self.env.arena);
+    fn entry_free_map(&mut self) -> &mut MutMap<*const Stmt<'a>, Vec<'a, Symbol>> {
+        &mut self.entry_free_map
+    }
 
-        let aligned_stack_size = CC::setup_stack(
-            &mut out,
-            &used_general_regs,
-            &used_float_regs,
-            self.storage_manager.stack_size() as i32,
-            self.storage_manager.fn_call_stack_size() as i32,
-        );
-        let setup_offset = out.len();
+    fn set_unused_results(&mut self, set: MutSet<Symbol>) {
+        self.unused_results = set;
+    }
 
-        // Deal with jumps to the return address.
-        let old_relocs = std::mem::replace(&mut self.relocs, bumpalo::vec![in self.env.arena]);
+    fn unused_results(&mut self) -> &mut MutSet<Symbol> {
+        &mut self.unused_results
+    }
 
-        // Check if their is an unnessary jump to return right at the end of the function.
-        let mut end_jmp_size = 0;
-        for reloc in old_relocs
-            .iter()
-            .filter(|reloc| matches!(reloc, Relocation::JmpToReturn { .. }))
-        {
-            if let Relocation::JmpToReturn {
-                inst_loc,
-                inst_size,
-                ..
-            } = reloc
-            {
-                if *inst_loc as usize + *inst_size as usize == self.buf.len() {
-                    end_jmp_size = *inst_size as usize;
-                    break;
-                }
-            }
-        }
+    fn set_use_positions(&mut self, positions: UsePositions<'a>) {
+        self.storage_manager.set_use_positions(positions);
+    }
 
-        // Update jumps to returns.
-        let ret_offset = self.buf.len() - end_jmp_size;
-        for reloc in old_relocs
-            .iter()
-            .filter(|reloc| matches!(reloc, Relocation::JmpToReturn { .. }))
-        {
-            if let Relocation::JmpToReturn {
-                inst_loc,
-                inst_size,
-                offset,
-            } = reloc
-            {
-                if *inst_loc as usize + *inst_size as usize != self.buf.len() {
-                    ASM::update_jmp_imm32_offset(
-                        &mut self.buf,
-                        *inst_loc,
-                        *offset,
-                        ret_offset as u64,
-                    );
-                }
-            }
+    fn enter_stmt(&mut self, stmt: &Stmt<'a>) {
+        self.current_stmt = Some(stmt as *const Stmt<'a>);
+        self.storage_manager.enter_stmt(stmt);
+    }
+
+    fn pass_last_uses_in_registers(&mut self, args: &[Symbol]) {
+        self.last_use_args.clear();
+        let last_uses = self.current_stmt.and_then(|stmt| self.free_map.get(&stmt));
+        if let Some(last_uses) = last_uses {
+            self.last_use_args
+                .extend(args.iter().filter(|arg| last_uses.contains(arg)).copied());
         }
+    }
 
-        // Add function body.
-        out.extend(&self.buf[..self.buf.len() - end_jmp_size]);
+    fn finalize(&mut self) -> (Vec<u8>, Vec<Relocation>) {
+        let mut out = bumpalo::vec![in self.env.arena];
+        let relocs = self.finalize_into(&mut out);
+        (out, relocs)
+    }
 
-        // Cleanup stack.
-        CC::cleanup_stack(
-            &mut out,
-            &used_general_regs,
-            &used_float_regs,
-            aligned_stack_size,
-            self.storage_manager.fn_call_stack_size() as i32,
-        );
-        ASM::ret(&mut out);
+    fn disable_red_zone(&mut self) {
+        self.red_zone_allowed = false;
+    }
 
-        // Update other relocs to include stack setup offset.
-        let mut out_relocs = bumpalo::vec![in self.env.arena];
-        out_relocs.extend(
-            old_relocs
-                .into_iter()
-                .filter(|reloc| !matches!(reloc, Relocation::JmpToReturn { .. }))
-                .map(|reloc| match reloc {
-                    Relocation::LocalData { offset, data } => Relocation::LocalData {
-                        offset: offset + setup_offset as u64,
-                        data,
-                    },
-                    Relocation::LinkedData { offset, name } => Relocation::LinkedData {
-                        offset: offset + setup_offset as u64,
-                        name,
-                    },
-                    Relocation::LinkedFunction { offset, name } => Relocation::LinkedFunction {
-                        offset: offset + setup_offset as u64,
-                        name,
-                    },
-                    Relocation::JmpToReturn { .. } => unreachable!(),
-                }),
-        );
-        (out, out_relocs)
+    fn claim_canary_slot(&mut self, args: &[(InLayout<'a>, Symbol)], body: &Stmt<'a>) {
+        // Only glibc and musl on x86_64 keep the guard at a fixed thread-local offset.
+        let protected = self.target() == Target::LinuxX64
+            && match self.env.options.stack_protector {
+                StackProtector::Off => false,
+                StackProtector::Buffers => may_have_stack_buffers(self.layout_interner, args, body),
+                StackProtector::All => true,
+            };
+        if protected {
+            self.canary_slot = Some(self.storage_manager.claim_reserved_stack_slot());
+        }
+    }
+
+    fn unwind_info(&self) -> Option<&[u8]> {
+        self.unwind_info.as_deref()
     }
 
     fn load_args(&mut self, args: &'a [(InLayout<'a>, Symbol)], ret_layout: &InLayout<'a>) {
@@ -1044,6 +1369,9 @@ impl<
     fn build_wrapped_jmp(&mut self) -> (&'a [u8], u64) {
         let mut out = bumpalo::vec![in self.env.arena];
         let offset = ASM::tail_call(&mut out);
+        if self.env.options.trap_unreachable {
+            ASM::unreachable(&mut out);
+        }
 
         (out.into_bump_slice(), offset)
     }
@@ -1073,17 +1401,52 @@ impl<
         (out.into_bump_slice(), relocs)
     }
 
+    fn build_roc_dbg(&mut self) -> &'a [u8] {
+        let mut out = bumpalo::vec![in self.env.arena];
+
+        // The message is dropped, all that matters is that the program carries on.
+        ASM::ret(&mut out);
+
+        out.into_bump_slice()
+    }
+
+    fn build_entry_shim(&mut self, main: String) -> (&'a [u8], Vec<'a, Relocation>) {
+        let mut out = bumpalo::vec![in self.env.arena];
+        let mut relocs = bumpalo::vec![in self.env.arena];
+
+        CC::entry_shim(&mut out, &mut relocs, main);
+
+        (out.into_bump_slice(), relocs)
+    }
+
+    fn build_unreachable(&mut self) {
+        ASM::unreachable(&mut self.buf);
+    }
+
+    fn mark_unreachable(&mut self) {
+        self.reachable = false;
+    }
+
     fn build_fn_pointer(&mut self, dst: &Symbol, fn_name: String) {
         let reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
 
         ASM::function_pointer(&mut self.buf, &mut self.relocs, fn_name, reg)
     }
 
+    fn build_self_pointer(&mut self, dst: &Symbol) {
+        let fn_name = match &self.proc_name {
+            Some(name) => name.clone(),
+            None => internal_error!("taking the address of a proc before it was reset"),
+        };
+
+        self.build_fn_pointer(dst, fn_name)
+    }
+
     fn build_data_pointer(&mut self, dst: &Symbol, data_name: String) {
         let reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
 
         // now, this gives a pointer to the value
-        ASM::data_pointer(&mut self.buf, &mut self.relocs, data_name, reg);
+        ASM::data_pointer(&mut self.buf, &mut self.relocs, data_name, 0, reg);
     }
 
     fn build_fn_call(
@@ -1094,27 +1457,66 @@ impl<
         arg_layouts: &[InLayout<'a>],
         ret_layout: &InLayout<'a>,
     ) {
-        debug_assert_eq!(args.len(), arg_layouts.len());
+        self.build_fn_call_for_effect(dst, fn_name, args, arg_layouts, ret_layout);
+        self.move_return_value(dst, ret_layout)
+    }
 
-        // Save used caller saved regs.
-        self.storage_manager
-            .push_used_caller_saved_regs_to_stack(&mut self.buf);
-
-        // Put values in param regs or on top of the stack.
-        CC::store_args(
-            &mut self.buf,
-            &mut self.storage_manager,
-            self.layout_interner,
-            dst,
-            args,
-            arg_layouts,
-            ret_layout,
-        );
+    fn build_fn_call_for_effect(
+        &mut self,
+        dst: &Symbol,
+        fn_name: String,
+        args: &[Symbol],
+        arg_layouts: &[InLayout<'a>],
+        ret_layout: &InLayout<'a>,
+    ) {
+        self.set_up_call(dst, args, arg_layouts, ret_layout);
 
         // Call function and generate reloc.
         ASM::call(&mut self.buf, &mut self.relocs, fn_name);
+        self.makes_calls = true;
+    }
 
-        self.move_return_value(dst, ret_layout)
+    fn build_tail_call(
+        &mut self,
+        dst: &Symbol,
+        fn_name: String,
+        args: &[Symbol],
+        arg_layouts: &[InLayout<'a>],
+        ret_layout: &InLayout<'a>,
+    ) -> bool {
+        // Arguments on the stack would be freed along with the frame, and a result returned
+        // through memory would need our own return pointer. So only calls that pass and return
+        // everything in registers can reuse the caller's return address.
+        let in_one_register = |layout: &InLayout<'a>| {
+            matches!(
+                self.layout_interner.get_repr(*layout),
+                single_register_layouts!() | pointer_layouts!()
+            )
+        };
+        // A `Ptr` may point at an alloca in this frame, which is gone by the time the callee runs.
+        let into_this_frame = |layout: &InLayout<'a>| {
+            matches!(self.layout_interner.get_repr(*layout), LayoutRepr::Ptr(_))
+        };
+        let param_regs = CC::GENERAL_PARAM_REGS.len().min(CC::FLOAT_PARAM_REGS.len());
+        // The callee would only pop its own stack arguments, and leave ours to our caller.
+        let pops_stack_args =
+            CC::CALLEE_POPS_STACK_ARGS && self.storage_manager.stack_args_size() > 0;
+        if pops_stack_args
+            || args.len() > param_regs
+            || !arg_layouts.iter().all(in_one_register)
+            || arg_layouts.iter().any(into_this_frame)
+            || !in_one_register(ret_layout)
+        {
+            return false;
+        }
+
+        self.set_up_call(dst, args, arg_layouts, ret_layout);
+
+        let inst_loc = self.buf.len() as u64;
+        let offset = ASM::jmp_imm32(&mut self.buf, JUMP_PLACEHOLDER) as u64;
+        self.tail_calls.push((inst_loc, offset, fn_name));
+        self.reachable = false;
+        true
     }
 
     fn move_return_value(&mut self, dst: &Symbol, ret_layout: &InLayout<'a>) {
@@ -1166,6 +1568,16 @@ impl<
                     dst,
                     ret_layout,
                 );
+
+                // Storing the struct to the stack leaves the return registers untouched.
+                let size = self.layout_interner.stack_size(*ret_layout);
+                if matches!(ret_repr, LayoutRepr::Struct(_))
+                    && CC::GENERAL_RETURN_REGS.len() > 1
+                    && (1..=16).contains(&size)
+                    && !CC::returns_floats_in_float_regs(self.layout_interner, ret_layout)
+                {
+                    self.struct_in_return_regs = Some((*dst, self.buf.len()));
+                }
             }
         }
     }
@@ -1174,17 +1586,58 @@ impl<
         &mut self,
         layout_ids: &mut LayoutIds<'a>,
         cond_symbol: &Symbol,
-        _cond_layout: &InLayout<'a>, // cond_layout must be a integer due to potential jump table optimizations.
+        cond_layout: &InLayout<'a>,
         branches: &'a [(u64, BranchInfo<'a>, Stmt<'a>)],
         default_branch: &(BranchInfo<'a>, &'a Stmt<'a>),
         ret_layout: &InLayout<'a>,
     ) {
+        if let Some((flag, flag_offset)) = self.overflow_flag.take() {
+            if flag == *cond_symbol {
+                return self.build_overflow_switch(
+                    layout_ids,
+                    flag,
+                    flag_offset,
+                    branches,
+                    default_branch,
+                    ret_layout,
+                );
+            }
+        }
+
         // Switches are a little complex due to keeping track of jumps.
         // In general I am trying to not have to loop over things multiple times or waste memory.
         // The basic plan is to make jumps to nowhere and then correct them once we know the correct address.
-        let cond_reg = self
-            .storage_manager
-            .load_to_general_reg(&mut self.buf, cond_symbol);
+        // A float can't be compared with an immediate. So for a float discriminant, the value of
+        // each branch is the bit pattern of a constant, which is compared with the discriminant
+        // in float registers first. The branch then tests the resulting flag instead.
+        // An unordered compare clears the flag, so NaN always goes to the default branch.
+        let float_cond = match self.layout_interner.get_repr(*cond_layout) {
+            LayoutRepr::Builtin(Builtin::Float(width)) => {
+                let cond_freg = self
+                    .storage_manager
+                    .load_to_float_reg(&mut self.buf, cond_symbol);
+                let flag = self.debug_symbol("switch_flag");
+                let constant = self.debug_symbol("switch_constant");
+                let constant_reg = self
+                    .storage_manager
+                    .claim_float_reg(&mut self.buf, &constant);
+                Some((width, cond_freg, constant_reg, flag, constant))
+            }
+            _ => None,
+        };
+        // A Bool is only ever 0 or 1, so testing it against itself is enough to pick a branch.
+        let bool_cond = matches!(
+            self.layout_interner.get_repr(*cond_layout),
+            LayoutRepr::Builtin(Builtin::Bool)
+        );
+        let cond_reg = match float_cond {
+            Some((_, _, _, flag, _)) => {
+                self.storage_manager.claim_general_reg(&mut self.buf, &flag)
+            }
+            None => self
+                .storage_manager
+                .load_to_general_reg(&mut self.buf, cond_symbol),
+        };
 
         // this state is updated destructively in the branches. We don't want the branches to
         // influence each other, so we must clone here.
@@ -1197,38 +1650,78 @@ impl<
         for (val, _branch_info, stmt) in branches.iter() {
             // TODO: look into branch info and if it matters here.
             tmp.clear();
+            let val = match float_cond {
+                Some((width, cond_freg, constant_reg, ..)) => {
+                    match width {
+                        FloatWidth::F64 => ASM::mov_freg64_imm64(
+                            &mut self.buf,
+                            &mut self.relocs,
+                            constant_reg,
+                            f64::from_bits(*val),
+                        ),
+                        FloatWidth::F32 => ASM::mov_freg32_imm32(
+                            &mut self.buf,
+                            &mut self.relocs,
+                            constant_reg,
+                            f32::from_bits(*val as u32),
+                        ),
+                    }
+                    ASM::eq_freg_freg_reg64(
+                        &mut self.buf,
+                        cond_reg,
+                        cond_freg,
+                        constant_reg,
+                        width,
+                    );
+                    1
+                }
+                None => *val,
+            };
+
             // Create jump to next branch if cond_sym not equal to value.
             // Since we don't know the offset yet, set it to 0 and overwrite later.
             let jne_location = self.buf.len();
-            let start_offset = ASM::jne_reg64_imm64_imm32(
-                &mut self.buf,
-                &mut self.storage_manager,
-                cond_reg,
-                *val,
-                0,
-            );
+            let start_offset = if bool_cond {
+                Self::jump_unless_bool(&mut self.buf, cond_reg, val, 0)
+            } else {
+                ASM::jne_reg64_imm64_imm32(
+                    &mut self.buf,
+                    &mut self.storage_manager,
+                    cond_reg,
+                    val,
+                    0,
+                )
+            };
 
             // Build all statements in this branch. Using storage as from before any branch.
             self.storage_manager = base_storage.clone();
             self.literal_map = base_literal_map.clone();
             self.build_stmt(layout_ids, stmt, ret_layout);
 
-            // Build unconditional jump to the end of this switch.
+            // Build unconditional jump to the end of this switch, unless the branch already left.
             // Since we don't know the offset yet, set it to 0 and overwrite later.
-            let jmp_location = self.buf.len();
-            let jmp_offset = ASM::jmp_imm32(&mut self.buf, JUMP_PLACEHOLDER);
-            ret_jumps.push((jmp_location, jmp_offset));
+            if self.reachable {
+                let jmp_location = self.buf.len();
+                let jmp_offset = ASM::jmp_imm32(&mut self.buf, JUMP_PLACEHOLDER);
+                ret_jumps.push((jmp_location, jmp_offset));
+            }
 
             // Overwrite the original jne with the correct offset.
+            // The next branch starts at a jump target, so it is reachable again.
             let end_offset = self.buf.len();
+            self.reachable = true;
             let jne_offset = end_offset - start_offset;
-            ASM::jne_reg64_imm64_imm32(
-                &mut tmp,
-                &mut self.storage_manager,
-                cond_reg,
-                *val,
-                jne_offset as i32,
-            );
+            if bool_cond {
+                Self::jump_unless_bool(&mut tmp, cond_reg, val, jne_offset as i32);
+            } else {
+                ASM::jne_reg64_imm64_imm32(
+                    &mut tmp,
+                    &mut self.storage_manager,
+                    cond_reg,
+                    val,
+                    jne_offset as i32,
+                );
+            }
             for (i, byte) in tmp.iter().enumerate() {
                 self.buf[jne_location + i] = *byte;
             }
@@ -1248,11 +1741,18 @@ impl<
         self.literal_map = base_literal_map;
         self.storage_manager
             .update_stack_size(max_branch_stack_size);
+        if let Some((_, _, _, flag, constant)) = float_cond {
+            self.storage_manager.free_symbol(&flag);
+            self.storage_manager.free_symbol(&constant);
+        }
         let (_branch_info, stmt) = default_branch;
         self.build_stmt(layout_ids, stmt, ret_layout);
 
         // Update all return jumps to jump past the default case.
         let ret_offset = self.buf.len();
+        if !ret_jumps.is_empty() {
+            self.reachable = true;
+        }
         for (jmp_location, start_offset) in ret_jumps.into_iter() {
             ASM::update_jmp_imm32_offset(
                 &mut self.buf,
@@ -1278,6 +1778,8 @@ impl<
 
         // Ensure all the joinpoint parameters have storage locations.
         // On jumps to the joinpoint, we will overwrite those locations as a way to "pass parameters" to the joinpoint.
+        // The body starts with its parameters only in those locations, so returning one (e.g. the
+        // accumulator of a loop) loads it straight from there into the return register.
         self.storage_manager
             .setup_joinpoint(self.layout_interner, &mut self.buf, id, parameters);
 
@@ -1286,10 +1788,29 @@ impl<
         // Build remainder of function first. It is what gets run and jumps to join.
         self.build_stmt(layout_ids, remainder, ret_layout);
 
+        // A remainder that is only a jump here, like a proc that enters a loop with its own
+        // arguments, has already placed them in the parameters, and can fall through to the body.
+        // That is only the case if its jump is the last thing emitted, so dropping it leaves
+        // nothing else behind.
+        if let (Stmt::Jump(jump_id, _), Some(jumps)) = (remainder, self.join_map.get_mut(id)) {
+            let buf_len = self.buf.len() as u64;
+            if jump_id == id {
+                if let Some(&(jmp_location, _)) = jumps.last() {
+                    if jmp_location + ASM::JMP_IMM32_SIZE == buf_len {
+                        jumps.pop();
+                        self.buf.truncate(jmp_location as usize);
+                    }
+                }
+            }
+        }
+
         let join_location = self.buf.len() as u64;
+        self.reachable = true;
 
         // Build all statements in body.
+        self.join_locations.insert(*id, join_location);
         self.build_stmt(layout_ids, body, ret_layout);
+        self.join_locations.remove(id);
 
         // Overwrite the all jumps to the joinpoint with the correct offset.
         for (jmp_location, start_offset) in self
@@ -1315,6 +1836,14 @@ impl<
         self.storage_manager
             .setup_jump(self.layout_interner, &mut self.buf, id, args, arg_layouts);
 
+        self.reachable = false;
+
+        // A jump from the body of the join point back to its start can be emitted as is.
+        if let Some(join_location) = self.join_locations.get(id) {
+            ASM::jmp_to_offset(&mut self.buf, *join_location);
+            return;
+        }
+
         let jmp_location = self.buf.len();
         let start_offset = ASM::jmp_imm32(&mut self.buf, JUMP_PLACEHOLDER);
 
@@ -1348,6 +1877,23 @@ impl<
 
     fn build_num_add(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>) {
         match self.layout_interner.get_repr(*layout) {
+            LayoutRepr::Builtin(Builtin::Int(_))
+                if self.env.options.overflow_mode == OverflowMode::Wrap =>
+            {
+                self.build_num_add_wrap(dst, src1, src2, layout)
+            }
+            LayoutRepr::Builtin(Builtin::Int(IntWidth::I64)) => {
+                let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+                let src1_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, src1);
+                let src2_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, src2);
+                ASM::adds_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
+
+                self.trap_if_overflow("Integer addition overflowed!");
+            }
             LayoutRepr::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
                 dst,
                 bitcode::NUM_ADD_OR_PANIC_INT[int_width].to_string(),
@@ -1458,6 +2004,193 @@ impl<
         )
     }
 
+    fn build_conditional_increment(
+        &mut self,
+        dst: &Symbol,
+        value: &Symbol,
+        cond: &Symbol,
+        layout: &InLayout<'a>,
+    ) -> bool {
+        let checked = match self.layout_interner.get_repr(*layout) {
+            LayoutRepr::Builtin(Builtin::Int(quadword_and_smaller!()))
+                if self.env.options.overflow_mode == OverflowMode::Wrap =>
+            {
+                false
+            }
+            LayoutRepr::Builtin(Builtin::Int(IntWidth::I64)) => true,
+            _ => return false,
+        };
+
+        self.load_literal_symbols(&[*value, *cond]);
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+        let value_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, value);
+        let cond_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, cond);
+
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |_, buf, tmp| {
+                ASM::set_carry_if_nonzero_reg8(buf, tmp, cond_reg);
+            });
+        ASM::add_carry_reg64_reg64(&mut self.buf, dst_reg, value_reg);
+
+        if checked {
+            self.trap_if_overflow("Integer addition overflowed!");
+        }
+
+        true
+    }
+
+    fn build_chained_comparison(
+        &mut self,
+        dst: &Symbol,
+        ops: [LowLevel; 2],
+        operands: &[Symbol; 3],
+        arg_layout: &InLayout<'a>,
+    ) -> bool {
+        let int_width = match self.layout_interner.get_repr(*arg_layout) {
+            single_register_integers!() => arg_layout.try_int_width().unwrap(),
+            _ => return false,
+        };
+        let [first_op, second_op] = ops.map(|op| match op {
+            LowLevel::NumLt => CompareOperation::LessThan,
+            LowLevel::NumLte => CompareOperation::LessThanOrEqual,
+            LowLevel::NumGt => CompareOperation::GreaterThan,
+            LowLevel::NumGte => CompareOperation::GreaterThanOrEqual,
+            other => internal_error!("{other:?} is not a comparison"),
+        });
+        let [a, b, c] = operands;
+
+        self.load_literal_symbols(operands);
+
+        // The shared operand is held in its register for both comparisons.
+        self.storage_manager.with_general_reg_constraint(
+            &mut self.buf,
+            b,
+            None,
+            |storage_manager, buf, b_reg| {
+                let dst_reg = storage_manager.claim_general_reg(buf, dst);
+                let a_reg = storage_manager.load_to_general_reg(buf, a);
+                let c_reg = storage_manager.load_to_general_reg(buf, c);
+
+                Self::compare_int_reg64(buf, first_op, int_width, dst_reg, a_reg, b_reg);
+                storage_manager.with_tmp_general_reg(buf, |_, buf, tmp| {
+                    Self::compare_int_reg64(buf, second_op, int_width, tmp, b_reg, c_reg);
+                    ASM::and_reg64_reg64_reg64(buf, dst_reg, dst_reg, tmp);
+                });
+            },
+        );
+
+        true
+    }
+
+    fn build_checked_overflow_branch(
+        &mut self,
+        op: LowLevel,
+        result: &Symbol,
+        args: &'a [Symbol],
+        result_layout: &InLayout<'a>,
+        flag: Symbol,
+        flag_index: u64,
+        field_layouts: &'a [InLayout<'a>],
+    ) -> bool {
+        let value_index = match (flag_index, field_layouts) {
+            (1, [Layout::I64, Layout::BOOL]) => 0,
+            (0, [Layout::BOOL, Layout::I64]) => 1,
+            _ => return false,
+        };
+        let field_offset = |index: usize| -> i32 {
+            field_layouts[..index]
+                .iter()
+                .map(|layout| self.layout_interner.stack_size(*layout) as i32)
+                .sum()
+        };
+        let value_field_offset = field_offset(value_index);
+        let flag_field_offset = field_offset(flag_index as usize);
+
+        self.load_literal_symbols(args);
+        let base_offset = self.storage_manager.claim_stack_area_layout(
+            self.layout_interner,
+            *result,
+            *result_layout,
+        );
+        let src1_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &args[0]);
+        let src2_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &args[1]);
+
+        // Storing the value does not touch the flags, so the switch can branch on the overflow directly.
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |_, buf, reg| {
+                match op {
+                    LowLevel::NumAddChecked => {
+                        ASM::adds_reg64_reg64_reg64(buf, reg, src1_reg, src2_reg)
+                    }
+                    LowLevel::NumSubChecked => {
+                        ASM::subs_reg64_reg64_reg64(buf, reg, src1_reg, src2_reg)
+                    }
+                    other => internal_error!("{other:?} does not report overflow"),
+                }
+                ASM::mov_base32_reg64(buf, base_offset + value_field_offset, reg);
+            });
+
+        self.overflow_flag = Some((flag, base_offset + flag_field_offset));
+        true
+    }
+
+    fn build_checked_arithmetic_return(
+        &mut self,
+        op: LowLevel,
+        args: &'a [Symbol],
+        result_layout: &InLayout<'a>,
+    ) -> bool {
+        let is_value_and_flag = matches!(
+            self.layout_interner.get_repr(*result_layout),
+            LayoutRepr::Struct(&[value, flag]) if value == Layout::I64 && flag == Layout::BOOL
+        );
+        if !is_value_and_flag || CC::GENERAL_RETURN_REGS.len() < 2 {
+            return false;
+        }
+        let value_reg = CC::GENERAL_RETURN_REGS[0];
+        let flag_reg = CC::GENERAL_RETURN_REGS[1];
+
+        self.load_literal_symbols(args);
+        self.storage_manager
+            .ensure_reg_free(&mut self.buf, RegStorage::General(value_reg));
+        self.storage_manager
+            .ensure_reg_free(&mut self.buf, RegStorage::General(flag_reg));
+        let src1_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &args[0]);
+        let src2_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &args[1]);
+
+        // The sources may be in the return registers, so compute the value elsewhere first.
+        // Moving it does not touch the flags, so the overflow can be read afterwards.
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |_, buf, reg| {
+                match op {
+                    LowLevel::NumAddChecked => {
+                        ASM::adds_reg64_reg64_reg64(buf, reg, src1_reg, src2_reg)
+                    }
+                    LowLevel::NumSubChecked => {
+                        ASM::subs_reg64_reg64_reg64(buf, reg, src1_reg, src2_reg)
+                    }
+                    other => internal_error!("{other:?} does not report overflow"),
+                }
+                ASM::mov_reg64_reg64(buf, value_reg, reg);
+                ASM::set_if_overflow(buf, flag_reg);
+            });
+
+        self.jmp_to_return();
+        true
+    }
+
     fn build_num_sub_saturated(
         &mut self,
         dst: Symbol,
@@ -1508,6 +2241,11 @@ impl<
 
     fn build_num_mul(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>) {
         match self.layout_interner.get_repr(*layout) {
+            LayoutRepr::Builtin(Builtin::Int(_))
+                if self.env.options.overflow_mode == OverflowMode::Wrap =>
+            {
+                self.build_num_mul_wrap(dst, src1, src2, layout)
+            }
             LayoutRepr::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
                 dst,
                 bitcode::NUM_MUL_OR_PANIC_INT[int_width].to_string(),
@@ -1587,6 +2325,111 @@ impl<
         }
     }
 
+    fn build_num_mul_wrap_literal(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        layout: &InLayout<'a>,
+    ) -> bool {
+        if !matches!(
+            self.layout_interner.get_repr(*layout),
+            single_register_int_builtins!()
+        ) {
+            return false;
+        }
+
+        // `x * 3`, `x * 5`, and `x * 9` are `x + x * 2`, `x + x * 4`, and `x + x * 8`,
+        // which fit in a single `lea` (or shifted `add`) without loading the literal at all.
+        let (src, scale) = match (self.lea_multiplier(src1), self.lea_multiplier(src2)) {
+            (_, Some(scale)) => (src1, scale),
+            (Some(scale), None) => (src2, scale),
+            (None, None) => return false,
+        };
+
+        self.load_literal_symbols(&[*src]);
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+        let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
+        ASM::add_scaled_reg64_reg64_reg64(&mut self.buf, dst_reg, src_reg, src_reg, scale);
+        true
+    }
+
+    fn build_num_add_wrap_literal(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        layout: &InLayout<'a>,
+    ) -> bool {
+        if !matches!(
+            self.layout_interner.get_repr(*layout),
+            single_register_int_builtins!()
+        ) {
+            return false;
+        }
+
+        let (src, delta) = match (self.int_literal(src1), self.int_literal(src2)) {
+            (_, Some(delta @ (1 | -1))) => (src1, delta),
+            (Some(delta @ (1 | -1)), _) => (src2, delta),
+            _ => return false,
+        };
+
+        self.load_literal_symbols(&[*src]);
+        self.build_int_step(dst, src, delta);
+        true
+    }
+
+    fn build_num_sub_wrap_literal(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        layout: &InLayout<'a>,
+    ) -> bool {
+        if !matches!(
+            self.layout_interner.get_repr(*layout),
+            single_register_int_builtins!()
+        ) {
+            return false;
+        }
+
+        let Some(delta @ (1 | -1)) = self.int_literal(src2) else {
+            return false;
+        };
+
+        self.load_literal_symbols(&[*src1]);
+        self.build_int_step(dst, src1, -delta);
+        true
+    }
+
+    fn build_num_lt_literal(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        arg_layout: &InLayout<'a>,
+    ) -> bool {
+        let repr = self.layout_interner.get_repr(*arg_layout);
+        let width = match repr {
+            LayoutRepr::I8 | LayoutRepr::I16 | LayoutRepr::I32 | LayoutRepr::I64 => {
+                RegisterWidth::try_from_layout(repr).unwrap()
+            }
+            _ => return false,
+        };
+        if self.int_literal(src2) != Some(0) {
+            return false;
+        }
+
+        // Only the sign bit matters, so the zero is never loaded.
+        self.load_literal_symbols(&[*src1]);
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+        let src_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, src1);
+        ASM::is_negative_reg64(&mut self.buf, width, dst_reg, src_reg);
+        true
+    }
+
     fn build_num_mul_saturated(
         &mut self,
         dst: Symbol,
@@ -1708,6 +2551,76 @@ impl<
         layout: &InLayout<'a>,
     ) {
         match self.layout_interner.get_repr(*layout) {
+            LayoutRepr::Builtin(Builtin::Int(int_width @ quadword_and_smaller!())) => {
+                // Division truncates, so the quotient is one too small exactly when there is a
+                // remainder and the exact quotient is positive. For signed operands that is when
+                // the remainder, which has the sign of the dividend, has the sign of the divisor.
+                self.trap_if_zero(src2, "Integer division by 0!");
+
+                let quotient = self.debug_symbol("div_ceil_quotient");
+                let remainder = self.debug_symbol("div_ceil_remainder");
+                self.build_num_div(&quotient, src1, src2, layout);
+                self.build_num_rem(&remainder, src1, src2, layout);
+
+                let round_up = self.debug_symbol("div_ceil_round_up");
+                let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+                let round_up_reg = self
+                    .storage_manager
+                    .claim_general_reg(&mut self.buf, &round_up);
+                let quotient_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &quotient);
+                let remainder_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &remainder);
+
+                // The destination doubles as a zero to compare against until the very end.
+                ASM::mov_reg64_imm64(&mut self.buf, dst_reg, 0);
+                ASM::neq_reg_reg_reg(
+                    &mut self.buf,
+                    RegisterWidth::W64,
+                    round_up_reg,
+                    remainder_reg,
+                    dst_reg,
+                );
+
+                if int_width.is_signed() {
+                    let divisor_reg = self
+                        .storage_manager
+                        .load_to_general_reg(&mut self.buf, src2);
+                    self.storage_manager.with_tmp_general_reg(
+                        &mut self.buf,
+                        |_storage_manager, buf, same_sign_reg| {
+                            ASM::xor_reg64_reg64_reg64(
+                                buf,
+                                same_sign_reg,
+                                remainder_reg,
+                                divisor_reg,
+                            );
+                            ASM::signed_compare_reg64(
+                                buf,
+                                RegisterWidth::W64,
+                                CompareOperation::GreaterThanOrEqual,
+                                same_sign_reg,
+                                same_sign_reg,
+                                dst_reg,
+                            );
+                            ASM::and_reg64_reg64_reg64(
+                                buf,
+                                round_up_reg,
+                                round_up_reg,
+                                same_sign_reg,
+                            );
+                        },
+                    );
+                }
+
+                ASM::add_reg64_reg64_reg64(&mut self.buf, dst_reg, quotient_reg, round_up_reg);
+
+                self.free_symbol(&quotient);
+                self.free_symbol(&remainder);
+                self.free_symbol(&round_up);
+            }
             LayoutRepr::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
                 dst,
                 bitcode::NUM_DIV_CEIL[int_width].to_string(),
@@ -1791,14 +2704,85 @@ impl<
         }
     }
 
-    fn build_num_sub(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>) {
+    fn build_num_count_leading_zero_bits(
+        &mut self,
+        dst: &Symbol,
+        src: &Symbol,
+        layout: &InLayout<'a>,
+        ret_layout: &InLayout<'a>,
+    ) {
         match self.layout_interner.get_repr(*layout) {
+            LayoutRepr::Builtin(Builtin::Int(IntWidth::I64 | IntWidth::U64)) => {
+                let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+                let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
+                ASM::count_leading_zeros_reg64_reg64(&mut self.buf, dst_reg, src_reg);
+            }
             LayoutRepr::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
                 dst,
-                bitcode::NUM_SUB_OR_PANIC_INT[int_width].to_string(),
-                &[*src1, *src2],
-                &[*layout, *layout],
-                layout,
+                bitcode::NUM_COUNT_LEADING_ZERO_BITS[int_width].to_string(),
+                &[*src],
+                &[*layout],
+                ret_layout,
+            ),
+            other => internal_error!(
+                "unreachable: NumCountLeadingZeroBits for layout, {:?}",
+                other
+            ),
+        }
+    }
+
+    fn build_num_count_trailing_zero_bits(
+        &mut self,
+        dst: &Symbol,
+        src: &Symbol,
+        layout: &InLayout<'a>,
+        ret_layout: &InLayout<'a>,
+    ) {
+        match self.layout_interner.get_repr(*layout) {
+            LayoutRepr::Builtin(Builtin::Int(IntWidth::I64 | IntWidth::U64)) => {
+                let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+                let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
+                ASM::count_trailing_zeros_reg64_reg64(&mut self.buf, dst_reg, src_reg);
+            }
+            LayoutRepr::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
+                dst,
+                bitcode::NUM_COUNT_TRAILING_ZERO_BITS[int_width].to_string(),
+                &[*src],
+                &[*layout],
+                ret_layout,
+            ),
+            other => internal_error!(
+                "unreachable: NumCountTrailingZeroBits for layout, {:?}",
+                other
+            ),
+        }
+    }
+
+    fn build_num_sub(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>) {
+        match self.layout_interner.get_repr(*layout) {
+            LayoutRepr::Builtin(Builtin::Int(_))
+                if self.env.options.overflow_mode == OverflowMode::Wrap =>
+            {
+                self.build_num_sub_wrap(dst, src1, src2, layout)
+            }
+            LayoutRepr::Builtin(Builtin::Int(IntWidth::I64)) => {
+                let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+                let src1_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, src1);
+                let src2_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, src2);
+                ASM::subs_reg64_reg64_reg64(&mut self.buf, dst_reg, src1_reg, src2_reg);
+
+                self.trap_if_overflow("Integer subtraction overflowed!");
+            }
+            LayoutRepr::Builtin(Builtin::Int(int_width)) => self.build_fn_call(
+                dst,
+                bitcode::NUM_SUB_OR_PANIC_INT[int_width].to_string(),
+                &[*src1, *src2],
+                &[*layout, *layout],
+                layout,
             ),
 
             LayoutRepr::Builtin(Builtin::Float(FloatWidth::F64)) => {
@@ -2060,14 +3044,10 @@ impl<
                 let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
                 let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
 
+                // Booleans in registers are always 0 or 1 with the upper bits zeroed,
+                // so flipping the lowest bit is enough. `src` must be left untouched, it may be used later.
                 ASM::mov_reg64_imm64(&mut self.buf, dst_reg, 1);
-                ASM::xor_reg64_reg64_reg64(&mut self.buf, src_reg, src_reg, dst_reg);
-
-                // we may need to mask out other bits in the end? but a boolean should be 0 or 1.
-                // if that invariant is upheld, this mask should not be required
-                // ASM::and_reg64_reg64_reg64(&mut self.buf, src_reg, src_reg, dst_reg);
-
-                ASM::mov_reg64_reg64(&mut self.buf, dst_reg, src_reg);
+                ASM::xor_reg64_reg64_reg64(&mut self.buf, dst_reg, src_reg, dst_reg);
             }
             x => todo!("Not: layout, {:?}", x),
         }
@@ -2834,19 +3814,27 @@ impl<
             .storage_manager
             .load_to_general_reg(&mut self.buf, index);
         let ret_stack_size = self.layout_interner.stack_size(*ret_layout);
-        // TODO: This can be optimized with smarter instructions.
-        // Also can probably be moved into storage manager at least partly.
+        // TODO: This can probably be moved into storage manager at least partly.
         self.storage_manager.with_tmp_general_reg(
             &mut self.buf,
             |storage_manager, buf, list_ptr| {
                 ASM::mov_reg64_base32(buf, list_ptr, base_offset);
                 storage_manager.with_tmp_general_reg(buf, |storage_manager, buf, tmp| {
-                    // calculate `element_width * index`
-                    ASM::mov_reg64_imm64(buf, tmp, ret_stack_size as i64);
-                    ASM::imul_reg64_reg64_reg64(buf, tmp, tmp, index_reg);
-
-                    // add the offset to the list pointer, store in `tmp`
-                    ASM::add_reg64_reg64_reg64(buf, tmp, tmp, list_ptr);
+                    // calculate `list_ptr + element_width * index` into `tmp`
+                    match ret_stack_size {
+                        1 | 2 | 4 | 8 => ASM::add_scaled_reg64_reg64_reg64(
+                            buf,
+                            tmp,
+                            list_ptr,
+                            index_reg,
+                            ret_stack_size as u8,
+                        ),
+                        _ => {
+                            ASM::mov_reg64_imm64(buf, tmp, ret_stack_size as i64);
+                            ASM::imul_reg64_reg64_reg64(buf, tmp, tmp, index_reg);
+                            ASM::add_reg64_reg64_reg64(buf, tmp, tmp, list_ptr);
+                        }
+                    }
                     let element_ptr = tmp;
 
                     Self::ptr_read(
@@ -3251,6 +4239,17 @@ impl<
             .storage_manager
             .load_to_general_reg(&mut self.buf, &allocation_symbol);
 
+        // Large lists of words are written with non-temporal stores so they don't evict everything else.
+        let streaming = self
+            .env
+            .options
+            .streaming_write_min_bytes
+            .is_some_and(|min| data_bytes >= min)
+            && matches!(
+                element_layout,
+                LayoutRepr::I64 | LayoutRepr::U64 | pointer_layouts!()
+            );
+
         // Copy everything into output array.
         let mut element_offset = 0;
         for elem in element_symbols {
@@ -3258,16 +4257,23 @@ impl<
                 Origin::S(s) | Origin::L(s) => s,
             };
 
-            Self::ptr_write(
-                &mut self.buf,
-                &mut self.storage_manager,
-                self.layout_interner,
-                ptr_reg,
-                element_offset,
-                element_width,
-                element_layout,
-                element_symbol,
-            );
+            if streaming {
+                let sym_reg = self
+                    .storage_manager
+                    .load_to_general_reg(&mut self.buf, &element_symbol);
+                ASM::movnti_mem64_offset32_reg64(&mut self.buf, ptr_reg, element_offset, sym_reg);
+            } else {
+                Self::ptr_write(
+                    &mut self.buf,
+                    &mut self.storage_manager,
+                    self.layout_interner,
+                    ptr_reg,
+                    element_offset,
+                    element_width,
+                    element_layout,
+                    element_symbol,
+                );
+            }
 
             element_offset += element_width as i32;
             if let Origin::L(element_symbol) = elem {
@@ -3275,6 +4281,10 @@ impl<
             }
         }
 
+        if streaming {
+            ASM::store_fence(&mut self.buf);
+        }
+
         // Setup list on stack.
         self.storage_manager.with_tmp_general_reg(
             &mut self.buf,
@@ -3309,6 +4319,10 @@ impl<
         index: u64,
         field_layouts: &'a [InLayout<'a>],
     ) {
+        if self.load_field_from_return_regs(sym, structure, index, field_layouts) {
+            return;
+        }
+
         self.storage_manager.load_field_at_index(
             self.layout_interner,
             sym,
@@ -4253,6 +5267,10 @@ impl<
     }
 
     fn load_literal(&mut self, sym: &Symbol, layout: &InLayout<'a>, lit: &Literal<'a>) {
+        if self.rematerialize_cost(lit, *layout).is_some() {
+            self.loaded_literals.insert(*sym, (*lit, *layout));
+        }
+
         let layout = self.layout_interner.get_repr(*layout);
 
         if let LayoutRepr::LambdaSet(lambda_set) = layout {
@@ -4305,8 +5323,7 @@ impl<
             }
             (Literal::Byte(x), LayoutRepr::Builtin(Builtin::Int(IntWidth::U8 | IntWidth::I8))) => {
                 let reg = self.storage_manager.claim_general_reg(&mut self.buf, sym);
-                let val = *x;
-                ASM::mov_reg64_imm64(&mut self.buf, reg, val as i64);
+                ASM::movzx_reg64_imm8(&mut self.buf, reg, *x);
             }
             (Literal::Bool(x), LayoutRepr::Builtin(Builtin::Bool)) => {
                 let reg = self.storage_manager.claim_general_reg(&mut self.buf, sym);
@@ -4319,7 +5336,7 @@ impl<
             }
             (Literal::Float(x), LayoutRepr::Builtin(Builtin::Float(FloatWidth::F32))) => {
                 let freg = self.storage_manager.claim_float_reg(&mut self.buf, sym);
-                let val = *x as f32;
+                let val = f32_literal(*x);
                 ASM::mov_freg32_imm32(&mut self.buf, &mut self.relocs, freg, val);
             }
             (Literal::Decimal(bytes), LayoutRepr::Builtin(Builtin::Decimal)) => {
@@ -4346,8 +5363,27 @@ impl<
                 );
             }
             (Literal::Str(x), LayoutRepr::Builtin(Builtin::Str)) => {
-                if x.len() < 24 {
-                    // Load small string.
+                if x.is_empty() {
+                    // The empty small string is all zeroes apart from the small string bit.
+                    let base_offset = self.storage_manager.claim_stack_area_layout(
+                        self.layout_interner,
+                        *sym,
+                        Layout::STR,
+                    );
+
+                    self.storage_manager.with_tmp_general_reg(
+                        &mut self.buf,
+                        |_storage_manager, buf, reg| {
+                            ASM::mov_reg64_imm64(buf, reg, 0);
+                            ASM::mov_base32_reg64(buf, base_offset, reg);
+                            ASM::mov_base32_reg64(buf, base_offset + 8, reg);
+                            ASM::mov_reg64_imm64(buf, reg, i64::MIN);
+                            ASM::mov_base32_reg64(buf, base_offset + 16, reg);
+                        },
+                    );
+                } else if x.len() < 24 {
+                    // Load small string: the bytes are stored inline, and the last byte holds the
+                    // length with the high bit set to mark the string as small.
                     self.storage_manager.with_tmp_general_reg(
                         &mut self.buf,
                         |storage_manager, buf, reg| {
@@ -4395,7 +5431,11 @@ impl<
     }
 
     fn free_symbol(&mut self, sym: &Symbol) {
+        // A literal that was never loaded has no storage, so there is nothing else to free.
+        self.literal_map.remove(sym);
+        self.loaded_literals.remove(sym);
         self.join_map.remove(&JoinPointId(*sym));
+        self.join_locations.remove(&JoinPointId(*sym));
         self.storage_manager.free_symbol(sym);
     }
 
@@ -4405,18 +5445,10 @@ impl<
             // Just load it to the correct type of reg as a stand alone value.
             match repr {
                 single_register_integers!() | pointer_layouts!() => {
-                    self.storage_manager.load_to_specified_general_reg(
-                        &mut self.buf,
-                        sym,
-                        CC::GENERAL_RETURN_REGS[0],
-                    );
+                    self.load_symbol_to_general_reg(CC::GENERAL_RETURN_REGS[0], sym);
                 }
                 single_register_floats!() => {
-                    self.storage_manager.load_to_specified_float_reg(
-                        &mut self.buf,
-                        sym,
-                        CC::FLOAT_RETURN_REGS[0],
-                    );
+                    self.load_symbol_to_float_reg(CC::FLOAT_RETURN_REGS[0], sym);
                 }
                 LayoutRepr::LambdaSet(lambda_set) => {
                     self.return_symbol(sym, &lambda_set.runtime_representation())
@@ -4442,13 +5474,7 @@ impl<
                 layout,
             )
         }
-        let inst_loc = self.buf.len() as u64;
-        let offset = ASM::jmp_imm32(&mut self.buf, JUMP_PLACEHOLDER) as u64;
-        self.relocs.push(Relocation::JmpToReturn {
-            inst_loc,
-            inst_size: self.buf.len() as u64 - inst_loc,
-            offset,
-        });
+        self.jmp_to_return();
     }
 
     fn build_int_bitwise_and(
@@ -4518,23 +5544,11 @@ impl<
         src2: &Symbol,
         int_width: IntWidth,
     ) {
-        let buf = &mut self.buf;
-
         match int_width {
             IntWidth::U128 | IntWidth::I128 => todo!(),
-            _ => {
-                let dst_reg = self.storage_manager.claim_general_reg(buf, dst);
-                let src1_reg = self.storage_manager.load_to_general_reg(buf, src1);
-                let src2_reg = self.storage_manager.load_to_general_reg(buf, src2);
-
-                ASM::shl_reg64_reg64_reg64(
-                    buf,
-                    &mut self.storage_manager,
-                    dst_reg,
-                    src1_reg,
-                    src2_reg,
-                );
-            }
+            _ => self.build_shift_by_reg(dst, src1, src2, |buf, dst_reg, src1_reg, src2_reg| {
+                ASM::shl_reg64_reg64_reg64(buf, dst_reg, src1_reg, src2_reg)
+            }),
         }
     }
 
@@ -4545,62 +5559,26 @@ impl<
         src2: &Symbol,
         int_width: IntWidth,
     ) {
-        let buf = &mut self.buf;
-
         match int_width {
             IntWidth::U128 | IntWidth::I128 => todo!(),
             _ => {
-                let dst_reg = self.storage_manager.claim_general_reg(buf, dst);
-                let src1_reg = self.storage_manager.load_to_general_reg(buf, src1);
-                let src2_reg = self.storage_manager.load_to_general_reg(buf, src2);
-
                 // to get sign extension "for free", we move our bits to the left
                 // so the integers sign bit is stored in the register's sign bit.
                 // Then we arithmetic shift right, getting the correct sign extension behavior,
                 // then shift logical right to get the bits back into the position they should
                 // be for our particular integer width
-                let sign_extend_shift_amount = 64 - (int_width.stack_size() as i64 * 8);
-
-                if sign_extend_shift_amount > 0 {
-                    self.storage_manager.with_tmp_general_reg(
-                        buf,
-                        |storage_manager, buf, tmp_reg| {
-                            ASM::mov_reg64_imm64(buf, tmp_reg, sign_extend_shift_amount);
-                            ASM::shl_reg64_reg64_reg64(
-                                buf,
-                                storage_manager,
-                                src1_reg,
-                                src1_reg,
-                                tmp_reg,
-                            );
-                        },
-                    )
-                }
-
-                ASM::sar_reg64_reg64_reg64(
-                    buf,
-                    &mut self.storage_manager,
-                    dst_reg,
-                    src1_reg,
-                    src2_reg,
-                );
-
-                if sign_extend_shift_amount > 0 {
-                    // shift back if needed
-                    self.storage_manager.with_tmp_general_reg(
-                        &mut self.buf,
-                        |storage_manager, buf, tmp_reg| {
-                            ASM::mov_reg64_imm64(buf, tmp_reg, sign_extend_shift_amount);
-                            ASM::shr_reg64_reg64_reg64(
-                                buf,
-                                storage_manager,
-                                dst_reg,
-                                dst_reg,
-                                tmp_reg,
-                            );
-                        },
-                    )
-                }
+                let sign_extend_shift_amount = 64 - (int_width.stack_size() as u8 * 8);
+
+                self.build_shift_by_reg(dst, src1, src2, |buf, dst_reg, src1_reg, src2_reg| {
+                    if sign_extend_shift_amount > 0 {
+                        ASM::shl_reg64_reg64_imm8(buf, dst_reg, src1_reg, sign_extend_shift_amount);
+                        ASM::sar_reg64_reg64_reg64(buf, dst_reg, dst_reg, src2_reg);
+                        // shift back
+                        ASM::shr_reg64_reg64_imm8(buf, dst_reg, dst_reg, sign_extend_shift_amount);
+                    } else {
+                        ASM::sar_reg64_reg64_reg64(buf, dst_reg, src1_reg, src2_reg);
+                    }
+                })
             }
         }
     }
@@ -4612,8 +5590,6 @@ impl<
         src2: &Symbol,
         int_width: IntWidth,
     ) {
-        let buf = &mut self.buf;
-
         match int_width {
             IntWidth::U128 | IntWidth::I128 => {
                 let layout = match int_width {
@@ -4630,211 +5606,964 @@ impl<
                     &layout,
                 );
             }
+            _ => self.build_shift_by_reg(dst, src1, src2, |buf, dst_reg, src1_reg, src2_reg| {
+                ASM::shr_reg64_reg64_reg64(buf, dst_reg, src1_reg, src2_reg)
+            }),
+        }
+    }
+
+    fn build_num_sqrt(&mut self, dst: Symbol, src: Symbol, float_width: FloatWidth) {
+        let buf = &mut self.buf;
+
+        let dst_reg = self.storage_manager.claim_float_reg(buf, &dst);
+        let src_reg = self.storage_manager.load_to_float_reg(buf, &src);
+
+        match float_width {
+            FloatWidth::F32 => ASM::sqrt_freg32_freg32(buf, dst_reg, src_reg),
+            FloatWidth::F64 => ASM::sqrt_freg64_freg64(buf, dst_reg, src_reg),
+        }
+    }
+
+    fn build_num_int_cast(
+        &mut self,
+        dst: &Symbol,
+        src: &Symbol,
+        source: IntWidth,
+        target: IntWidth,
+    ) {
+        use IntWidth::*;
+
+        let buf = &mut self.buf;
+
+        match (source, target) {
+            (U128 | I128, U128 | I128) => {
+                let to_offset = self.storage_manager.claim_stack_area_layout(
+                    self.layout_interner,
+                    *dst,
+                    Layout::from_int_width(target),
+                );
+
+                let (from_offset, size) = self.storage_manager.stack_offset_and_size(src);
+
+                self.storage_manager
+                    .copy_to_stack_offset(buf, size, from_offset, to_offset);
+            }
+            (U128 | I128, _) => {
+                let dst_reg = self.storage_manager.claim_general_reg(buf, dst);
+
+                // truncating only ever keeps bits of the lower 8 bytes
+                let (offset, _size) = self.storage_manager.stack_offset_and_size(src);
+                ASM::mov_reg64_base32(buf, dst_reg, offset);
+
+                if target.stack_size() < 8 {
+                    Self::extend_int_reg(buf, dst_reg, dst_reg, target);
+                }
+            }
+            (_, U128 | I128) => {
+                let src_reg = self.storage_manager.load_to_general_reg(buf, src);
+
+                let base_offset = self.storage_manager.claim_stack_area_layout(
+                    self.layout_interner,
+                    *dst,
+                    Layout::from_int_width(target),
+                );
+
+                let tmp = Symbol::DEV_TMP;
+                let tmp_reg = self.storage_manager.claim_general_reg(buf, &tmp);
+
+                // the lower 8 bytes are the source extended to 64 bits
+                Self::extend_int_reg(buf, tmp_reg, src_reg, source);
+                ASM::mov_base32_reg64(buf, base_offset, tmp_reg);
+
+                // the upper 8 bytes are all copies of the sign bit, or zero
+                let tmp_reg = if source.is_signed() {
+                    let shift = Symbol::DEV_TMP2;
+                    let shift_reg = self.storage_manager.claim_general_reg(buf, &shift);
+                    ASM::mov_reg64_imm64(buf, shift_reg, 63);
+                    self.storage_manager.with_general_reg_constraint(
+                        buf,
+                        &shift,
+                        ASM::SHIFT_COUNT_REG,
+                        |storage_manager, buf, shift_reg| {
+                            let tmp_reg = storage_manager.load_to_general_reg(buf, &tmp);
+                            ASM::sar_reg64_reg64_reg64(buf, tmp_reg, tmp_reg, shift_reg);
+                        },
+                    );
+                    self.free_symbol(&shift);
+                    self.storage_manager
+                        .load_to_general_reg(&mut self.buf, &tmp)
+                } else {
+                    ASM::mov_reg64_imm64(buf, tmp_reg, 0);
+                    tmp_reg
+                };
+                ASM::mov_base32_reg64(&mut self.buf, base_offset + 8, tmp_reg);
+
+                self.free_symbol(&tmp);
+            }
             _ => {
                 let dst_reg = self.storage_manager.claim_general_reg(buf, dst);
-                let src1_reg = self.storage_manager.load_to_general_reg(buf, src1);
-                let src2_reg = self.storage_manager.load_to_general_reg(buf, src2);
+                let src_reg = self.storage_manager.load_to_general_reg(buf, src);
 
-                ASM::shr_reg64_reg64_reg64(
-                    buf,
-                    &mut self.storage_manager,
-                    dst_reg,
-                    src1_reg,
-                    src2_reg,
+                // Widening extends according to the source's signedness. Truncating keeps the low
+                // bits and then extends them according to the target's signedness, so the
+                // register holds the value the same way a load of the target type would.
+                let width = if target.stack_size() > source.stack_size() {
+                    source
+                } else {
+                    target
+                };
+                Self::extend_int_reg(buf, dst_reg, src_reg, width);
+            }
+        }
+    }
+
+    /// Builds a shift of `src1` by `src2` into `dst`.
+    /// The shift count is allocated to the register the architecture requires it in, if any.
+    fn build_shift_by_reg(
+        &mut self,
+        dst: &Symbol,
+        src1: &Symbol,
+        src2: &Symbol,
+        shift: impl FnOnce(&mut Vec<'a, u8>, GeneralReg, GeneralReg, GeneralReg),
+    ) {
+        self.storage_manager.with_general_reg_constraint(
+            &mut self.buf,
+            src2,
+            ASM::SHIFT_COUNT_REG,
+            |storage_manager, buf, src2_reg| {
+                let dst_reg = storage_manager.claim_general_reg(buf, dst);
+                let src1_reg = storage_manager.load_to_general_reg(buf, src1);
+                shift(buf, dst_reg, src1_reg, src2_reg);
+            },
+        );
+    }
+
+    /// Moves the low `width` bits of `src` into `dst`, sign- or zero-extended to 64 bits
+    /// depending on the signedness of `width`.
+    fn extend_int_reg(buf: &mut Vec<'a, u8>, dst: GeneralReg, src: GeneralReg, width: IntWidth) {
+        let register_width =
+            RegisterWidth::try_from_layout(LayoutRepr::Builtin(Builtin::Int(width))).unwrap();
+
+        if width.is_signed() {
+            ASM::movsx_reg_reg(buf, register_width, dst, src);
+        } else {
+            ASM::movzx_reg_reg(buf, register_width, dst, src);
+        }
+    }
+}
+
+/// This impl block is for ir related instructions that need backend specific information.
+/// For example, loading a symbol for doing a computation.
+impl<
+        'a,
+        'r,
+        FloatReg: RegTrait,
+        GeneralReg: RegTrait,
+        ASM: Assembler<GeneralReg, FloatReg>,
+        CC: CallConv<GeneralReg, FloatReg, ASM>,
+    > Backend64Bit<'a, 'r, GeneralReg, FloatReg, ASM, CC>
+{
+    /// Like `finalize`, but appends the code to `out` instead of a new arena `Vec`, so the caller
+    /// decides where it goes. Relocation offsets are from where the proc starts in `out`.
+    pub fn finalize_into<S: CodeSink>(&mut self, out: &mut S) -> Vec<'a, Relocation> {
+        let mut prologue = bumpalo::vec![in self.env.arena];
+
+        let mut profiling_relocs = bumpalo::vec![in self.env.arena];
+        if self.env.options.profiling {
+            // The hook runs before the frame exists, which only works where the call leaves the
+            // return address alone, and only x86_64 ELF toolchains provide `__fentry__`.
+            if self.target != Target::LinuxX64 {
+                user_error!(
+                    "Profiling with the dev backend is only supported on x86_64 Linux, not {}.",
+                    self.target
                 );
             }
+            ASM::call(
+                &mut prologue,
+                &mut profiling_relocs,
+                PROFILING_HOOK.to_string(),
+            );
+        }
+        let aligned_stack_size = self.emit_prologue(&mut prologue);
+        let setup_offset = prologue.len();
+
+        // Deal with jumps to the return address.
+        let old_relocs = std::mem::replace(&mut self.relocs, bumpalo::vec![in self.env.arena]);
+
+        // Check if their is an unnessary jump to return right at the end of the function.
+        let mut end_jmp_size = 0;
+        for reloc in old_relocs
+            .iter()
+            .filter(|reloc| matches!(reloc, Relocation::JmpToReturn { .. }))
+        {
+            if let Relocation::JmpToReturn {
+                inst_loc,
+                inst_size,
+                ..
+            } = reloc
+            {
+                if *inst_loc as usize + *inst_size as usize == self.buf.len() {
+                    end_jmp_size = *inst_size as usize;
+                    break;
+                }
+            }
+        }
+
+        // Update jumps to returns.
+        let ret_offset = self.buf.len() - end_jmp_size;
+        for reloc in old_relocs
+            .iter()
+            .filter(|reloc| matches!(reloc, Relocation::JmpToReturn { .. }))
+        {
+            if let Relocation::JmpToReturn {
+                inst_loc,
+                inst_size,
+                offset,
+            } = reloc
+            {
+                if *inst_loc as usize + *inst_size as usize != self.buf.len() {
+                    ASM::update_jmp_imm32_offset(
+                        &mut self.buf,
+                        *inst_loc,
+                        *offset,
+                        ret_offset as u64,
+                    );
+                }
+            }
+        }
+
+        // Calls in tail position tear down the frame like the epilogue does, then jump to the
+        // callee instead of returning. These go after the epilogue.
+        // A body that only ever leaves through tail calls never returns by itself.
+        let body_size = self.buf.len() - end_jmp_size;
+        let mut epilogue = bumpalo::vec![in self.env.arena];
+        let mut stack_check_relocs = bumpalo::vec![in self.env.arena];
+        let returns = self.reachable
+            || old_relocs
+                .iter()
+                .any(|reloc| matches!(reloc, Relocation::JmpToReturn { .. }));
+        if returns {
+            if let Some(canary_offset) = self.canary_slot {
+                let epilogue_start = (setup_offset + body_size) as u64;
+                self.emit_stack_check(
+                    &mut epilogue,
+                    &mut stack_check_relocs,
+                    epilogue_start,
+                    canary_offset,
+                );
+            }
+            self.emit_epilogue(&mut epilogue, aligned_stack_size);
+        }
+
+        let mut tail_call_stubs = bumpalo::vec![in self.env.arena];
+        let mut tail_call_relocs = bumpalo::vec![in self.env.arena];
+        let tail_calls = std::mem::replace(&mut self.tail_calls, bumpalo::vec![in self.env.arena]);
+        for (inst_loc, offset, fn_name) in tail_calls {
+            let stub_offset = body_size + epilogue.len() + tail_call_stubs.len();
+            ASM::update_jmp_imm32_offset(&mut self.buf, inst_loc, offset, stub_offset as u64);
+
+            if let Some(canary_offset) = self.canary_slot {
+                let stubs_start = (setup_offset + body_size + epilogue.len()) as u64;
+                self.emit_stack_check(
+                    &mut tail_call_stubs,
+                    &mut stack_check_relocs,
+                    stubs_start,
+                    canary_offset,
+                );
+            }
+            self.emit_frame_cleanup(&mut tail_call_stubs, aligned_stack_size);
+            let jmp_offset = ASM::tail_call(&mut tail_call_stubs);
+            tail_call_relocs.push(Relocation::LinkedFunction {
+                offset: (setup_offset + body_size + epilogue.len()) as u64 + jmp_offset,
+                name: fn_name,
+            });
+        }
+
+        // Add function body.
+        out.write(&prologue);
+        out.write(&self.buf[..body_size]);
+        out.write(&epilogue);
+        out.write(&tail_call_stubs);
+
+        // Update other relocs to include stack setup offset.
+        let mut out_relocs = profiling_relocs;
+        out_relocs.extend(
+            old_relocs
+                .into_iter()
+                .filter(|reloc| !matches!(reloc, Relocation::JmpToReturn { .. }))
+                .map(|reloc| match reloc {
+                    Relocation::LocalData {
+                        offset,
+                        data,
+                        align,
+                    } => Relocation::LocalData {
+                        offset: offset + setup_offset as u64,
+                        data,
+                        align,
+                    },
+                    Relocation::LinkedData {
+                        offset,
+                        name,
+                        addend,
+                    } => Relocation::LinkedData {
+                        offset: offset + setup_offset as u64,
+                        name,
+                        addend,
+                    },
+                    Relocation::LinkedFunction { offset, name } => Relocation::LinkedFunction {
+                        offset: offset + setup_offset as u64,
+                        name,
+                    },
+                    Relocation::JmpToReturn { .. } => unreachable!(),
+                }),
+        );
+        out_relocs.extend(stack_check_relocs);
+        out_relocs.extend(tail_call_relocs);
+        out_relocs
+    }
+
+    /// Whether the prologue has to move the stack pointer to make room for the frame.
+    /// A leaf proc whose frame fits in the red zone can leave it where it is.
+    fn requires_stack_modification(
+        &self,
+        saved_general_regs: &[GeneralReg],
+        saved_float_regs: &[FloatReg],
+    ) -> bool {
+        let frame_size = self.storage_manager.stack_size() as usize
+            + 8 * saved_general_regs.len()
+            + 16 * saved_float_regs.len();
+        let is_leaf = !self.makes_calls && self.storage_manager.fn_call_stack_size() == 0;
+
+        !(self.red_zone_allowed && is_leaf && frame_size <= CC::RED_ZONE_SIZE as usize)
+    }
+
+    fn build_fn_call_stack_return<const N: usize>(
+        &mut self,
+        function_name: String,
+        arguments: &[Symbol; N],
+        layouts: &[InLayout<'a>; N],
+        ret_layout: InLayout<'a>,
+        dst: Symbol,
+    ) {
+        // Setup the return location.
+        let base_offset =
+            self.storage_manager
+                .claim_stack_area_layout(self.layout_interner, dst, ret_layout);
+
+        let tmp = self.debug_symbol("call_with_stack_return_result");
+
+        self.build_fn_call(
+            &tmp,
+            function_name,
+            arguments.as_slice(),
+            layouts.as_slice(),
+            &ret_layout,
+        );
+
+        // Return list value from fn call
+        self.storage_manager.copy_symbol_to_stack_offset(
+            self.layout_interner,
+            &mut self.buf,
+            base_offset,
+            &tmp,
+            &ret_layout,
+        );
+
+        self.free_symbol(&tmp);
+    }
+
+    fn clear_tag_id(&mut self, ptr_reg: GeneralReg) -> (Symbol, GeneralReg) {
+        let unmasked_symbol = self.debug_symbol("unmasked");
+        let unmasked_reg = self
+            .storage_manager
+            .claim_general_reg(&mut self.buf, &unmasked_symbol);
+
+        ASM::mov_reg64_imm64(&mut self.buf, unmasked_reg, (!0b111) as _);
+
+        ASM::and_reg64_reg64_reg64(&mut self.buf, unmasked_reg, ptr_reg, unmasked_reg);
+
+        (unmasked_symbol, unmasked_reg)
+    }
+
+    /// Panics with `msg` if the preceding arithmetic instruction overflowed.
+    fn trap_if_overflow(&mut self, msg: &'a str) {
+        // The panic is only built on one path, and it never returns.
+        // So the code after it must not see any of its changes to the storage.
+        let mut base_storage = self.storage_manager.clone();
+        let base_literal_map = self.literal_map.clone();
+
+        // Jump over the unconditional jump below, straight into the panic.
+        let jo_location = self.buf.len();
+        let jo_start_offset = ASM::jo_imm32(&mut self.buf, 0);
+
+        let jmp_location = self.buf.len();
+        let jmp_start_offset = ASM::jmp_imm32(&mut self.buf, JUMP_PLACEHOLDER);
+
+        let mut tmp = bumpalo::vec![in self.env.arena];
+        ASM::jo_imm32(&mut tmp, (self.buf.len() - jo_start_offset) as i32);
+        for (i, byte) in tmp.iter().enumerate() {
+            self.buf[jo_location + i] = *byte;
+        }
+
+        let msg_symbol = self.debug_symbol("overflow_message");
+        self.load_literal(&msg_symbol, &Layout::STR, &Literal::Str(msg));
+        self.roc_panic(msg_symbol, CrashTag::Roc);
+
+        // Update important storage information to avoid overwrites.
+        base_storage.update_stack_size(self.storage_manager.stack_size());
+        base_storage.update_fn_call_stack_size(self.storage_manager.fn_call_stack_size());
+        base_storage
+            .used_callee_saved_regs
+            .extend(&self.storage_manager.used_callee_saved_regs);
+
+        self.storage_manager = base_storage;
+        self.literal_map = base_literal_map;
+
+        let end_offset = self.buf.len();
+        ASM::update_jmp_imm32_offset(
+            &mut self.buf,
+            jmp_location as u64,
+            jmp_start_offset as u64,
+            end_offset as u64,
+        );
+    }
+
+    /// Panics with `msg` if the integer `sym` is zero.
+    fn trap_if_zero(&mut self, sym: &Symbol, msg: &'a str) {
+        let reg = self.storage_manager.load_to_general_reg(&mut self.buf, sym);
+
+        // The panic is only built on one path, and it never returns.
+        // So the code after it must not see any of its changes to the storage.
+        let mut base_storage = self.storage_manager.clone();
+        let base_literal_map = self.literal_map.clone();
+
+        // Jump over the panic unless the value is zero.
+        // Since we don't know the offset yet, set it to 0 and overwrite later.
+        let jne_location = self.buf.len();
+        let start_offset =
+            ASM::jne_reg64_imm64_imm32(&mut self.buf, &mut self.storage_manager, reg, 0, 0);
+
+        let msg_symbol = self.debug_symbol("zero_message");
+        self.load_literal(&msg_symbol, &Layout::STR, &Literal::Str(msg));
+        self.roc_panic(msg_symbol, CrashTag::Roc);
+
+        // Update important storage information to avoid overwrites.
+        base_storage.update_stack_size(self.storage_manager.stack_size());
+        base_storage.update_fn_call_stack_size(self.storage_manager.fn_call_stack_size());
+        base_storage
+            .used_callee_saved_regs
+            .extend(&self.storage_manager.used_callee_saved_regs);
+
+        self.storage_manager = base_storage;
+        self.literal_map = base_literal_map;
+
+        let mut tmp = bumpalo::vec![in self.env.arena];
+        let jne_offset = self.buf.len() - start_offset;
+        ASM::jne_reg64_imm64_imm32(
+            &mut tmp,
+            &mut self.storage_manager,
+            reg,
+            0,
+            jne_offset as i32,
+        );
+        for (i, byte) in tmp.iter().enumerate() {
+            self.buf[jne_location + i] = *byte;
+        }
+    }
+
+    /// Jumps by `offset` unless the overflow flag matches the switch branch value `val`.
+    fn jump_unless_overflow(buf: &mut Vec<'_, u8>, val: u64, offset: i32) -> usize {
+        match val {
+            1 => ASM::jno_imm32(buf, offset),
+            0 => ASM::jo_imm32(buf, offset),
+            _ => internal_error!("an overflow flag can only be 0 or 1, not {val}"),
+        }
+    }
+
+    /// Jumps by `offset` unless the Bool in `reg` matches the switch branch value `val`.
+    fn jump_unless_bool(buf: &mut Vec<'_, u8>, reg: GeneralReg, val: u64, offset: i32) -> usize {
+        match val {
+            1 => ASM::jz_reg64_imm32(buf, reg, offset),
+            0 => ASM::jnz_reg64_imm32(buf, reg, offset),
+            _ => internal_error!("a Bool can only be 0 or 1, not {val}"),
+        }
+    }
+
+    /// Within a branch, the overflow is known, so the flag is bound to a constant.
+    /// The flag's field is written too, in case the result struct is used as a whole.
+    fn bind_overflow_flag(&mut self, flag: Symbol, flag_offset: i32, overflowed: bool) {
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |_, buf, reg| {
+                ASM::mov_reg64_imm64(buf, reg, overflowed as i64);
+                ASM::mov_base32_reg8(buf, flag_offset, reg);
+            });
+
+        let literal = self.env.arena.alloc(Literal::Bool(overflowed));
+        if self.env.lazy_literals {
+            self.literal_map
+                .insert(flag, (literal as *const _, &Layout::BOOL as *const _));
+        } else {
+            self.load_literal(&flag, &Layout::BOOL, literal);
+        }
+    }
+
+    /// Like `build_switch`, but branches on the overflow flag left by `build_checked_overflow_branch`
+    /// instead of materializing it with a `seto`.
+    fn build_overflow_switch(
+        &mut self,
+        layout_ids: &mut LayoutIds<'a>,
+        flag: Symbol,
+        flag_offset: i32,
+        branches: &'a [(u64, BranchInfo<'a>, Stmt<'a>)],
+        default_branch: &(BranchInfo<'a>, &'a Stmt<'a>),
+        ret_layout: &InLayout<'a>,
+    ) {
+        let mut base_storage = self.storage_manager.clone();
+        let base_literal_map = self.literal_map.clone();
+
+        let mut max_branch_stack_size = 0;
+        let mut ret_jumps = bumpalo::vec![in self.env.arena];
+        let mut tmp = bumpalo::vec![in self.env.arena];
+        for (val, _branch_info, stmt) in branches.iter() {
+            tmp.clear();
+            // Every branch ends in a jump to the end, so the flags are intact for each of these jumps.
+            let jump_location = self.buf.len();
+            let start_offset = Self::jump_unless_overflow(&mut self.buf, *val, 0);
+
+            self.storage_manager = base_storage.clone();
+            self.literal_map = base_literal_map.clone();
+            self.bind_overflow_flag(flag, flag_offset, *val == 1);
+            self.build_stmt(layout_ids, stmt, ret_layout);
+
+            if self.reachable {
+                let jmp_location = self.buf.len();
+                let jmp_offset = ASM::jmp_imm32(&mut self.buf, JUMP_PLACEHOLDER);
+                ret_jumps.push((jmp_location, jmp_offset));
+            }
+
+            let end_offset = self.buf.len();
+            self.reachable = true;
+            Self::jump_unless_overflow(&mut tmp, *val, (end_offset - start_offset) as i32);
+            for (i, byte) in tmp.iter().enumerate() {
+                self.buf[jump_location + i] = *byte;
+            }
+
+            max_branch_stack_size =
+                std::cmp::max(max_branch_stack_size, self.storage_manager.stack_size());
+            base_storage.update_fn_call_stack_size(self.storage_manager.fn_call_stack_size());
+            base_storage
+                .used_callee_saved_regs
+                .extend(&self.storage_manager.used_callee_saved_regs);
+        }
+        self.storage_manager = base_storage;
+        self.literal_map = base_literal_map;
+        self.storage_manager
+            .update_stack_size(max_branch_stack_size);
+
+        // If both values have a branch, the default is unreachable and the flag is irrelevant.
+        let overflowed = !branches.iter().any(|(val, ..)| *val == 1);
+        self.bind_overflow_flag(flag, flag_offset, overflowed);
+        let (_branch_info, stmt) = default_branch;
+        self.build_stmt(layout_ids, stmt, ret_layout);
+
+        let ret_offset = self.buf.len();
+        if !ret_jumps.is_empty() {
+            self.reachable = true;
+        }
+        for (jmp_location, start_offset) in ret_jumps.into_iter() {
+            ASM::update_jmp_imm32_offset(
+                &mut self.buf,
+                jmp_location as u64,
+                start_offset as u64,
+                ret_offset as u64,
+            );
+        }
+    }
+
+    /// Two scratch registers for the stack protector. They are free in the prologue, where the
+    /// params are still in their registers, and on the way out, where the return value is.
+    fn stack_protector_regs() -> (GeneralReg, GeneralReg) {
+        let mut regs = CC::GENERAL_DEFAULT_FREE_REGS
+            .iter()
+            .rev()
+            .copied()
+            .filter(|reg| {
+                CC::general_caller_saved(reg)
+                    && !CC::GENERAL_PARAM_REGS.contains(reg)
+                    && !CC::GENERAL_RETURN_REGS.contains(reg)
+            });
+        match (regs.next(), regs.next()) {
+            (Some(guard_reg), Some(canary_reg)) => (guard_reg, canary_reg),
+            _ => internal_error!(
+                "{} has no scratch registers for the stack protector",
+                CC::NAME
+            ),
+        }
+    }
+
+    /// Compares the canary against the guard and calls `__stack_chk_fail` if they differ.
+    /// `out_start` is the offset of `out` in the finished proc, for the relocation of the call.
+    fn emit_stack_check(
+        &self,
+        out: &mut Vec<'a, u8>,
+        relocs: &mut Vec<'a, Relocation>,
+        out_start: u64,
+        canary_offset: i32,
+    ) {
+        let (guard_reg, canary_reg) = Self::stack_protector_regs();
+        ASM::load_stack_guard(out, guard_reg);
+        ASM::mov_reg64_base32(out, canary_reg, canary_offset);
+        ASM::xor_reg64_reg64_reg64(out, guard_reg, guard_reg, canary_reg);
+
+        // Skip the call if they match. Since we don't know the offset yet, set it to 0 and
+        // overwrite later.
+        let jz_location = out.len();
+        let start_offset = ASM::jz_reg64_imm32(out, guard_reg, 0);
+
+        let mut call_relocs = bumpalo::vec![in self.env.arena];
+        ASM::call(out, &mut call_relocs, STACK_CHK_FAIL.to_string());
+        relocs.extend(call_relocs.into_iter().map(|reloc| match reloc {
+            Relocation::LinkedFunction { offset, name } => Relocation::LinkedFunction {
+                offset: offset + out_start,
+                name,
+            },
+            _ => internal_error!("a call should only need a function relocation"),
+        }));
+
+        let mut tmp = bumpalo::vec![in self.env.arena];
+        ASM::jz_reg64_imm32(&mut tmp, guard_reg, (out.len() - start_offset) as i32);
+        for (i, byte) in tmp.iter().enumerate() {
+            out[jz_location + i] = *byte;
+        }
+    }
+
+    /// Writes the entry sequence of the proc being finalized to `out`: the convention's prologue,
+    /// plus the stack protector's canary if the proc has one.
+    /// It returns the size of the frame, which is passed on to `emit_epilogue`.
+    fn emit_prologue(&mut self, out: &mut Vec<'a, u8>) -> i32 {
+        let canary_slot = self.canary_slot;
+        if canary_slot.is_some() {
+            // A failed check calls out, so the frame can't live in the red zone.
+            self.makes_calls = true;
+        }
+
+        let (used_general_regs, used_float_regs) = self
+            .storage_manager
+            .used_callee_saved_regs
+            .as_vecs(self.env.arena);
+
+        let frame_start = out.len();
+        let aligned_stack_size = CC::emit_prologue(
+            out,
+            &used_general_regs,
+            &used_float_regs,
+            self.storage_manager.stack_size() as i32,
+            self.storage_manager.fn_call_stack_size() as i32,
+            self.requires_stack_modification(&used_general_regs, &used_float_regs),
+        );
+        if let Some(canary_offset) = canary_slot {
+            let (guard_reg, _) = Self::stack_protector_regs();
+            ASM::load_stack_guard(out, guard_reg);
+            ASM::mov_base32_reg64(out, canary_offset, guard_reg);
         }
+        self.unwind_info = CC::unwind_info(
+            out,
+            frame_start,
+            &used_general_regs,
+            &used_float_regs,
+            aligned_stack_size,
+            self.storage_manager.fn_call_stack_size() as i32,
+        );
+        aligned_stack_size
     }
 
-    fn build_num_sqrt(&mut self, dst: Symbol, src: Symbol, float_width: FloatWidth) {
-        let buf = &mut self.buf;
-
-        let dst_reg = self.storage_manager.claim_float_reg(buf, &dst);
-        let src_reg = self.storage_manager.load_to_float_reg(buf, &src);
+    /// Writes the exit sequence that undoes `emit_prologue` to `out`, including the return.
+    fn emit_epilogue(&mut self, out: &mut Vec<'a, u8>, frame_size: i32) {
+        let (used_general_regs, used_float_regs) = self
+            .storage_manager
+            .used_callee_saved_regs
+            .as_vecs(self.env.arena);
 
-        match float_width {
-            FloatWidth::F32 => ASM::sqrt_freg32_freg32(buf, dst_reg, src_reg),
-            FloatWidth::F64 => ASM::sqrt_freg64_freg64(buf, dst_reg, src_reg),
+        CC::emit_epilogue(
+            out,
+            &used_general_regs,
+            &used_float_regs,
+            frame_size,
+            self.storage_manager.fn_call_stack_size() as i32,
+            self.requires_stack_modification(&used_general_regs, &used_float_regs),
+            self.storage_manager.stack_args_size(),
+        );
+        if self.env.options.trap_unreachable {
+            ASM::unreachable(out);
         }
     }
 
-    fn build_num_int_cast(
+    /// Restores the callee saved registers and the caller's frame, leaving the return address
+    /// on top of the stack.
+    fn emit_frame_cleanup(&mut self, out: &mut Vec<'a, u8>, frame_size: i32) {
+        let (used_general_regs, used_float_regs) = self
+            .storage_manager
+            .used_callee_saved_regs
+            .as_vecs(self.env.arena);
+
+        CC::cleanup_stack(
+            out,
+            &used_general_regs,
+            &used_float_regs,
+            frame_size,
+            self.storage_manager.fn_call_stack_size() as i32,
+            self.requires_stack_modification(&used_general_regs, &used_float_regs),
+        );
+    }
+
+    /// Puts `args` where the callee expects them, after saving everything a call may clobber.
+    fn set_up_call(
         &mut self,
         dst: &Symbol,
-        src: &Symbol,
-        source: IntWidth,
-        target: IntWidth,
+        args: &[Symbol],
+        arg_layouts: &[InLayout<'a>],
+        ret_layout: &InLayout<'a>,
     ) {
-        use IntWidth::*;
-
-        let buf = &mut self.buf;
-
-        match (source, target) {
-            (U128, U64) => {
-                let dst_reg = self.storage_manager.claim_general_reg(buf, dst);
+        debug_assert_eq!(args.len(), arg_layouts.len());
 
-                let (offset, _size) = self.storage_manager.stack_offset_and_size(src);
+        self.forget_cheap_literals(args);
+        let last_use_args =
+            std::mem::replace(&mut self.last_use_args, bumpalo::vec![in self.env.arena]);
 
-                ASM::mov_reg64_base32(buf, dst_reg, offset + 8);
+        if cfg!(feature = "register-allocator") {
+            // Save used caller saved regs.
+            self.storage_manager
+                .push_used_caller_saved_regs_to_stack(&mut self.buf);
+        } else {
+            // Spill every live value, whatever register it is in, and reload it when next used.
+            // This is simple enough to be obviously correct, at the cost of extra stack traffic.
+            // Arguments that are dead after the call are never used again, so they can stay put,
+            // e.g. the result of `f` in `g (f x)` goes from the return register to a param register.
+            self.storage_manager
+                .free_all_to_stack_except(&mut self.buf, &last_use_args);
+        }
 
-                return;
-            }
-            (U64, U128) => {
-                let src_reg = self.storage_manager.load_to_general_reg(buf, src);
+        // Put values in param regs or on top of the stack.
+        // Without arguments, this only sets up the return pointer and shadow space, if any.
+        // A result returned through memory gets its area for `dst` here, even if it goes unused.
+        CC::store_args(
+            &mut self.buf,
+            &mut self.storage_manager,
+            self.layout_interner,
+            dst,
+            args,
+            arg_layouts,
+            ret_layout,
+        );
+    }
 
-                let base_offset = self.storage_manager.claim_stack_area_layout(
-                    self.layout_interner,
-                    *dst,
-                    Layout::U128,
-                );
+    /// Jumps to the epilogue, once the return value is in place.
+    fn jmp_to_return(&mut self) {
+        let inst_loc = self.buf.len() as u64;
+        let offset = ASM::jmp_imm32(&mut self.buf, JUMP_PLACEHOLDER) as u64;
+        self.relocs.push(Relocation::JmpToReturn {
+            inst_loc,
+            inst_size: self.buf.len() as u64 - inst_loc,
+            offset,
+        });
+        self.reachable = false;
+    }
 
-                let tmp = Symbol::DEV_TMP;
-                let tmp_reg = self.storage_manager.claim_general_reg(buf, &tmp);
+    /// Loads field `index` of `structure` straight from the return registers, if the struct
+    /// was just returned in them and the field is an integer within one of them.
+    /// Returns false if it can't, in which case nothing is emitted.
+    fn load_field_from_return_regs(
+        &mut self,
+        sym: &Symbol,
+        structure: &Symbol,
+        index: u64,
+        field_layouts: &'a [InLayout<'a>],
+    ) -> bool {
+        match self.struct_in_return_regs {
+            Some((returned, len)) if returned == *structure && len == self.buf.len() => {}
+            _ => return false,
+        }
 
-                // move a zero into the lower 8 bytes
-                ASM::mov_reg64_imm64(buf, tmp_reg, 0x0);
-                ASM::mov_base32_reg64(buf, base_offset + 8, tmp_reg);
+        let layout = field_layouts[index as usize];
+        let repr = self.layout_interner.get_repr(layout);
+        let Some(width) = RegisterWidth::try_from_layout(repr) else {
+            return false;
+        };
+        let offset: u32 = field_layouts[..index as usize]
+            .iter()
+            .map(|field| self.layout_interner.stack_size(*field))
+            .sum();
+        let size = self.layout_interner.stack_size(layout);
+        let (word, shift) = (offset / 8, offset % 8);
+        if shift + size > 8 {
+            return false;
+        }
 
-                ASM::mov_base32_reg64(buf, base_offset, src_reg);
+        let src_reg = CC::GENERAL_RETURN_REGS[word as usize];
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, sym);
+        let src_reg = if shift == 0 {
+            src_reg
+        } else {
+            ASM::shr_reg64_reg64_imm8(&mut self.buf, dst_reg, src_reg, (shift * 8) as u8);
+            dst_reg
+        };
+        match width {
+            RegisterWidth::W64 => ASM::mov_reg64_reg64(&mut self.buf, dst_reg, src_reg),
+            _ if matches!(repr, sign_extended_int_builtins!()) => {
+                ASM::movsx_reg_reg(&mut self.buf, width, dst_reg, src_reg)
+            }
+            _ => ASM::movzx_reg_reg(&mut self.buf, width, dst_reg, src_reg),
+        }
 
-                self.free_symbol(&tmp);
+        // The other fields can still be read from the registers, unless one was just overwritten.
+        self.struct_in_return_regs = if CC::GENERAL_RETURN_REGS[..2].contains(&dst_reg) {
+            None
+        } else {
+            Some((*structure, self.buf.len()))
+        };
+        true
+    }
 
-                return;
+    /// The value of `sym`, if it is a lazy integer literal.
+    fn int_literal(&self, sym: &Symbol) -> Option<i128> {
+        let (lit, layout) = self.literal_map.get(sym)?;
+        // See `load_literal_symbols` for why reading through these pointers is safe.
+        let (lit, layout) = unsafe { (**lit, **layout) };
+        match (lit, self.layout_interner.get_repr(layout)) {
+            (Literal::Int(bytes), single_register_int_builtins!()) => {
+                Some(i128::from_ne_bytes(bytes))
             }
-            (U128, I128) | (I128, U128) => {
-                let to_offset = self.storage_manager.claim_stack_area_layout(
-                    self.layout_interner,
-                    *dst,
-                    Layout::from_int_width(target),
-                );
+            (Literal::Byte(byte), LayoutRepr::U8 | LayoutRepr::I8) => Some(byte as i128),
+            _ => None,
+        }
+    }
 
-                let (from_offset, size) = self.storage_manager.stack_offset_and_size(src);
+    /// If `sym` is a lazy integer literal of 3, 5, or 9, returns the `lea` scale
+    /// that multiplies by it: `x * (scale + 1)` is `x + x * scale`.
+    fn lea_multiplier(&self, sym: &Symbol) -> Option<u8> {
+        match self.int_literal(sym)? {
+            3 => Some(2),
+            5 => Some(4),
+            9 => Some(8),
+            _ => None,
+        }
+    }
 
-                self.storage_manager
-                    .copy_to_stack_offset(buf, size, from_offset, to_offset);
+    /// Stores `src + delta` into `dst`, for a `delta` of 1 or -1.
+    /// The flags of a wrapping add are never read, so this can use `inc` or `dec`.
+    fn build_int_step(&mut self, dst: &Symbol, src: &Symbol, delta: i128) {
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+        let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
+        match delta {
+            1 => ASM::inc_reg64_reg64(&mut self.buf, dst_reg, src_reg),
+            -1 => ASM::dec_reg64_reg64(&mut self.buf, dst_reg, src_reg),
+            _ => internal_error!("a step is 1 or -1, not {delta}"),
+        }
+    }
 
-                return;
+    /// What it costs to load `lit` into a register, or None if it isn't a single register value.
+    /// Spilling a value and reloading it costs 2.
+    fn rematerialize_cost(&self, lit: &Literal<'a>, layout: InLayout<'a>) -> Option<u32> {
+        match (lit, self.layout_interner.get_repr(layout)) {
+            (Literal::Int(bytes), single_register_int_builtins!()) => {
+                let value = i128::from_ne_bytes(*bytes);
+                Some(if i32::try_from(value).is_ok() { 1 } else { 2 })
             }
-
-            _ => {}
+            (Literal::Byte(_), LayoutRepr::U8 | LayoutRepr::I8) => Some(1),
+            (Literal::Bool(_), LayoutRepr::BOOL) => Some(1),
+            // Floats are loaded from the constant pool.
+            (Literal::Float(_), single_register_floats!()) => Some(2),
+            _ => None,
         }
+    }
 
-        let dst_reg = self.storage_manager.claim_general_reg(buf, dst);
-        let src_reg = self.storage_manager.load_to_general_reg(buf, src);
+    /// Before a call, drops the registers of live literals that are cheaper to load again
+    /// than to spill, and makes them lazy again, so their next use reloads them.
+    /// The arguments of the call are kept, since they are about to be passed.
+    fn forget_cheap_literals(&mut self, args: &[Symbol]) {
+        if !self.env.lazy_literals {
+            return;
+        }
 
-        if source.stack_size() == target.stack_size() {
-            match source.stack_size() {
-                8 => ASM::mov_reg64_reg64(buf, dst_reg, src_reg),
-                4 => ASM::mov_reg32_reg32(buf, dst_reg, src_reg),
-                2 => ASM::mov_reg16_reg16(buf, dst_reg, src_reg),
-                1 => ASM::mov_reg8_reg8(buf, dst_reg, src_reg),
-                _ => todo!("int cast from {source:?} to {target:?}"),
+        let mut cheap = bumpalo::vec![in self.env.arena];
+        for (sym, (lit, layout)) in self.loaded_literals.iter() {
+            // Values that are already on the stack (e.g. everything live at a join point)
+            // cost nothing to keep, and must stay where they are.
+            if args.contains(sym) || !self.storage_manager.is_only_in_reg(sym) {
+                continue;
             }
-        } else {
-            match (source, target) {
-                // -- CASTING UP --
-                (I8 | U8, U16 | U32 | U64) => {
-                    // zero  out the register
-                    ASM::xor_reg64_reg64_reg64(buf, dst_reg, dst_reg, dst_reg);
-
-                    // move the 8-bit integer
-                    ASM::mov_reg_reg(buf, RegisterWidth::W8, dst_reg, src_reg);
+            match self.rematerialize_cost(lit, *layout) {
+                Some(cost) if cost <= self.env.options.rematerialize_cost => {
+                    cheap.push((*sym, *lit, *layout))
                 }
-                (U16, U32 | U64) => {
-                    // zero  out the register
-                    ASM::xor_reg64_reg64_reg64(buf, dst_reg, dst_reg, dst_reg);
+                _ => {}
+            }
+        }
 
-                    // move the 16-bit integer
-                    ASM::mov_reg_reg(buf, RegisterWidth::W16, dst_reg, src_reg);
-                }
-                (U32, U64) => {
-                    // zero  out the register
-                    ASM::xor_reg64_reg64_reg64(buf, dst_reg, dst_reg, dst_reg);
+        for (sym, lit, layout) in cheap {
+            self.storage_manager.free_symbol(&sym);
+            let lit: &'a Literal<'a> = self.env.arena.alloc(lit);
+            let layout: &'a InLayout<'a> = self.env.arena.alloc(layout);
+            self.literal_map.insert(sym, (lit, layout));
+        }
+    }
 
-                    // move the 32-bit integer
-                    ASM::mov_reg_reg(buf, RegisterWidth::W32, dst_reg, src_reg);
+    /// Loads `sym` into `dst`, wherever the symbol currently lives.
+    /// Lazy integer literals are materialized directly into `dst` and stay lazy.
+    /// Register sources are moved, and stack sources are loaded with the width of their layout.
+    /// Like `load_to_specified_general_reg`, this does not free `dst` first or track the move.
+    fn load_symbol_to_general_reg(&mut self, dst: GeneralReg, sym: &Symbol) {
+        if let Some((lit, layout)) = self.literal_map.get(sym) {
+            // See `load_literal_symbols` for why reading through these pointers is safe.
+            let (lit, layout) = unsafe { (**lit, **layout) };
+            let value = match (lit, self.layout_interner.get_repr(layout)) {
+                (Literal::Int(bytes), single_register_int_builtins!()) => {
+                    Some(i128::from_ne_bytes(bytes) as i64)
                 }
-                (I8, I16 | I32 | I64) => {
-                    ASM::movsx_reg_reg(buf, RegisterWidth::W8, dst_reg, src_reg)
+                (Literal::Byte(byte), LayoutRepr::U8 | LayoutRepr::I8) => {
+                    ASM::movzx_reg64_imm8(&mut self.buf, dst, byte);
+                    return;
                 }
-                (I16, I32 | I64) => ASM::movsx_reg_reg(buf, RegisterWidth::W16, dst_reg, src_reg),
-                (I32, I64) => ASM::movsx_reg_reg(buf, RegisterWidth::W32, dst_reg, src_reg),
-                // -- CASTING DOWN --
-                (U64 | I64, I32 | U32) => {
-                    // move as a 32-bit integer (leaving any other bits behind)
-                    ASM::mov_reg_reg(buf, RegisterWidth::W32, dst_reg, src_reg);
+                (Literal::Bool(b), LayoutRepr::BOOL) => Some(b as i64),
+                _ => None,
+            };
+
+            match value {
+                Some(value) => {
+                    ASM::mov_reg64_imm64(&mut self.buf, dst, value);
+                    return;
                 }
-                (U64 | I64 | U32 | I32, I16 | U16) => {
-                    // move as a 16-bit integer (leaving any other bits behind)
-                    ASM::mov_reg_reg(buf, RegisterWidth::W16, dst_reg, src_reg);
+                None => self.load_literal_symbols(&[*sym]),
+            }
+        }
+
+        self.storage_manager
+            .load_to_specified_general_reg(&mut self.buf, sym, dst);
+    }
+
+    /// Loads `sym` into the float register `dst`, wherever the symbol currently lives.
+    /// Lazy float literals are loaded from the constant pool directly into `dst` and stay lazy.
+    /// Register sources are moved, and stack sources are loaded with the width of their layout.
+    /// Like `load_to_specified_float_reg`, this does not free `dst` first or track the move.
+    fn load_symbol_to_float_reg(&mut self, dst: FloatReg, sym: &Symbol) {
+        if let Some((lit, layout)) = self.literal_map.get(sym) {
+            // See `load_literal_symbols` for why reading through these pointers is safe.
+            let (lit, layout) = unsafe { (**lit, **layout) };
+            match (lit, self.layout_interner.get_repr(layout)) {
+                (Literal::Float(x), LayoutRepr::F64) => {
+                    ASM::mov_freg64_imm64(&mut self.buf, &mut self.relocs, dst, x);
+                    return;
                 }
-                (U64 | I64 | U32 | I32 | U16 | I16, I8 | U8) => {
-                    // move as an 8-bit integer (leaving any other bits behind)
-                    ASM::mov_reg_reg(buf, RegisterWidth::W8, dst_reg, src_reg);
+                (Literal::Float(x), LayoutRepr::F32) => {
+                    ASM::mov_freg32_imm32(&mut self.buf, &mut self.relocs, dst, f32_literal(x));
+                    return;
                 }
-                _ => todo!("int cast from {source:?} to {target:?}"),
+                _ => self.load_literal_symbols(&[*sym]),
             }
         }
-    }
-}
-
-/// This impl block is for ir related instructions that need backend specific information.
-/// For example, loading a symbol for doing a computation.
-impl<
-        'a,
-        'r,
-        FloatReg: RegTrait,
-        GeneralReg: RegTrait,
-        ASM: Assembler<GeneralReg, FloatReg>,
-        CC: CallConv<GeneralReg, FloatReg, ASM>,
-    > Backend64Bit<'a, 'r, GeneralReg, FloatReg, ASM, CC>
-{
-    fn build_fn_call_stack_return<const N: usize>(
-        &mut self,
-        function_name: String,
-        arguments: &[Symbol; N],
-        layouts: &[InLayout<'a>; N],
-        ret_layout: InLayout<'a>,
-        dst: Symbol,
-    ) {
-        // Setup the return location.
-        let base_offset =
-            self.storage_manager
-                .claim_stack_area_layout(self.layout_interner, dst, ret_layout);
-
-        let tmp = self.debug_symbol("call_with_stack_return_result");
-
-        self.build_fn_call(
-            &tmp,
-            function_name,
-            arguments.as_slice(),
-            layouts.as_slice(),
-            &ret_layout,
-        );
-
-        // Return list value from fn call
-        self.storage_manager.copy_symbol_to_stack_offset(
-            self.layout_interner,
-            &mut self.buf,
-            base_offset,
-            &tmp,
-            &ret_layout,
-        );
-
-        self.free_symbol(&tmp);
-    }
-
-    fn clear_tag_id(&mut self, ptr_reg: GeneralReg) -> (Symbol, GeneralReg) {
-        let unmasked_symbol = self.debug_symbol("unmasked");
-        let unmasked_reg = self
-            .storage_manager
-            .claim_general_reg(&mut self.buf, &unmasked_symbol);
-
-        ASM::mov_reg64_imm64(&mut self.buf, unmasked_reg, (!0b111) as _);
-
-        ASM::and_reg64_reg64_reg64(&mut self.buf, unmasked_reg, ptr_reg, unmasked_reg);
 
-        (unmasked_symbol, unmasked_reg)
+        self.storage_manager
+            .load_to_specified_float_reg(&mut self.buf, sym, dst);
     }
 
     fn num_to_f32(&mut self, dst: &Symbol, src: &Symbol, arg_layout: &InLayout<'a>) {
@@ -4842,6 +6571,9 @@ impl<
             LayoutRepr::Builtin(Builtin::Int(IntWidth::I32 | IntWidth::I64)) => {
                 let dst_reg = self.storage_manager.claim_float_reg(&mut self.buf, dst);
                 let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
+                if self.env.options.break_false_dependencies {
+                    ASM::break_freg_dependency(&mut self.buf, dst_reg);
+                }
                 ASM::to_float_freg32_reg64(&mut self.buf, dst_reg, src_reg);
             }
             LayoutRepr::Builtin(Builtin::Float(FloatWidth::F64)) => {
@@ -4867,6 +6599,9 @@ impl<
             LayoutRepr::Builtin(Builtin::Int(IntWidth::I32 | IntWidth::I64)) => {
                 let dst_reg = self.storage_manager.claim_float_reg(&mut self.buf, dst);
                 let src_reg = self.storage_manager.load_to_general_reg(&mut self.buf, src);
+                if self.env.options.break_false_dependencies {
+                    ASM::break_freg_dependency(&mut self.buf, dst_reg);
+                }
                 ASM::to_float_freg64_reg64(&mut self.buf, dst_reg, src_reg);
             }
             LayoutRepr::Builtin(Builtin::Float(FloatWidth::F32)) => {
@@ -4911,20 +6646,67 @@ impl<
         src2: &Symbol,
         width: IntWidth,
     ) {
-        let intrinsic = match op {
-            CompareOperation::LessThan => &bitcode::NUM_LESS_THAN[width],
-            CompareOperation::LessThanOrEqual => &bitcode::NUM_LESS_THAN_OR_EQUAL[width],
-            CompareOperation::GreaterThan => &bitcode::NUM_GREATER_THAN[width],
-            CompareOperation::GreaterThanOrEqual => &bitcode::NUM_GREATER_THAN_OR_EQUAL[width],
+        // The high words decide, unless they are equal. Then the low words decide.
+        // Only the high words carry a sign, the low words are always compared unsigned.
+        let strict_op = match op {
+            CompareOperation::LessThan | CompareOperation::LessThanOrEqual => {
+                CompareOperation::LessThan
+            }
+            CompareOperation::GreaterThan | CompareOperation::GreaterThanOrEqual => {
+                CompareOperation::GreaterThan
+            }
         };
 
-        self.build_fn_call(
-            dst,
-            intrinsic.to_string(),
-            &[*src1, *src2],
-            &[Layout::U128, Layout::U128],
-            &Layout::U8,
-        );
+        let (src1_offset, _) = self.storage_manager.stack_offset_and_size(src1);
+        let (src2_offset, _) = self.storage_manager.stack_offset_and_size(src2);
+
+        let dst_reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |storage_manager, buf, tmp1| {
+                storage_manager.with_tmp_general_reg(buf, |storage_manager, buf, tmp2| {
+                    storage_manager.with_tmp_general_reg(buf, |_, buf, high_result| {
+                        // dst = low1 op low2
+                        ASM::mov_reg64_base32(buf, tmp1, src1_offset);
+                        ASM::mov_reg64_base32(buf, tmp2, src2_offset);
+                        ASM::unsigned_compare_reg64(
+                            buf,
+                            RegisterWidth::W64,
+                            op,
+                            dst_reg,
+                            tmp1,
+                            tmp2,
+                        );
+
+                        // dst &= high1 == high2
+                        ASM::mov_reg64_base32(buf, tmp1, src1_offset + 8);
+                        ASM::mov_reg64_base32(buf, tmp2, src2_offset + 8);
+                        ASM::eq_reg_reg_reg(buf, RegisterWidth::W64, high_result, tmp1, tmp2);
+                        ASM::and_reg64_reg64_reg64(buf, dst_reg, dst_reg, high_result);
+
+                        // dst |= high1 strict_op high2
+                        if width.is_signed() {
+                            ASM::signed_compare_reg64(
+                                buf,
+                                RegisterWidth::W64,
+                                strict_op,
+                                high_result,
+                                tmp1,
+                                tmp2,
+                            );
+                        } else {
+                            ASM::unsigned_compare_reg64(
+                                buf,
+                                RegisterWidth::W64,
+                                strict_op,
+                                high_result,
+                                tmp1,
+                                tmp2,
+                            );
+                        }
+                        ASM::or_reg64_reg64_reg64(buf, dst_reg, dst_reg, high_result);
+                    });
+                });
+            });
     }
 
     fn compare(
@@ -4944,19 +6726,7 @@ impl<
                 let src2 = self.storage_manager.load_to_general_reg(buf, src2);
 
                 let int_width = arg_layout.try_int_width().unwrap();
-                let register_width = match int_width.stack_size() {
-                    8 => RegisterWidth::W64,
-                    4 => RegisterWidth::W32,
-                    2 => RegisterWidth::W16,
-                    1 => RegisterWidth::W8,
-                    _ => unreachable!(),
-                };
-
-                if int_width.is_signed() {
-                    ASM::signed_compare_reg64(buf, register_width, op, dst, src1, src2)
-                } else {
-                    ASM::unsigned_compare_reg64(buf, register_width, op, dst, src1, src2)
-                }
+                Self::compare_int_reg64(buf, op, int_width, dst, src1, src2);
             }
             LayoutRepr::F32 | LayoutRepr::F64 => {
                 let float_width = match *arg_layout {
@@ -4988,6 +6758,30 @@ impl<
         }
     }
 
+    /// Sets `dst` to the Bool `src1 op src2` for integers of `int_width`.
+    fn compare_int_reg64(
+        buf: &mut Vec<'a, u8>,
+        op: CompareOperation,
+        int_width: IntWidth,
+        dst: GeneralReg,
+        src1: GeneralReg,
+        src2: GeneralReg,
+    ) {
+        let register_width = match int_width.stack_size() {
+            8 => RegisterWidth::W64,
+            4 => RegisterWidth::W32,
+            2 => RegisterWidth::W16,
+            1 => RegisterWidth::W8,
+            _ => unreachable!(),
+        };
+
+        if int_width.is_signed() {
+            ASM::signed_compare_reg64(buf, register_width, op, dst, src1, src2)
+        } else {
+            ASM::unsigned_compare_reg64(buf, register_width, op, dst, src1, src2)
+        }
+    }
+
     fn allocate_with_refcount(
         &mut self,
         dst: Symbol,
@@ -5510,3 +7304,17 @@ macro_rules! pointer_layouts {
             | LayoutRepr::FunctionPointer(_)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f32_literal_keeps_the_top_of_a_nan_payload() {
+        let nan = f64::from_bits(0x7FF8_0246_8000_0000);
+        assert_eq!(f32_literal(nan).to_bits(), 0x7FC0_1234);
+        assert_eq!(f32_literal(-nan).to_bits(), 0xFFC0_1234);
+        assert_eq!(f32_literal(f64::NEG_INFINITY).to_bits(), 0xFF80_0000);
+        assert_eq!(f32_literal(1.5).to_bits(), 1.5f32.to_bits());
+    }
+}
@@ -2,6 +2,7 @@ use crate::{
     generic64::{Assembler, CallConv, RegTrait},
     pointer_layouts, sign_extended_int_builtins, single_register_floats,
     single_register_int_builtins, single_register_integers, single_register_layouts, Env,
+    SpillHeuristic, UsePositions,
 };
 use bumpalo::collections::{CollectIn, Vec};
 use roc_builtins::bitcode::{FloatWidth, IntWidth};
@@ -9,7 +10,7 @@ use roc_collections::all::{MutMap, MutSet};
 use roc_error_macros::{internal_error, todo_lambda_erasure};
 use roc_module::symbol::Symbol;
 use roc_mono::{
-    ir::{JoinPointId, Param},
+    ir::{JoinPointId, Param, Stmt},
     layout::{
         Builtin, InLayout, Layout, LayoutInterner, LayoutRepr, STLayoutInterner, UnionLayout,
     },
@@ -118,6 +119,11 @@ pub struct StorageManager<
     general_used_regs: Vec<'a, (GeneralReg, Symbol)>,
     float_used_regs: Vec<'a, (FloatReg, Symbol)>,
 
+    // Where upcoming uses of each symbol are, and the position of the statement being built.
+    // These are only filled in with `SpillHeuristic::FurthestNextUse`.
+    use_positions: UsePositions<'a>,
+    position: usize,
+
     pub(crate) used_callee_saved_regs: UsedCalleeRegisters<GeneralReg, FloatReg>,
 
     free_stack_chunks: Vec<'a, (i32, u32)>,
@@ -126,6 +132,9 @@ pub struct StorageManager<
     /// Amount of extra stack space needed to pass arguments for a function call
     /// This is usually zero, and only used when the argument passing registers are all used
     fn_call_stack_size: u32,
+
+    /// The area (start and end base offsets) of the arguments the caller passed on the stack.
+    stack_args: Option<(i32, i32)>,
 }
 
 pub fn new_storage_manager<
@@ -153,9 +162,12 @@ pub fn new_storage_manager<
         used_callee_saved_regs: UsedCalleeRegisters::default(),
         float_free_regs: bumpalo::vec![in env.arena],
         float_used_regs: bumpalo::vec![in env.arena],
+        use_positions: UsePositions::default(),
+        position: 0,
         free_stack_chunks: bumpalo::vec![in env.arena],
         stack_size: 0,
         fn_call_stack_size: 0,
+        stack_args: None,
     }
 }
 
@@ -228,9 +240,12 @@ impl<
         self.float_free_regs
             .extend_from_slice(CC::FLOAT_DEFAULT_FREE_REGS);
         self.used_callee_saved_regs.clear();
+        self.use_positions = UsePositions::default();
+        self.position = 0;
         self.free_stack_chunks.clear();
         self.stack_size = 0;
         self.fn_call_stack_size = 0;
+        self.stack_args = None;
     }
 
     pub fn stack_size(&self) -> u32 {
@@ -241,6 +256,20 @@ impl<
         self.fn_call_stack_size
     }
 
+    /// The number of bytes of arguments the caller passed this function on the stack.
+    pub fn stack_args_size(&self) -> u32 {
+        self.stack_args
+            .map_or(0, |(start, end)| (end - start) as u32)
+    }
+
+    fn claim_stack_arg(&mut self, base_offset: i32, size: u32) {
+        let end = base_offset + size as i32;
+        self.stack_args = Some(match self.stack_args {
+            Some((start, old_end)) => (start.min(base_offset), old_end.max(end)),
+            None => (base_offset, end),
+        });
+    }
+
     /// Returns true if the symbol is storing a primitive value.
     pub fn is_stored_primitive(&self, sym: &Symbol) -> bool {
         matches!(
@@ -249,6 +278,11 @@ impl<
         )
     }
 
+    /// Returns true if the symbol lives in a register and has no copy on the stack.
+    pub fn is_only_in_reg(&self, sym: &Symbol) -> bool {
+        matches!(self.symbol_storage_map.get(sym), Some(Reg(_)))
+    }
+
     /// Get a general register from the free list.
     /// Will free data to the stack if necessary to get the register.
     fn get_general_reg(&mut self, buf: &mut Vec<'a, u8>) -> GeneralReg {
@@ -258,7 +292,8 @@ impl<
             }
             reg
         } else if !self.general_used_regs.is_empty() {
-            let (reg, sym) = self.general_used_regs.remove(0);
+            let victim = self.spill_victim(self.general_used_regs.iter().map(|(_, sym)| sym));
+            let (reg, sym) = self.general_used_regs.remove(victim);
             self.free_to_stack(buf, &sym, General(reg));
             reg
         } else {
@@ -275,7 +310,8 @@ impl<
             }
             reg
         } else if !self.float_used_regs.is_empty() {
-            let (reg, sym) = self.float_used_regs.remove(0);
+            let victim = self.spill_victim(self.float_used_regs.iter().map(|(_, sym)| sym));
+            let (reg, sym) = self.float_used_regs.remove(victim);
             self.free_to_stack(buf, &sym, Float(reg));
             reg
         } else {
@@ -283,6 +319,35 @@ impl<
         }
     }
 
+    /// Picks which of the symbols in use registers, oldest first, to spill.
+    fn spill_victim<'s>(&self, used: impl Iterator<Item = &'s Symbol>) -> usize {
+        match self.env.options.spill_heuristic {
+            SpillHeuristic::LeastRecentlyLoaded => 0,
+            SpillHeuristic::FurthestNextUse => used
+                .map(|sym| {
+                    self.use_positions
+                        .next_use(sym, self.position)
+                        .unwrap_or(usize::MAX)
+                })
+                .enumerate()
+                // On a tie, the oldest is spilled, like `LeastRecentlyLoaded`.
+                .rev()
+                .max_by_key(|(_, next_use)| *next_use)
+                .map_or(0, |(i, _)| i),
+        }
+    }
+
+    pub fn set_use_positions(&mut self, positions: UsePositions<'a>) {
+        self.use_positions = positions;
+    }
+
+    /// Records that `stmt` is being built, so uses before it no longer count.
+    pub fn enter_stmt(&mut self, stmt: &Stmt<'a>) {
+        if let Some(position) = self.use_positions.position(stmt) {
+            self.position = position;
+        }
+    }
+
     /// Claims a general reg for a specific symbol.
     /// They symbol should not already have storage.
     pub fn claim_general_reg(&mut self, buf: &mut Vec<'a, u8>, sym: &Symbol) -> GeneralReg {
@@ -319,7 +384,6 @@ impl<
         self.general_free_regs.push(reg);
     }
 
-    #[allow(dead_code)]
     /// This claims a temporary float register and enables is used in the passed in function.
     /// Temporary registers are not safe across call instructions.
     pub fn with_tmp_float_reg<F: FnOnce(&mut Self, &mut Vec<'a, u8>, FloatReg)>(
@@ -407,6 +471,113 @@ impl<
         }
     }
 
+    /// Loads a symbol into a general reg that is held for the duration of `callback`.
+    /// With a `fixed` register, as some instructions require for an operand, the symbol is loaded
+    /// into exactly that register, evicting whatever it held.
+    /// While `callback` runs, the register is neither handed out nor spilled, so the other operands
+    /// of the instruction can be allocated around it.
+    pub fn with_general_reg_constraint<F: FnOnce(&mut Self, &mut Vec<'a, u8>, GeneralReg)>(
+        &mut self,
+        buf: &mut Vec<'a, u8>,
+        sym: &Symbol,
+        fixed: Option<GeneralReg>,
+        callback: F,
+    ) {
+        let reg = match fixed {
+            Some(reg) => {
+                self.load_to_fixed_general_reg(buf, sym, reg);
+                reg
+            }
+            None => self.load_to_general_reg(buf, sym),
+        };
+
+        let position = self
+            .general_used_regs
+            .iter()
+            .position(|(used_reg, _)| *used_reg == reg)
+            .unwrap_or_else(|| internal_error!("{sym:?} is not loaded in {reg:?}"));
+        let held = self.general_used_regs.remove(position);
+        callback(self, buf, reg);
+        self.general_used_regs.push(held);
+    }
+
+    /// Loads a symbol into exactly `reg` and tracks it there.
+    /// A different symbol in `reg` is moved to a free register, or spilled if there is none.
+    fn load_to_fixed_general_reg(&mut self, buf: &mut Vec<'a, u8>, sym: &Symbol, reg: GeneralReg) {
+        let current = match self.get_storage_for_sym(sym) {
+            Reg(General(old_reg))
+            | Stack(Primitive {
+                reg: Some(General(old_reg)),
+                ..
+            }) => Some(*old_reg),
+            _ => None,
+        };
+        if current == Some(reg) {
+            return;
+        }
+
+        if let Some(position) = self
+            .general_used_regs
+            .iter()
+            .position(|(used_reg, _)| *used_reg == reg)
+        {
+            match self.general_free_regs.pop() {
+                Some(free_reg) => {
+                    let (_, evicted) = self.general_used_regs[position];
+                    self.move_general_reg(buf, &evicted, reg, free_reg);
+                    self.general_used_regs[position].0 = free_reg;
+                }
+                None => self.ensure_reg_free(buf, General(reg)),
+            }
+        }
+
+        match current {
+            Some(old_reg) => {
+                self.general_free_regs.retain(|r| *r != reg);
+                self.move_general_reg(buf, sym, old_reg, reg);
+                for (used_reg, _) in self.general_used_regs.iter_mut() {
+                    if *used_reg == old_reg {
+                        *used_reg = reg;
+                    }
+                }
+                self.general_free_regs.push(old_reg);
+            }
+            None => {
+                // Put `reg` on top of the free list so the load picks it.
+                self.general_free_regs.retain(|r| *r != reg);
+                self.general_free_regs.push(reg);
+                self.load_to_general_reg(buf, sym);
+            }
+        }
+    }
+
+    /// Moves the value of `sym` from `from` to `to`, updating its storage.
+    /// The used and free register lists are expected to be updated outside of this function.
+    fn move_general_reg(
+        &mut self,
+        buf: &mut Vec<'a, u8>,
+        sym: &Symbol,
+        from: GeneralReg,
+        to: GeneralReg,
+    ) {
+        if CC::general_callee_saved(&to) {
+            self.used_callee_saved_regs.insert_general(to);
+        }
+        ASM::mov_reg64_reg64(buf, to, from);
+        let storage = match self.remove_storage_for_sym(sym) {
+            Reg(General(_)) => Reg(General(to)),
+            Stack(Primitive {
+                base_offset,
+                reg: Some(General(_)),
+            }) => Stack(Primitive {
+                base_offset,
+                reg: Some(General(to)),
+            }),
+            storage => internal_error!("{sym:?} is not in a general register: {storage:?}"),
+        };
+        self.symbol_storage_map.insert(*sym, storage);
+    }
+
     /// Loads a symbol into a float reg and returns that register.
     /// The symbol must already be stored somewhere.
     /// Will fail on values stored in general regs.
@@ -478,6 +649,64 @@ impl<
         }
     }
 
+    /// Loads the symbol into a parameter register for an upcoming call.
+    /// Unlike `load_to_specified_general_reg`, a different symbol still held in `reg` is spilled
+    /// first, so placing one argument can never clobber a value that a later argument reads.
+    /// The argument itself is not tracked in `reg`.
+    pub fn load_to_param_general_reg(
+        &mut self,
+        buf: &mut Vec<'a, u8>,
+        sym: &Symbol,
+        reg: GeneralReg,
+    ) {
+        let already_there = match self.get_storage_for_sym(sym) {
+            Reg(General(old_reg))
+            | Stack(Primitive {
+                reg: Some(General(old_reg)),
+                ..
+            }) => *old_reg == reg,
+            _ => false,
+        };
+        if !already_there {
+            self.evict_general_reg(buf, reg);
+        }
+        self.load_to_specified_general_reg(buf, sym, reg);
+    }
+
+    /// Loads the symbol into a float parameter register for an upcoming call.
+    /// See `load_to_param_general_reg`.
+    pub fn load_to_param_float_reg(&mut self, buf: &mut Vec<'a, u8>, sym: &Symbol, reg: FloatReg) {
+        let already_there = match self.get_storage_for_sym(sym) {
+            Reg(Float(old_reg))
+            | Stack(Primitive {
+                reg: Some(Float(old_reg)),
+                ..
+            }) => *old_reg == reg,
+            _ => false,
+        };
+        if !already_there
+            && self
+                .float_used_regs
+                .iter()
+                .any(|(used_reg, _)| *used_reg == reg)
+        {
+            self.ensure_reg_free(buf, Float(reg));
+        }
+        self.load_to_specified_float_reg(buf, sym, reg);
+    }
+
+    /// Spills the symbol held in `reg`, if any, so the register can be overwritten directly.
+    /// Registers outside of the allocator's pools (scratch registers) are left alone.
+    pub fn evict_general_reg(&mut self, buf: &mut Vec<'a, u8>, reg: GeneralReg) {
+        if self
+            .general_used_regs
+            .iter()
+            .any(|(used_reg, _)| *used_reg == reg)
+        {
+            self.ensure_reg_free(buf, General(reg));
+        }
+    }
+
     /// Loads the symbol to the specified register.
     /// It will fail if the symbol is stored in a float register.
     /// This is only made to be used in special cases where exact regs are needed (function args and returns).
@@ -728,6 +957,21 @@ impl<
             self.symbol_storage_map.insert(*sym, NoData);
             return;
         }
+
+        // A single field struct has the same layout as its field.
+        // If the field already owns an area of the stack, the struct can simply share it.
+        if let [field] = fields {
+            if let (Stack(Complex { base_offset, size }), Some(owned_data)) = (
+                *self.get_storage_for_sym(field),
+                self.allocation_map.get(field).cloned(),
+            ) {
+                self.allocation_map.insert(*sym, owned_data);
+                self.symbol_storage_map
+                    .insert(*sym, Stack(Complex { base_offset, size }));
+                return;
+            }
+        }
+
         let base_offset = self.claim_stack_area_layout(layout_interner, *sym, *layout);
 
         let mut in_layout = *layout;
@@ -883,7 +1127,14 @@ impl<
                 let (from_offset, size) = self.stack_offset_and_size(sym);
                 debug_assert_eq!(size, layout_interner.stack_size(*layout));
 
-                self.copy_to_stack_offset(buf, size, from_offset, to_offset)
+                if size == 16 {
+                    // Two eightbytes move in one go through a float register.
+                    self.with_tmp_float_reg(buf, |_storage_manager, buf, reg| {
+                        ASM::mov_base32_base32_128(buf, reg, to_offset, from_offset);
+                    });
+                } else {
+                    self.copy_to_stack_offset(buf, size, from_offset, to_offset)
+                }
             }
             LayoutRepr::Erased(_) => todo_lambda_erasure!(),
             pointer_layouts!() => {
@@ -952,7 +1203,6 @@ impl<
         });
     }
 
-    #[allow(dead_code)]
     /// Ensures that a register is free. If it is not free, data will be moved to make it free.
     pub fn ensure_reg_free(
         &mut self,
@@ -1025,8 +1275,16 @@ impl<
 
     /// Frees all symbols to the stack setuping up a clean slate.
     pub fn free_all_to_stack(&mut self, buf: &mut Vec<'a, u8>) {
+        self.free_all_to_stack_except(buf, &[])
+    }
+
+    /// Frees all symbols but `kept` to the stack. The symbols in `kept` stay in their registers.
+    pub fn free_all_to_stack_except(&mut self, buf: &mut Vec<'a, u8>, kept: &[Symbol]) {
         let mut free_list = bumpalo::vec![in self.env.arena];
         for (sym, storage) in self.symbol_storage_map.iter() {
+            if kept.contains(sym) {
+                continue;
+            }
             match storage {
                 Reg(reg_storage)
                 | Stack(Primitive {
@@ -1143,6 +1401,7 @@ impl<
             }),
         );
         self.allocation_map.insert(*sym, Rc::new((base_offset, 8)));
+        self.claim_stack_arg(base_offset, 8);
     }
 
     /// Specifies a complex is loaded at the specific base offset.
@@ -1151,6 +1410,7 @@ impl<
             .insert(*sym, Stack(Complex { base_offset, size }));
         self.allocation_map
             .insert(*sym, Rc::new((base_offset, size)));
+        self.claim_stack_arg(base_offset, size);
     }
 
     /// Specifies a no data exists.
@@ -1375,6 +1635,12 @@ impl<
         base_offset
     }
 
+    /// Claims 8 bytes of the stack that belong to no symbol and are never freed.
+    /// Returns the base offset of the slot.
+    pub fn claim_reserved_stack_slot(&mut self) -> i32 {
+        self.claim_stack_size_with_alignment(8, 8)
+    }
+
     pub fn claim_pointer_stack_area(&mut self, sym: Symbol) -> i32 {
         // pointers are 8 bytes wide with an alignment of 8
         let base_offset = self.claim_stack_size_with_alignment(8, 8);
@@ -1405,46 +1671,54 @@ impl<
             }
         }
 
-        pub const fn is_multiple_of(lhs: i32, rhs: i32) -> bool {
-            match rhs {
-                0 => false,
-                _ => lhs % rhs == 0,
-            }
-        }
-
         // round value to the alignment.
         let amount = next_multiple_of(amount, alignment);
 
-        let chunk_fits = |(offset, size): &(i32, u32)| {
-            *size >= amount && is_multiple_of(*offset, alignment as i32)
+        // The first suitably aligned offset in a free chunk, if the allocation fits from there.
+        // A 16-byte value can still go in an 8-byte aligned chunk if the chunk is big enough.
+        let aligned_start = |(offset, size): &(i32, u32)| {
+            let misalignment = (*offset).rem_euclid(alignment as i32);
+            let start = match misalignment {
+                0 => *offset,
+                _ => *offset + alignment as i32 - misalignment,
+            };
+            let skipped = (start - *offset) as u32;
+            (*size >= skipped + amount).then_some(start)
         };
 
         // padding on the stack to make sure an allocation is aligned
         let padding = next_multiple_of(*stack_size, alignment) - *stack_size;
 
-        if let Some(fitting_chunk) = free_stack_chunks
+        if let Some((pos, start, _)) = free_stack_chunks
             .iter()
             .enumerate()
-            .filter(|(_, chunk)| chunk_fits(chunk))
-            .min_by_key(|(_, (_, size))| size)
+            .filter_map(|(pos, chunk)| aligned_start(chunk).map(|start| (pos, start, chunk.1)))
+            .min_by_key(|(_, _, size)| *size)
         {
-            let (pos, (offset, size)) = fitting_chunk;
-            let (offset, size) = (*offset, *size);
-            if size == amount {
-                // fill the whole chunk precisely
-                free_stack_chunks.remove(pos);
-                offset
-            } else {
-                // use part of this chunk
-                let (prev_offset, prev_size) = free_stack_chunks[pos];
-                free_stack_chunks[pos] = (prev_offset + amount as i32, prev_size - amount);
-                prev_offset
+            let (offset, size) = free_stack_chunks[pos];
+            let before = (start - offset) as u32;
+            let after = size - before - amount;
+
+            // Keep whatever is left of the chunk on either side of the allocation.
+            free_stack_chunks.remove(pos);
+            if after != 0 {
+                free_stack_chunks.insert(pos, (start + amount as i32, after));
             }
+            if before != 0 {
+                free_stack_chunks.insert(pos, (offset, before));
+            }
+            start
         } else if let Some(new_size) = stack_size.checked_add(padding + amount) {
             // Since stack size is u32, but the max offset is i32, if we pass i32 max, we have overflowed.
             if new_size > i32::MAX as u32 {
                 internal_error!("Ran out of stack space");
             } else {
+                if padding != 0 {
+                    // The padding sits right below the previous stack top.
+                    // Hand it out to later allocations of a smaller size class instead of wasting it.
+                    let padding_offset = -((*stack_size + padding) as i32);
+                    Self::free_stack_chunk_help(free_stack_chunks, padding_offset, padding);
+                }
                 *stack_size = new_size;
                 -(*stack_size as i32)
             }
@@ -1510,17 +1784,22 @@ impl<
     }
 
     fn free_stack_chunk(&mut self, base_offset: i32, size: u32) {
+        Self::free_stack_chunk_help(&mut self.free_stack_chunks, base_offset, size)
+    }
+
+    fn free_stack_chunk_help(
+        free_stack_chunks: &mut Vec<'a, (i32, u32)>,
+        base_offset: i32,
+        size: u32,
+    ) {
         let loc = (base_offset, size);
         // Note: this position current points to the offset following the specified location.
         // If loc was inserted at this position, it would shift the data at this position over by 1.
-        let pos = self
-            .free_stack_chunks
-            .binary_search(&loc)
-            .unwrap_or_else(|e| e);
+        let pos = free_stack_chunks.binary_search(&loc).unwrap_or_else(|e| e);
 
         // Check for overlap with previous and next free chunk.
         let merge_with_prev = if pos > 0 {
-            if let Some((prev_offset, prev_size)) = self.free_stack_chunks.get(pos - 1) {
+            if let Some((prev_offset, prev_size)) = free_stack_chunks.get(pos - 1) {
                 let prev_end = *prev_offset + *prev_size as i32;
                 if prev_end > base_offset {
                     internal_error!("Double free? A previously freed stack location overlaps with the currently freed stack location.");
@@ -1532,7 +1811,7 @@ impl<
         } else {
             false
         };
-        let merge_with_next = if let Some((next_offset, _)) = self.free_stack_chunks.get(pos) {
+        let merge_with_next = if let Some((next_offset, _)) = free_stack_chunks.get(pos) {
             let current_end = base_offset + size as i32;
             if current_end > *next_offset {
                 internal_error!("Double free? A previously freed stack location overlaps with the currently freed stack location.");
@@ -1544,20 +1823,20 @@ impl<
 
         match (merge_with_prev, merge_with_next) {
             (true, true) => {
-                let (prev_offset, prev_size) = self.free_stack_chunks[pos - 1];
-                let (_, next_size) = self.free_stack_chunks[pos];
-                self.free_stack_chunks[pos - 1] = (prev_offset, prev_size + size + next_size);
-                self.free_stack_chunks.remove(pos);
+                let (prev_offset, prev_size) = free_stack_chunks[pos - 1];
+                let (_, next_size) = free_stack_chunks[pos];
+                free_stack_chunks[pos - 1] = (prev_offset, prev_size + size + next_size);
+                free_stack_chunks.remove(pos);
             }
             (true, false) => {
-                let (prev_offset, prev_size) = self.free_stack_chunks[pos - 1];
-                self.free_stack_chunks[pos - 1] = (prev_offset, prev_size + size);
+                let (prev_offset, prev_size) = free_stack_chunks[pos - 1];
+                free_stack_chunks[pos - 1] = (prev_offset, prev_size + size);
             }
             (false, true) => {
-                let (_, next_size) = self.free_stack_chunks[pos];
-                self.free_stack_chunks[pos] = (base_offset, next_size + size);
+                let (_, next_size) = free_stack_chunks[pos];
+                free_stack_chunks[pos] = (base_offset, next_size + size);
             }
-            (false, false) => self.free_stack_chunks.insert(pos, loc),
+            (false, false) => free_stack_chunks.insert(pos, loc),
         }
     }
 
@@ -1697,4 +1976,134 @@ mod tests {
             (16, -8, vec![in &arena; ])
         );
     }
+
+    #[test]
+    fn claim_stack_memory_reuses_padding() {
+        let arena = bumpalo::Bump::new();
+        let env = test_env(&arena, SpillHeuristic::default());
+        let layout_interner = STLayoutInterner::with_capacity(4, Target::LinuxX64);
+        let mut storage_manager: SystemVStorageManager =
+            new_storage_manager(&env, Target::LinuxX64);
+        storage_manager.reset();
+
+        // An I128 is 16 byte aligned, so after an I64 it leaves 8 bytes of padding,
+        // which the next I64 fills instead of growing the stack.
+        let offsets: std::vec::Vec<_> = [
+            (Symbol::DEV_TMP, Layout::I64),
+            (Symbol::DEV_TMP2, Layout::I128),
+            (Symbol::DEV_TMP3, Layout::I64),
+        ]
+        .into_iter()
+        .map(|(sym, layout)| storage_manager.claim_stack_area_layout(&layout_interner, sym, layout))
+        .collect();
+
+        assert_eq!(offsets, [-8, -32, -16]);
+        assert_eq!(storage_manager.stack_size(), 32);
+    }
+
+    /// Fills three registers with `a`, `b` and `c`, in that order, while building a body that uses
+    /// them in the order `a`, `c`, `b`, and returns the one spilled to make room for another.
+    fn spilled_with(spill_heuristic: SpillHeuristic) -> Symbol {
+        use roc_mono::ir::Expr;
+
+        let arena = bumpalo::Bump::new();
+        let env = Env {
+            arena: &arena,
+            module_id: roc_module::symbol::ModuleId::ATTR,
+            exposed_to_host: MutSet::default(),
+            lazy_literals: true,
+            mode: crate::AssemblyBackendMode::Test,
+            options: crate::DevOptions {
+                spill_heuristic,
+                trap_unreachable: true,
+                ..crate::DevOptions::default()
+            },
+        };
+        let [a, b, c, d, e, f, g] = [
+            Symbol::DEV_TMP,
+            Symbol::DEV_TMP2,
+            Symbol::DEV_TMP3,
+            Symbol::DEV_TMP4,
+            Symbol::DEV_TMP5,
+            Symbol::ARG_1,
+            Symbol::ARG_2,
+        ];
+
+        let ret = arena.alloc(Stmt::Ret(g));
+        let use_b: &Stmt = arena.alloc(Stmt::Let(
+            g,
+            Expr::Struct(arena.alloc([b])),
+            Layout::I64,
+            ret,
+        ));
+        let use_c: &Stmt = arena.alloc(Stmt::Let(
+            f,
+            Expr::Struct(arena.alloc([c])),
+            Layout::I64,
+            use_b,
+        ));
+        let body: &Stmt = arena.alloc(Stmt::Let(
+            e,
+            Expr::Struct(arena.alloc([a])),
+            Layout::I64,
+            use_c,
+        ));
+
+        let mut storage_manager: SystemVStorageManager =
+            new_storage_manager(&env, Target::LinuxX64);
+        storage_manager.reset();
+        if spill_heuristic == SpillHeuristic::FurthestNextUse {
+            storage_manager.set_use_positions(UsePositions::scan(body));
+        }
+        storage_manager.enter_stmt(body);
+
+        let mut buf = bumpalo::vec![in &arena];
+        storage_manager.general_free_regs.truncate(3);
+        for sym in [a, b, c] {
+            storage_manager.claim_general_reg(&mut buf, &sym);
+        }
+        storage_manager.claim_general_reg(&mut buf, &d);
+
+        let spilled: std::vec::Vec<_> = [a, b, c]
+            .into_iter()
+            .filter(|sym| !storage_manager.is_only_in_reg(sym))
+            .collect();
+        assert_eq!(spilled.len(), 1);
+        spilled[0]
+    }
+
+    #[test]
+    fn least_recently_loaded_spills_the_oldest_value() {
+        assert_eq!(
+            spilled_with(SpillHeuristic::LeastRecentlyLoaded),
+            Symbol::DEV_TMP
+        );
+    }
+
+    #[test]
+    fn furthest_next_use_spills_the_value_used_last() {
+        assert_eq!(
+            spilled_with(SpillHeuristic::FurthestNextUse),
+            Symbol::DEV_TMP2
+        );
+    }
+
+    #[test]
+    fn claim_stack_memory_aligns_within_free_chunks() {
+        use bumpalo::vec;
+        let arena = bumpalo::Bump::new();
+
+        // A 16 byte aligned slot is carved out of an 8 byte aligned chunk,
+        // and what is left on either side stays free.
+        assert_eq!(
+            claim_helper(vec![in &arena; (-40, 32)], 48, 16, 16),
+            (48, -32, vec![in &arena; (-40, 8), (-16, 8)])
+        );
+
+        // Once aligned, the value does not fit in the chunk anymore.
+        assert_eq!(
+            claim_helper(vec![in &arena; (-40, 16)], 48, 16, 16),
+            (64, -64, vec![in &arena; (-40, 16)])
+        );
+    }
 }
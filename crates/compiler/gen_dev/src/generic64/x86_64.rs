@@ -130,6 +130,8 @@ pub struct X86_64SystemV {}
 const STACK_ALIGNMENT: u8 = 16;
 
 impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64SystemV {
+    const NAME: &'static str = "System V";
+
     const BASE_PTR_REG: X86_64GeneralReg = X86_64GeneralReg::RBP;
     const STACK_PTR_REG: X86_64GeneralReg = X86_64GeneralReg::RSP;
 
@@ -201,6 +203,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
         X86_64FloatReg::XMM0,
     ];
     const SHADOW_SPACE_SIZE: u8 = 0;
+    const RED_ZONE_SIZE: u8 = 128;
 
     // These are registers that a called function must save and restore if it wants to use them.
     #[inline(always)]
@@ -228,6 +231,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
         saved_float_regs: &[X86_64FloatReg],
         requested_stack_size: i32,
         fn_call_stack_size: i32,
+        requires_stack_modification: bool,
     ) -> i32 {
         x86_64_generic_setup_stack(
             buf,
@@ -235,6 +239,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
             saved_float_regs,
             requested_stack_size,
             fn_call_stack_size,
+            requires_stack_modification,
         )
     }
 
@@ -245,6 +250,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
         saved_float_regs: &[X86_64FloatReg],
         aligned_stack_size: i32,
         fn_call_stack_size: i32,
+        requires_stack_modification: bool,
     ) {
         x86_64_generic_cleanup_stack(
             buf,
@@ -252,6 +258,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
             saved_float_regs,
             aligned_stack_size,
             fn_call_stack_size,
+            requires_stack_modification,
         )
     }
 
@@ -315,6 +322,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
             // Set the first reg to the address base + offset.
             let ret_reg = Self::GENERAL_PARAM_REGS[general_i];
             general_i += 1;
+            storage_manager.evict_general_reg(buf, ret_reg);
             X86_64Assembler::add_reg64_reg64_imm32(
                 buf,
                 ret_reg,
@@ -357,29 +365,35 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
             _ if layout_interner.stack_size(*layout) == 0 => {}
             _ if !Self::returns_via_arg_pointer(layout_interner, layout) => {
                 let (base_offset, size) = storage_manager.stack_offset_and_size(sym);
-
-                if size <= 8 {
-                    X86_64Assembler::mov_reg64_base32(
-                        buf,
-                        Self::GENERAL_RETURN_REGS[0],
-                        base_offset,
-                    );
-                } else if size <= 16 {
-                    X86_64Assembler::mov_reg64_base32(
-                        buf,
-                        Self::GENERAL_RETURN_REGS[0],
-                        base_offset,
-                    );
-                    X86_64Assembler::mov_reg64_base32(
-                        buf,
-                        Self::GENERAL_RETURN_REGS[1],
-                        base_offset + 8,
-                    );
-                } else {
+                if size > 16 {
                     internal_error!(
                         "types that don't return via arg pointer must be less than 16 bytes"
                     );
                 }
+
+                let float_eightbytes = Self::float_eightbytes(layout_interner, *layout);
+                let mut general_regs = Self::GENERAL_RETURN_REGS.iter();
+                let mut float_regs = Self::FLOAT_RETURN_REGS.iter();
+                for (i, is_float) in float_eightbytes
+                    .iter()
+                    .enumerate()
+                    .take(size.div_ceil(8) as usize)
+                {
+                    let offset = base_offset + 8 * i as i32;
+                    if *is_float {
+                        X86_64Assembler::mov_freg64_base32(
+                            buf,
+                            *float_regs.next().unwrap(),
+                            offset,
+                        );
+                    } else {
+                        X86_64Assembler::mov_reg64_base32(
+                            buf,
+                            *general_regs.next().unwrap(),
+                            offset,
+                        );
+                    }
+                }
             }
             _ => {
                 // This is a large type returned via the arg pointer.
@@ -417,22 +431,37 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
             }
             _ if !Self::returns_via_arg_pointer(layout_interner, layout) => {
                 let size = layout_interner.stack_size(*layout);
-                let offset =
-                    storage_manager.claim_stack_area_layout(layout_interner, *sym, *layout);
-                if size <= 8 {
-                    X86_64Assembler::mov_base32_reg64(buf, offset, Self::GENERAL_RETURN_REGS[0]);
-                } else if size <= 16 {
-                    X86_64Assembler::mov_base32_reg64(buf, offset, Self::GENERAL_RETURN_REGS[0]);
-                    X86_64Assembler::mov_base32_reg64(
-                        buf,
-                        offset + 8,
-                        Self::GENERAL_RETURN_REGS[1],
-                    );
-                } else {
+                if size > 16 {
                     internal_error!(
                         "types that don't return via arg pointer must be less than 16 bytes"
                     );
                 }
+                let base_offset =
+                    storage_manager.claim_stack_area_layout(layout_interner, *sym, *layout);
+
+                let float_eightbytes = Self::float_eightbytes(layout_interner, *layout);
+                let mut general_regs = Self::GENERAL_RETURN_REGS.iter();
+                let mut float_regs = Self::FLOAT_RETURN_REGS.iter();
+                for (i, is_float) in float_eightbytes
+                    .iter()
+                    .enumerate()
+                    .take(size.div_ceil(8) as usize)
+                {
+                    let offset = base_offset + 8 * i as i32;
+                    if *is_float {
+                        X86_64Assembler::mov_base32_freg64(
+                            buf,
+                            offset,
+                            *float_regs.next().unwrap(),
+                        );
+                    } else {
+                        X86_64Assembler::mov_base32_reg64(
+                            buf,
+                            offset,
+                            *general_regs.next().unwrap(),
+                        );
+                    }
+                }
             }
             _ => {
                 // This should have been received via an arg pointer.
@@ -442,6 +471,14 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
         }
     }
 
+    fn returns_floats_in_float_regs<'a>(
+        layout_interner: &STLayoutInterner<'a>,
+        layout: &InLayout<'a>,
+    ) -> bool {
+        !Self::returns_via_arg_pointer(layout_interner, layout)
+            && Self::float_eightbytes(layout_interner, *layout).contains(&true)
+    }
+
     fn setjmp(buf: &mut Vec<'_, u8>) {
         use X86_64GeneralReg::*;
         type ASM = X86_64Assembler;
@@ -534,7 +571,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
         ASM::add_reg64_reg64_imm32(buf, RDX, RSI, 1);
 
         // the setlongjmp_buffer
-        ASM::data_pointer(buf, relocs, String::from("setlongjmp_buffer"), RDI);
+        ASM::data_pointer(buf, relocs, String::from("setlongjmp_buffer"), 0, RDI);
 
         // the value to return from the longjmp. It is a pointer to the last 3 words of the setlongjmp_buffer
         // they represent the errore message.
@@ -548,6 +585,40 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Syste
 
         Self::longjmp(buf)
     }
+
+    fn entry_shim(buf: &mut Vec<'_, u8>, relocs: &mut Vec<'_, Relocation>, main: String) {
+        use X86_64GeneralReg::*;
+        type ASM = X86_64Assembler;
+
+        // based on the musl libc _start, but without a libc to initialize
+        //
+        // _start:
+        //     xor    rbp, rbp
+        //     mov    rdi, QWORD PTR [rsp]
+        //     lea    rsi, [rsp+0x8]
+        //     and    rsp, -16
+        //     call   main
+        //     mov    rdi, rax
+        //     mov    rax, 231
+        //     syscall
+
+        // mark the outermost frame for debuggers and unwinders
+        ASM::xor_reg64_reg64_reg64(buf, RBP, RBP, RBP);
+
+        // the kernel leaves argc on top of the stack, followed by the argv pointers
+        ASM::mov_reg64_mem64_offset32(buf, RDI, RSP, 0);
+        lea_reg64_offset8(buf, RSI, RSP, 0x8);
+
+        // the stack should already be aligned, but libc doesn't count on it either
+        and_reg64_imm8(buf, RSP, -16);
+
+        ASM::call(buf, relocs, main);
+
+        // exit_group rather than exit, so no other thread outlives main
+        ASM::mov_reg64_reg64(buf, RDI, RAX);
+        ASM::mov_reg64_imm64(buf, RAX, 231);
+        syscall(buf);
+    }
 }
 
 fn copy_symbol_to_stack_offset<'a, CC>(
@@ -694,6 +765,9 @@ impl X64_64SystemVStoreArgs {
                     let reg1 = Self::GENERAL_PARAM_REGS[self.general_i];
                     let reg2 = Self::GENERAL_PARAM_REGS[self.general_i + 1];
 
+                    storage_manager.evict_general_reg(buf, reg1);
+                    storage_manager.evict_general_reg(buf, reg2);
+
                     ASM::mov_reg64_base32(buf, reg1, offset);
                     ASM::mov_reg64_base32(buf, reg2, offset + 8);
 
@@ -764,14 +838,14 @@ impl X64_64SystemVStoreArgs {
     ) {
         match Self::GENERAL_PARAM_REGS.get(self.general_i) {
             Some(reg) => {
-                storage_manager.load_to_specified_general_reg(buf, &sym, *reg);
+                storage_manager.load_to_param_general_reg(buf, &sym, *reg);
                 self.general_i += 1;
             }
             None => {
                 // Copy to stack using return reg as buffer.
                 let tmp = Self::GENERAL_RETURN_REGS[0];
 
-                storage_manager.load_to_specified_general_reg(buf, &sym, tmp);
+                storage_manager.load_to_param_general_reg(buf, &sym, tmp);
                 X86_64Assembler::mov_stack32_reg64(buf, self.tmp_stack_offset, tmp);
 
                 self.tmp_stack_offset += 8;
@@ -787,14 +861,14 @@ impl X64_64SystemVStoreArgs {
     ) {
         match Self::FLOAT_PARAM_REGS.get(self.float_i) {
             Some(reg) => {
-                storage_manager.load_to_specified_float_reg(buf, &sym, *reg);
+                storage_manager.load_to_param_float_reg(buf, &sym, *reg);
                 self.float_i += 1;
             }
             None => {
                 // Copy to stack using return reg as buffer.
                 let tmp = Self::FLOAT_RETURN_REGS[0];
 
-                storage_manager.load_to_specified_float_reg(buf, &sym, tmp);
+                storage_manager.load_to_param_float_reg(buf, &sym, tmp);
                 X86_64Assembler::mov_stack32_freg64(buf, self.tmp_stack_offset, tmp);
 
                 self.tmp_stack_offset += 8;
@@ -814,6 +888,7 @@ impl X64_64SystemVStoreArgs {
 
         match Self::GENERAL_PARAM_REGS.get(self.general_i) {
             Some(reg) => {
+                storage_manager.evict_general_reg(buf, *reg);
                 ASM::mov_reg64_base32(buf, *reg, offset);
 
                 self.general_i += 1;
@@ -844,6 +919,9 @@ impl X64_64SystemVStoreArgs {
             let reg1 = Self::GENERAL_PARAM_REGS[self.general_i];
             let reg2 = Self::GENERAL_PARAM_REGS[self.general_i + 1];
 
+            storage_manager.evict_general_reg(buf, reg1);
+            storage_manager.evict_general_reg(buf, reg2);
+
             ASM::mov_reg64_base32(buf, reg1, offset);
             ASM::mov_reg64_base32(buf, reg2, offset + 8);
 
@@ -902,6 +980,9 @@ impl X64_64WindowsFastCallStoreArgs {
                     let reg1 = Self::GENERAL_PARAM_REGS[self.general_i];
                     let reg2 = Self::GENERAL_PARAM_REGS[self.general_i + 1];
 
+                    storage_manager.evict_general_reg(buf, reg1);
+                    storage_manager.evict_general_reg(buf, reg2);
+
                     ASM::mov_reg64_base32(buf, reg1, offset);
                     ASM::mov_reg64_base32(buf, reg2, offset + 8);
 
@@ -927,6 +1008,7 @@ impl X64_64WindowsFastCallStoreArgs {
                         // if there is a general purpose register available, use it to store a pointer to the value
                         let (base_offset, _size) = storage_manager.stack_offset_and_size(&sym);
 
+                        storage_manager.evict_general_reg(buf, *reg);
                         ASM::add_reg64_reg64_imm32(buf, *reg, X86_64GeneralReg::RBP, base_offset);
 
                         self.general_i += 1;
@@ -988,14 +1070,14 @@ impl X64_64WindowsFastCallStoreArgs {
     ) {
         match Self::GENERAL_PARAM_REGS.get(self.general_i) {
             Some(reg) => {
-                storage_manager.load_to_specified_general_reg(buf, &sym, *reg);
+                storage_manager.load_to_param_general_reg(buf, &sym, *reg);
                 self.general_i += 1;
             }
             None => {
                 // Copy to stack using return reg as buffer.
                 let tmp = Self::GENERAL_RETURN_REGS[0];
 
-                storage_manager.load_to_specified_general_reg(buf, &sym, tmp);
+                storage_manager.load_to_param_general_reg(buf, &sym, tmp);
                 X86_64Assembler::mov_stack32_reg64(buf, self.tmp_stack_offset, tmp);
 
                 self.tmp_stack_offset += 8;
@@ -1011,14 +1093,14 @@ impl X64_64WindowsFastCallStoreArgs {
     ) {
         match Self::FLOAT_PARAM_REGS.get(self.float_i) {
             Some(reg) => {
-                storage_manager.load_to_specified_float_reg(buf, &sym, *reg);
+                storage_manager.load_to_param_float_reg(buf, &sym, *reg);
                 self.float_i += 1;
             }
             None => {
                 // Copy to stack using return reg as buffer.
                 let tmp = Self::FLOAT_RETURN_REGS[0];
 
-                storage_manager.load_to_specified_float_reg(buf, &sym, tmp);
+                storage_manager.load_to_param_float_reg(buf, &sym, tmp);
                 X86_64Assembler::mov_stack32_freg64(buf, self.tmp_stack_offset, tmp);
 
                 self.tmp_stack_offset += 8;
@@ -1345,9 +1427,59 @@ impl X86_64SystemV {
         // details here: https://github.com/hjl-tools/x86-psABI/wiki/x86-64-psABI-1.0.pdf
         interner.stack_size(*ret_layout) > 16
     }
+
+    /// Classifies the (up to two) eightbytes of a value returned in registers.
+    /// An eightbyte that holds nothing but floats is returned in a float register,
+    /// every other eightbyte in a general register.
+    ///
+    /// This is the SSE/INTEGER classification of the System V ABI (section 3.2.3), so it holds for
+    /// every proc, not just the builtins: a `{ x : F64, y : I64 }` comes back in xmm0 and rax,
+    /// exactly as a C host or the zig builtins expect.
+    fn float_eightbytes<'a>(interner: &STLayoutInterner<'a>, layout: InLayout<'a>) -> [bool; 2] {
+        fn visit<'a>(
+            interner: &STLayoutInterner<'a>,
+            layout: InLayout<'a>,
+            offset: u32,
+            has_float: &mut [bool; 2],
+            has_int: &mut [bool; 2],
+        ) {
+            let size = interner.stack_size(layout);
+            match interner.get_repr(layout) {
+                LayoutRepr::Struct(field_layouts) => {
+                    let mut field_offset = offset;
+                    for field in field_layouts {
+                        visit(interner, *field, field_offset, has_float, has_int);
+                        field_offset += interner.stack_size(*field);
+                    }
+                }
+                LayoutRepr::LambdaSet(lambda_set) => visit(
+                    interner,
+                    lambda_set.runtime_representation(),
+                    offset,
+                    has_float,
+                    has_int,
+                ),
+                single_register_floats!() => has_float[(offset / 8) as usize] = true,
+                _ if size == 0 => {}
+                _ => {
+                    for eightbyte in (offset / 8)..=((offset + size - 1) / 8).min(1) {
+                        has_int[eightbyte as usize] = true;
+                    }
+                }
+            }
+        }
+
+        let mut has_float = [false; 2];
+        let mut has_int = [false; 2];
+        visit(interner, layout, 0, &mut has_float, &mut has_int);
+
+        [has_float[0] && !has_int[0], has_float[1] && !has_int[1]]
+    }
 }
 
 impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64WindowsFastcall {
+    const NAME: &'static str = "Windows fastcall";
+
     const BASE_PTR_REG: X86_64GeneralReg = X86_64GeneralReg::RBP;
     const STACK_PTR_REG: X86_64GeneralReg = X86_64GeneralReg::RSP;
 
@@ -1412,6 +1544,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Windo
         X86_64FloatReg::XMM0,
     ];
     const SHADOW_SPACE_SIZE: u8 = 32;
+    const RED_ZONE_SIZE: u8 = 0;
 
     // These are registers that a called function must save and restore if it wants to use them.
     //
@@ -1461,6 +1594,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Windo
         saved_float_regs: &[X86_64FloatReg],
         requested_stack_size: i32,
         fn_call_stack_size: i32,
+        requires_stack_modification: bool,
     ) -> i32 {
         x86_64_generic_setup_stack(
             buf,
@@ -1468,6 +1602,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Windo
             saved_float_regs,
             requested_stack_size,
             fn_call_stack_size,
+            requires_stack_modification,
         )
     }
 
@@ -1478,6 +1613,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Windo
         saved_float_regs: &[X86_64FloatReg],
         aligned_stack_size: i32,
         fn_call_stack_size: i32,
+        requires_stack_modification: bool,
     ) {
         x86_64_generic_cleanup_stack(
             buf,
@@ -1485,9 +1621,28 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Windo
             saved_float_regs,
             aligned_stack_size,
             fn_call_stack_size,
+            requires_stack_modification,
         )
     }
 
+    fn unwind_info(
+        prologue: &[u8],
+        frame_start: usize,
+        saved_general_regs: &[X86_64GeneralReg],
+        saved_float_regs: &[X86_64FloatReg],
+        aligned_stack_size: i32,
+        fn_call_stack_size: i32,
+    ) -> Option<std::vec::Vec<u8>> {
+        Some(windows_unwind_info(
+            prologue,
+            frame_start,
+            saved_general_regs,
+            saved_float_regs,
+            aligned_stack_size,
+            fn_call_stack_size,
+        ))
+    }
+
     #[inline(always)]
     fn load_args<'a>(
         buf: &mut Vec<'a, u8>,
@@ -1542,6 +1697,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Windo
             // Set the first reg to the address base + offset.
             let ret_reg = Self::GENERAL_PARAM_REGS[general_i];
             general_i += 1;
+            storage_manager.evict_general_reg(buf, ret_reg);
             X86_64Assembler::add_reg64_reg64_imm32(
                 buf,
                 ret_reg,
@@ -1773,7 +1929,7 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Windo
 
         // the setlongjmp_buffer
         let env = R8;
-        ASM::data_pointer(buf, relocs, String::from("setlongjmp_buffer"), env);
+        ASM::data_pointer(buf, relocs, String::from("setlongjmp_buffer"), 0, env);
 
         // move the roc_str bytes into the setlongjmp_buffer
         for offset in [0, 8, 16] {
@@ -1812,6 +1968,10 @@ impl CallConv<X86_64GeneralReg, X86_64FloatReg, X86_64Assembler> for X86_64Windo
 
         jmp_reg64_offset8(buf, env, 0x50)
     }
+
+    fn entry_shim(_buf: &mut Vec<'_, u8>, _relocs: &mut Vec<'_, Relocation>, _main: String) {
+        internal_error!("entry shims are only generated for x86_64 Linux");
+    }
 }
 
 impl X86_64WindowsFastcall {
@@ -1835,12 +1995,13 @@ fn x86_64_generic_setup_stack(
     saved_float_regs: &[X86_64FloatReg],
     requested_stack_size: i32,
     fn_call_stack_size: i32,
+    requires_stack_modification: bool,
 ) -> i32 {
     X86_64Assembler::push_reg64(buf, X86_64GeneralReg::RBP);
     X86_64Assembler::mov_reg64_reg64(buf, X86_64GeneralReg::RBP, X86_64GeneralReg::RSP);
 
     let full_stack_size = match requested_stack_size
-        .checked_add(8 * (saved_general_regs.len() + saved_float_regs.len()) as i32)
+        .checked_add((8 * saved_general_regs.len() + 16 * saved_float_regs.len()) as i32)
         .and_then(|size| size.checked_add(fn_call_stack_size))
     {
         Some(size) => size,
@@ -1858,12 +2019,14 @@ fn x86_64_generic_setup_stack(
     };
     if let Some(aligned_stack_size) = full_stack_size.checked_add(offset as i32) {
         if aligned_stack_size > 0 {
-            X86_64Assembler::sub_reg64_reg64_imm32(
-                buf,
-                X86_64GeneralReg::RSP,
-                X86_64GeneralReg::RSP,
-                aligned_stack_size,
-            );
+            if requires_stack_modification {
+                X86_64Assembler::sub_reg64_reg64_imm32(
+                    buf,
+                    X86_64GeneralReg::RSP,
+                    X86_64GeneralReg::RSP,
+                    aligned_stack_size,
+                );
+            }
 
             // Put values at the top of the stack to avoid conflicts with previously saved variables.
             let mut offset = aligned_stack_size - fn_call_stack_size;
@@ -1872,8 +2035,12 @@ fn x86_64_generic_setup_stack(
                 offset -= 8;
             }
             for reg in saved_float_regs {
-                X86_64Assembler::mov_base32_freg64(buf, -offset, *reg);
-                offset -= 8;
+                if is_16_byte_aligned(-offset) {
+                    movaps_base64_offset32_freg128(buf, X86_64GeneralReg::RBP, -offset, *reg);
+                } else {
+                    movups_base64_offset32_freg128(buf, X86_64GeneralReg::RBP, -offset, *reg);
+                }
+                offset -= 16;
             }
             aligned_stack_size
         } else {
@@ -1892,6 +2059,7 @@ fn x86_64_generic_cleanup_stack(
     saved_float_regs: &[X86_64FloatReg],
     aligned_stack_size: i32,
     fn_call_stack_size: i32,
+    requires_stack_modification: bool,
 ) {
     if aligned_stack_size > 0 {
         let mut offset = aligned_stack_size - fn_call_stack_size;
@@ -1900,18 +2068,153 @@ fn x86_64_generic_cleanup_stack(
             offset -= 8;
         }
         for reg in saved_float_regs {
-            X86_64Assembler::mov_freg64_base32(buf, *reg, -offset);
-            offset -= 8;
+            if is_16_byte_aligned(-offset) {
+                movaps_freg128_base64_offset32(buf, *reg, X86_64GeneralReg::RBP, -offset);
+            } else {
+                movups_freg128_base64_offset32(buf, *reg, X86_64GeneralReg::RBP, -offset);
+            }
+            offset -= 16;
         }
-        X86_64Assembler::add_reg64_reg64_imm32(
-            buf,
+        if requires_stack_modification {
+            X86_64Assembler::add_reg64_reg64_imm32(
+                buf,
+                X86_64GeneralReg::RSP,
+                X86_64GeneralReg::RSP,
+                aligned_stack_size,
+            );
+        }
+    }
+    //X86_64Assembler::mov_reg64_reg64(buf, X86_64GeneralReg::RSP, X86_64GeneralReg::RBP);
+    X86_64Assembler::pop_reg64(buf, X86_64GeneralReg::RBP);
+}
+
+/// Builds the `UNWIND_INFO` that tells the Windows unwinder how to undo the prologue of
+/// `x86_64_generic_setup_stack`. It goes in the `.xdata` section.
+/// See https://learn.microsoft.com/en-us/cpp/build/exception-handling-x64
+///
+/// The frame is described relative to RSP at the end of the prologue, where the saved registers
+/// are at positive offsets. RBP is restored by undoing its push, so `mov rbp, rsp` needs no code.
+///
+/// The code offsets are those of the instructions in `prologue`, which sets up the frame starting
+/// at `frame_start`. Each instruction is checked against what `x86_64_generic_setup_stack` emits
+/// for it, so a prologue that doesn't match gets no wrong unwind info.
+fn windows_unwind_info(
+    prologue: &[u8],
+    frame_start: usize,
+    saved_general_regs: &[X86_64GeneralReg],
+    saved_float_regs: &[X86_64FloatReg],
+    aligned_stack_size: i32,
+    fn_call_stack_size: i32,
+) -> std::vec::Vec<u8> {
+    const UWOP_PUSH_NONVOL: u8 = 0;
+    const UWOP_ALLOC_LARGE: u8 = 1;
+    const UWOP_ALLOC_SMALL: u8 = 2;
+    const UWOP_SAVE_NONVOL: u8 = 4;
+    const UWOP_SAVE_NONVOL_FAR: u8 = 5;
+    const UWOP_SAVE_XMM128: u8 = 8;
+    const UWOP_SAVE_XMM128_FAR: u8 = 9;
+
+    // Step over the instructions of the frame setup to find where each of them ends.
+    // Every code is recorded at that offset, with any extra 16 bit slots it needs.
+    let arena = bumpalo::Bump::new();
+    let mut inst = bumpalo::vec![in &arena];
+    let mut codes: std::vec::Vec<(usize, u8, u8, std::vec::Vec<u16>)> = std::vec::Vec::new();
+
+    let mut end = frame_start;
+    let mut step_over = |inst: &mut Vec<'_, u8>| {
+        if prologue.get(end..end + inst.len()) != Some(&inst[..]) {
+            internal_error!("the prologue at {end:#x} does not set up the frame it is unwound as");
+        }
+        end += inst.len();
+        inst.clear();
+        end
+    };
+
+    let scaled_or_far = |offset: i32, scale: i32| match u16::try_from(offset / scale) {
+        Ok(scaled) if offset % scale == 0 => (false, vec![scaled]),
+        _ => (true, vec![offset as u16, (offset >> 16) as u16]),
+    };
+
+    X86_64Assembler::push_reg64(&mut inst, X86_64GeneralReg::RBP);
+    codes.push((
+        step_over(&mut inst),
+        UWOP_PUSH_NONVOL,
+        X86_64GeneralReg::RBP as u8,
+        vec![],
+    ));
+    X86_64Assembler::mov_reg64_reg64(&mut inst, X86_64GeneralReg::RBP, X86_64GeneralReg::RSP);
+    step_over(&mut inst);
+
+    if aligned_stack_size > 0 {
+        X86_64Assembler::sub_reg64_reg64_imm32(
+            &mut inst,
             X86_64GeneralReg::RSP,
             X86_64GeneralReg::RSP,
             aligned_stack_size,
         );
+        let alloc = if aligned_stack_size <= 128 {
+            (UWOP_ALLOC_SMALL, (aligned_stack_size / 8 - 1) as u8, vec![])
+        } else {
+            match scaled_or_far(aligned_stack_size, 8) {
+                (false, slots) => (UWOP_ALLOC_LARGE, 0, slots),
+                (true, _) => (UWOP_ALLOC_LARGE, 1, scaled_or_far(aligned_stack_size, 1).1),
+            }
+        };
+        codes.push((step_over(&mut inst), alloc.0, alloc.1, alloc.2));
+
+        let mut offset = aligned_stack_size - fn_call_stack_size;
+        for reg in saved_general_regs {
+            X86_64Assembler::mov_base32_reg64(&mut inst, -offset, *reg);
+            let op = match scaled_or_far(aligned_stack_size - offset, 8) {
+                (false, slots) => (UWOP_SAVE_NONVOL, slots),
+                (true, slots) => (UWOP_SAVE_NONVOL_FAR, slots),
+            };
+            codes.push((step_over(&mut inst), op.0, *reg as u8, op.1));
+            offset -= 8;
+        }
+        for reg in saved_float_regs {
+            if is_16_byte_aligned(-offset) {
+                movaps_base64_offset32_freg128(&mut inst, X86_64GeneralReg::RBP, -offset, *reg);
+            } else {
+                movups_base64_offset32_freg128(&mut inst, X86_64GeneralReg::RBP, -offset, *reg);
+            }
+            let op = match scaled_or_far(aligned_stack_size - offset, 16) {
+                (false, slots) => (UWOP_SAVE_XMM128, slots),
+                (true, slots) => (UWOP_SAVE_XMM128_FAR, slots),
+            };
+            codes.push((step_over(&mut inst), op.0, *reg as u8, op.1));
+            offset -= 16;
+        }
     }
-    //X86_64Assembler::mov_reg64_reg64(buf, X86_64GeneralReg::RSP, X86_64GeneralReg::RBP);
-    X86_64Assembler::pop_reg64(buf, X86_64GeneralReg::RBP);
+
+    let prologue_size = match u8::try_from(end) {
+        Ok(size) => size,
+        Err(_) => internal_error!("the prologue is too long to unwind on Windows"),
+    };
+    let slots: usize = codes.iter().map(|(_, _, _, extra)| 1 + extra.len()).sum();
+
+    // Version 1, no flags, and no frame register.
+    let mut info = vec![1, prologue_size, slots as u8, 0];
+    // The unwinder reads the codes from the end of the prologue backwards.
+    for (code_offset, op, op_info, extra) in codes.into_iter().rev() {
+        info.extend([code_offset as u8, op | (op_info << 4)]);
+        for slot in extra {
+            info.extend(slot.to_le_bytes());
+        }
+    }
+    // The codes are padded to an even number of slots, keeping the info 4 byte aligned.
+    if slots % 2 == 1 {
+        info.extend([0, 0]);
+    }
+    info
+}
+
+/// Whether `[rbp + offset]` is 16-byte aligned.
+/// The caller's `call` leaves RSP 8 bytes off of 16-byte alignment, so it is aligned again
+/// once RBP has been pushed, and RBP is set from it.
+#[inline(always)]
+fn is_16_byte_aligned(offset: i32) -> bool {
+    offset % 16 == 0
 }
 
 type Reg64 = X86_64GeneralReg;
@@ -1931,6 +2234,9 @@ where
 }
 
 impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
+    const SHIFT_COUNT_REG: Option<X86_64GeneralReg> = Some(X86_64GeneralReg::RCX);
+    const JMP_IMM32_SIZE: u64 = 5;
+
     // These functions should map to the raw assembly functions below.
     // In some cases, that means you can just directly call one of the direct assembly functions.
     #[inline(always)]
@@ -1953,6 +2259,7 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         relocs.push(Relocation::LocalData {
             offset: buf.len() as u64 - 4,
             data: 0x7fffffffffffffffu64.to_le_bytes().to_vec(),
+            align: 8,
         });
 
         andpd_freg64_freg64(buf, dst, src);
@@ -1971,6 +2278,7 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         relocs.push(Relocation::LocalData {
             offset: buf.len() as u64 - 4,
             data: 0x7fffffffu64.to_le_bytes().to_vec(),
+            align: 4,
         });
 
         andps_freg32_freg32(buf, dst, src);
@@ -1991,6 +2299,11 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
     fn add_reg64_reg64_reg64(buf: &mut Vec<'_, u8>, dst: Reg64, src1: Reg64, src2: Reg64) {
         binop_move_src_to_dst_reg64(buf, add_reg64_reg64, dst, src1, src2)
     }
+    #[inline(always)]
+    fn adds_reg64_reg64_reg64(buf: &mut Vec<'_, u8>, dst: Reg64, src1: Reg64, src2: Reg64) {
+        // on x86_64, `add` always sets the overflow flag
+        Self::add_reg64_reg64_reg64(buf, dst, src1, src2)
+    }
 
     #[inline(always)]
     fn add_freg32_freg32_freg32(
@@ -2087,18 +2400,46 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         buf: &mut Vec<'_, u8>,
         relocs: &mut Vec<'_, Relocation>,
         fn_name: String,
+        addend: i64,
         dst: X86_64GeneralReg,
     ) {
         lea_reg64(buf, dst);
 
+        // A GOT entry only holds the address of the symbol itself, so the relocation refers to
+        // the symbol and the offset into the data is added once its address is loaded.
         relocs.push(Relocation::LinkedData {
             offset: buf.len() as u64 - 4,
             name: fn_name,
+            addend: 0,
         });
 
         // on X86_64, we actually get a pointer to a pointer
-        // so we just dereference to get just a pointer to the data
+        // so we just dereference to get just a pointer to the data.
         X86_64Assembler::mov_reg64_mem64_offset32(buf, dst, dst, 0);
+
+        if addend != 0 {
+            let addend = i32::try_from(addend).unwrap_or_else(|_| {
+                internal_error!("data offset {addend} does not fit in 32 bits")
+            });
+            add_reg64_imm32(buf, dst, addend);
+        }
+    }
+
+    #[inline(always)]
+    fn load_stack_guard(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg) {
+        // glibc and musl both keep the guard at fs:0x28 in the thread control block.
+        mov_reg64_fs_offset32(buf, dst, 0x28);
+    }
+
+    #[inline(always)]
+    fn add_scaled_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: X86_64GeneralReg,
+        base: X86_64GeneralReg,
+        index: X86_64GeneralReg,
+        scale: u8,
+    ) {
+        lea_reg64_base_index_scale(buf, dst, base, index, scale);
     }
 
     #[inline(always)]
@@ -2284,12 +2625,56 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         buf.len()
     }
 
+    #[inline(always)]
+    fn jmp_to_offset(buf: &mut Vec<'_, u8>, target: u64) {
+        // on x86_64, jumps are calculated from the end of the jmp instruction
+        let offset = target as i64 - buf.len() as i64;
+        match i8::try_from(offset - 2) {
+            Ok(short_offset) => jmp_imm8(buf, short_offset),
+            Err(_) => jmp_imm32(buf, (offset - 5) as i32),
+        }
+    }
+
     #[inline(always)]
     fn tail_call(buf: &mut Vec<'_, u8>) -> u64 {
         Self::jmp_imm32(buf, 0);
         buf.len() as u64 - 4
     }
 
+    #[inline(always)]
+    fn jo_imm32(buf: &mut Vec<'_, u8>, offset: i32) -> usize {
+        jo_imm32(buf, offset);
+
+        // on x86_64, jumps are calculated from the end of the jmp instruction
+        buf.len()
+    }
+
+    #[inline(always)]
+    fn jno_imm32(buf: &mut Vec<'_, u8>, offset: i32) -> usize {
+        jno_imm32(buf, offset);
+
+        // on x86_64, jumps are calculated from the end of the jmp instruction
+        buf.len()
+    }
+
+    #[inline(always)]
+    fn jz_reg64_imm32(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg, offset: i32) -> usize {
+        test_reg64_reg64(buf, reg, reg);
+        je_imm32(buf, offset);
+
+        // on x86_64, jumps are calculated from the end of the jmp instruction
+        buf.len()
+    }
+
+    #[inline(always)]
+    fn jnz_reg64_imm32(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg, offset: i32) -> usize {
+        test_reg64_reg64(buf, reg, reg);
+        jne_imm32(buf, offset);
+
+        // on x86_64, jumps are calculated from the end of the jmp instruction
+        buf.len()
+    }
+
     #[inline(always)]
     fn jne_reg64_imm64_imm32<'a, ASM, CC>(
         buf: &mut Vec<'a, u8>,
@@ -2329,6 +2714,7 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         relocs.push(Relocation::LocalData {
             offset: buf.len() as u64 - 4,
             data: imm.to_le_bytes().to_vec(),
+            align: 4,
         });
     }
     #[inline(always)]
@@ -2342,6 +2728,7 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         relocs.push(Relocation::LocalData {
             offset: buf.len() as u64 - 4,
             data: imm.to_le_bytes().to_vec(),
+            align: 8,
         });
     }
     #[inline(always)]
@@ -2349,6 +2736,11 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         mov_reg64_imm64(buf, dst, imm);
     }
     #[inline(always)]
+    fn movzx_reg64_imm8(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: u8) {
+        // Writing a 32 bit register zeroes the upper 32 bits.
+        mov_reg32_imm32(buf, dst, imm as i32);
+    }
+    #[inline(always)]
     fn mov_freg64_freg64(buf: &mut Vec<'_, u8>, dst: X86_64FloatReg, src: X86_64FloatReg) {
         movsd_freg64_freg64(buf, dst, src);
     }
@@ -2468,6 +2860,23 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         movss_base32_offset32_freg32(buf, X86_64GeneralReg::RBP, offset, src)
     }
 
+    #[inline(always)]
+    fn mov_base32_base32_128(
+        buf: &mut Vec<'_, u8>,
+        tmp: X86_64FloatReg,
+        to_offset: i32,
+        from_offset: i32,
+    ) {
+        let rbp = X86_64GeneralReg::RBP;
+        if is_16_byte_aligned(from_offset) && is_16_byte_aligned(to_offset) {
+            movdqa_freg128_base64_offset32(buf, tmp, rbp, from_offset);
+            movdqa_base64_offset32_freg128(buf, rbp, to_offset, tmp);
+        } else {
+            movdqu_freg128_base64_offset32(buf, tmp, rbp, from_offset);
+            movdqu_base64_offset32_freg128(buf, rbp, to_offset, tmp);
+        }
+    }
+
     #[inline(always)]
     fn movesd_mem64_offset32_freg64(
         buf: &mut Vec<'_, u8>,
@@ -2527,6 +2936,16 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         }
     }
 
+    #[inline(always)]
+    fn movnti_mem64_offset32_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: X86_64GeneralReg,
+        offset: i32,
+        src: X86_64GeneralReg,
+    ) {
+        movnti_base64_offset32_reg64(buf, dst, offset, src)
+    }
+
     #[inline(always)]
     fn movsx_reg_base32(
         buf: &mut Vec<'_, u8>,
@@ -2596,6 +3015,18 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         mov_base_offset32_reg(buf, register_width, X86_64GeneralReg::RSP, offset, src)
     }
 
+    #[inline(always)]
+    fn inc_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
+        mov_reg64_reg64(buf, dst, src);
+        inc_reg64(buf, dst);
+    }
+
+    #[inline(always)]
+    fn dec_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
+        mov_reg64_reg64(buf, dst, src);
+        dec_reg64(buf, dst);
+    }
+
     #[inline(always)]
     fn neg_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
         mov_reg64_reg64(buf, dst, src);
@@ -2644,6 +3075,16 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         mov_reg64_reg64(buf, dst, src1);
         sub_reg64_reg64(buf, dst, src2);
     }
+    #[inline(always)]
+    fn subs_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: X86_64GeneralReg,
+        src1: X86_64GeneralReg,
+        src2: X86_64GeneralReg,
+    ) {
+        // on x86_64, `sub` always sets the overflow flag
+        Self::sub_reg64_reg64_reg64(buf, dst, src1, src2)
+    }
 
     #[inline(always)]
     fn eq_reg_reg_reg(
@@ -2706,6 +3147,17 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         }
     }
 
+    fn is_negative_reg64(
+        buf: &mut Vec<'_, u8>,
+        register_width: RegisterWidth,
+        dst: X86_64GeneralReg,
+        src: X86_64GeneralReg,
+    ) {
+        // `test` sets SF to the sign bit, without the immediate `cmp src, 0` would need.
+        test_reg_reg(buf, register_width, src, src);
+        sets_reg64(buf, dst);
+    }
+
     fn eq_freg_freg_reg64(
         buf: &mut Vec<'_, u8>,
         dst: X86_64GeneralReg,
@@ -2713,12 +3165,16 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         src2: X86_64FloatReg,
         width: FloatWidth,
     ) {
+        // An unordered comparison (either side NaN) sets ZF, PF and CF together, so ZF alone
+        // would report `NaN == NaN`. Only an ordered result (PF=0) may take the ZF answer.
+        xor_reg32_reg32(buf, dst, dst);
+
         match width {
             FloatWidth::F32 => cmp_freg32_freg32(buf, src1, src2),
             FloatWidth::F64 => cmp_freg64_freg64(buf, src1, src2),
         }
 
-        sete_reg64(buf, dst);
+        unless_unordered(buf, |buf| sete_reg8(buf, dst));
     }
 
     fn neq_freg_freg_reg64(
@@ -2728,12 +3184,15 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         src2: X86_64FloatReg,
         width: FloatWidth,
     ) {
+        // NaN is not equal to anything, so an unordered result keeps the `1` written up front.
+        mov_reg32_imm32(buf, dst, 1);
+
         match width {
             FloatWidth::F32 => cmp_freg32_freg32(buf, src1, src2),
             FloatWidth::F64 => cmp_freg64_freg64(buf, src1, src2),
         }
 
-        setne_reg64(buf, dst);
+        unless_unordered(buf, |buf| setne_reg8(buf, dst));
     }
 
     #[inline(always)]
@@ -2783,6 +3242,11 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         cvtsi2ss_freg64_reg64(buf, dst, src);
     }
 
+    #[inline(always)]
+    fn break_freg_dependency(buf: &mut Vec<'_, u8>, dst: X86_64FloatReg) {
+        xorps_freg32_freg32(buf, dst, dst);
+    }
+
     #[inline(always)]
     fn to_float_freg32_freg64(buf: &mut Vec<'_, u8>, dst: X86_64FloatReg, src: X86_64FloatReg) {
         cvtsd2ss_freg32_freg64(buf, dst, src);
@@ -2803,59 +3267,124 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
         ret(buf);
     }
 
-    fn set_if_overflow(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg) {
-        seto_reg64(buf, dst);
+    #[inline(always)]
+    fn unreachable(buf: &mut Vec<'_, u8>) {
+        ud2(buf);
     }
 
-    fn and_reg64_reg64_reg64(buf: &mut Vec<'_, u8>, dst: Reg64, src1: Reg64, src2: Reg64) {
-        binop_move_src_to_dst_reg64(buf, and_reg64_reg64, dst, src1, src2)
+    #[inline(always)]
+    fn store_fence(buf: &mut Vec<'_, u8>) {
+        sfence(buf);
     }
 
-    fn or_reg64_reg64_reg64(buf: &mut Vec<'_, u8>, dst: Reg64, src1: Reg64, src2: Reg64) {
-        binop_move_src_to_dst_reg64(buf, or_reg64_reg64, dst, src1, src2)
+    fn count_leading_zeros_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: X86_64GeneralReg,
+        src: X86_64GeneralReg,
+    ) {
+        // BSR gives the index of the highest set bit, and for an index in 0..=63, `63 - i` is `63 ^ i`.
+        bit_scan_help(buf, dst, src, |buf| {
+            bsr_reg64_reg64(buf, dst, src);
+            xor_reg64_imm8(buf, dst, 63);
+        });
     }
 
-    fn xor_reg64_reg64_reg64(buf: &mut Vec<'_, u8>, dst: Reg64, src1: Reg64, src2: Reg64) {
-        binop_move_src_to_dst_reg64(buf, xor_reg64_reg64, dst, src1, src2)
+    #[inline(always)]
+    fn count_trailing_zeros_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: X86_64GeneralReg,
+        src: X86_64GeneralReg,
+    ) {
+        bit_scan_help(buf, dst, src, |buf| bsf_reg64_reg64(buf, dst, src));
     }
 
-    fn shl_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        storage_manager: &mut StorageManager<'a, '_, X86_64GeneralReg, X86_64FloatReg, ASM, CC>,
-        dst: X86_64GeneralReg,
-        src1: X86_64GeneralReg,
-        src2: X86_64GeneralReg,
-    ) where
-        ASM: Assembler<X86_64GeneralReg, X86_64FloatReg>,
-        CC: CallConv<X86_64GeneralReg, X86_64FloatReg, ASM>,
-    {
-        shift_reg64_reg64_reg64(buf, storage_manager, shl_reg64_reg64, dst, src1, src2)
+    #[inline(always)]
+    fn set_carry_if_nonzero_reg8(
+        buf: &mut Vec<'_, u8>,
+        tmp: X86_64GeneralReg,
+        src: X86_64GeneralReg,
+    ) {
+        // Negating sets the carry flag unless the value is zero.
+        mov_reg_reg(buf, RegisterWidth::W32, tmp, src);
+        neg_reg8(buf, tmp);
     }
 
-    fn shr_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        storage_manager: &mut StorageManager<'a, '_, X86_64GeneralReg, X86_64FloatReg, ASM, CC>,
+    #[inline(always)]
+    fn add_carry_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
+        mov_reg64_reg64(buf, dst, src);
+        adc_reg64_imm8(buf, dst, 0);
+    }
+
+    #[inline(always)]
+    fn set_if_overflow(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg) {
+        seto_reg64(buf, dst);
+    }
+
+    fn and_reg64_reg64_reg64(buf: &mut Vec<'_, u8>, dst: Reg64, src1: Reg64, src2: Reg64) {
+        binop_move_src_to_dst_reg64(buf, and_reg64_reg64, dst, src1, src2)
+    }
+
+    fn or_reg64_reg64_reg64(buf: &mut Vec<'_, u8>, dst: Reg64, src1: Reg64, src2: Reg64) {
+        binop_move_src_to_dst_reg64(buf, or_reg64_reg64, dst, src1, src2)
+    }
+
+    fn xor_reg64_reg64_reg64(buf: &mut Vec<'_, u8>, dst: Reg64, src1: Reg64, src2: Reg64) {
+        binop_move_src_to_dst_reg64(buf, xor_reg64_reg64, dst, src1, src2)
+    }
+
+    fn shl_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
         dst: X86_64GeneralReg,
         src1: X86_64GeneralReg,
         src2: X86_64GeneralReg,
-    ) where
-        ASM: Assembler<X86_64GeneralReg, X86_64FloatReg>,
-        CC: CallConv<X86_64GeneralReg, X86_64FloatReg, ASM>,
-    {
-        shift_reg64_reg64_reg64(buf, storage_manager, shr_reg64_reg64, dst, src1, src2)
+    ) {
+        shift_reg64_reg64_reg64(buf, shl_reg64_reg64, dst, src1, src2)
     }
 
-    fn sar_reg64_reg64_reg64<'a, ASM, CC>(
-        buf: &mut Vec<'a, u8>,
-        storage_manager: &mut StorageManager<'a, '_, X86_64GeneralReg, X86_64FloatReg, ASM, CC>,
+    fn shr_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
         dst: X86_64GeneralReg,
         src1: X86_64GeneralReg,
         src2: X86_64GeneralReg,
-    ) where
-        ASM: Assembler<X86_64GeneralReg, X86_64FloatReg>,
-        CC: CallConv<X86_64GeneralReg, X86_64FloatReg, ASM>,
-    {
-        shift_reg64_reg64_reg64(buf, storage_manager, sar_reg64_reg64, dst, src1, src2)
+    ) {
+        shift_reg64_reg64_reg64(buf, shr_reg64_reg64, dst, src1, src2)
+    }
+
+    fn sar_reg64_reg64_reg64(
+        buf: &mut Vec<'_, u8>,
+        dst: X86_64GeneralReg,
+        src1: X86_64GeneralReg,
+        src2: X86_64GeneralReg,
+    ) {
+        shift_reg64_reg64_reg64(buf, sar_reg64_reg64, dst, src1, src2)
+    }
+
+    #[inline(always)]
+    fn shl_reg64_reg64_imm8(
+        buf: &mut Vec<'_, u8>,
+        dst: X86_64GeneralReg,
+        src: X86_64GeneralReg,
+        imm: u8,
+    ) {
+        debug_assert!(imm < 64);
+        if dst != src {
+            mov_reg64_reg64(buf, dst, src);
+        }
+        shl_reg64_imm8(buf, dst, imm);
+    }
+
+    #[inline(always)]
+    fn shr_reg64_reg64_imm8(
+        buf: &mut Vec<'_, u8>,
+        dst: X86_64GeneralReg,
+        src: X86_64GeneralReg,
+        imm: u8,
+    ) {
+        debug_assert!(imm < 64);
+        if dst != src {
+            mov_reg64_reg64(buf, dst, src);
+        }
+        shr_reg64_imm8(buf, dst, imm);
     }
 
     fn sqrt_freg64_freg64(buf: &mut Vec<'_, u8>, dst: X86_64FloatReg, src: X86_64FloatReg) {
@@ -2867,42 +3396,26 @@ impl Assembler<X86_64GeneralReg, X86_64FloatReg> for X86_64Assembler {
     }
 }
 
-fn shift_reg64_reg64_reg64<'a, ASM, CC>(
-    buf: &mut Vec<'a, u8>,
-    storage_manager: &mut StorageManager<'a, '_, X86_64GeneralReg, X86_64FloatReg, ASM, CC>,
+/// The count of a shift by a register is always in CL, so the allocator puts `src2` in RCX
+/// (see `Assembler::SHIFT_COUNT_REG`) and keeps every other operand out of it.
+fn shift_reg64_reg64_reg64(
+    buf: &mut Vec<'_, u8>,
     shift_function: fn(buf: &mut Vec<'_, u8>, X86_64GeneralReg),
     dst: X86_64GeneralReg,
     src1: X86_64GeneralReg,
     src2: X86_64GeneralReg,
-) where
-    ASM: Assembler<X86_64GeneralReg, X86_64FloatReg>,
-    CC: CallConv<X86_64GeneralReg, X86_64FloatReg, ASM>,
-{
-    macro_rules! helper {
-        ($buf:expr, $dst:expr, $src1:expr, $src2:expr) => {{
-            mov_reg64_reg64($buf, $dst, $src1);
-            mov_reg64_reg64($buf, X86_64GeneralReg::RCX, $src2);
-
-            shift_function($buf, $dst)
-        }};
-    }
-
-    // if RCX is one of our input registers, we need to move some stuff around
-    if let X86_64GeneralReg::RCX = dst {
-        storage_manager.with_tmp_general_reg(buf, |_, buf, tmp| {
-            helper!(buf, tmp, src1, src2);
-
-            mov_reg64_reg64(buf, dst, tmp);
-        })
-    } else if let X86_64GeneralReg::RCX = src2 {
-        storage_manager.with_tmp_general_reg(buf, |_, buf, tmp| {
-            mov_reg64_reg64(buf, tmp, src2);
+) {
+    debug_assert_eq!(
+        src2,
+        X86_64GeneralReg::RCX,
+        "the shift count must be in RCX"
+    );
+    debug_assert_ne!(dst, X86_64GeneralReg::RCX, "RCX holds the shift count");
 
-            helper!(buf, dst, src1, tmp);
-        })
-    } else {
-        helper!(buf, dst, src1, src2)
+    if dst != src1 {
+        mov_reg64_reg64(buf, dst, src1);
     }
+    shift_function(buf, dst)
 }
 
 impl X86_64Assembler {
@@ -2921,13 +3434,15 @@ const GRP_4: u8 = 0x66;
 
 const REX: u8 = 0x40;
 
+/// Makes the following read-modify-write instruction atomic
+const LOCK: u8 = 0xF0;
+
 // see https://wiki.osdev.org/X86-64_Instruction_Encoding#Encoding
 /// If set, 64-bit operand size is used
 const REX_PREFIX_W: u8 = 0b1000;
 /// Extension to the MODRM.reg
 /// Permits access to additional registers
 const REX_PREFIX_R: u8 = 0b0100;
-#[allow(unused)]
 /// Extension to the SIB.index field
 const REX_PREFIX_X: u8 = 0b0010;
 /// Extension to the MODRM.rm
@@ -3103,6 +3618,12 @@ fn xor_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64Gene
     binop_reg64_reg64(0x33, buf, src, dst);
 }
 
+/// `XOR r/m32,r32` -> Bitwise logical exclusive or r32 to r/m32.
+#[inline(always)]
+fn xor_reg32_reg32(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
+    binop_reg32_reg32(0x31, buf, dst, src);
+}
+
 /// `SHL r/m64, CL` -> Multiply r/m64 by 2, CL times.
 #[inline(always)]
 fn shl_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg) {
@@ -3113,6 +3634,15 @@ fn shl_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg) {
     buf.extend([rex, 0xD3, 0xC0 | (4 << 3) | dst_mod]);
 }
 
+/// `SHL r/m64, imm8` -> Multiply r/m64 by 2, imm8 times.
+#[inline(always)]
+fn shl_reg64_imm8(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: u8) {
+    let rex = add_rm_extension(dst, REX_W);
+
+    let dst_mod = dst as u8 % 8;
+    buf.extend([rex, 0xC1, 0xC0 | (4 << 3) | dst_mod, imm]);
+}
+
 /// `SHR r/m64, CL` -> Unsigned divide r/m64 by 2, CL times.
 #[inline(always)]
 fn shr_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg) {
@@ -3123,6 +3653,15 @@ fn shr_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg) {
     buf.extend([rex, 0xD3, 0xC0 | (5 << 3) | dst_mod]);
 }
 
+/// `SHR r/m64, imm8` -> Unsigned divide r/m64 by 2, imm8 times.
+#[inline(always)]
+fn shr_reg64_imm8(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: u8) {
+    let rex = add_rm_extension(dst, REX_W);
+
+    let dst_mod = dst as u8 % 8;
+    buf.extend([rex, 0xC1, 0xC0 | (5 << 3) | dst_mod, imm]);
+}
+
 /// `SAR r/m64, CL` -> Signed divide r/m64 by 2, CL times.
 #[inline(always)]
 fn sar_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg) {
@@ -3254,6 +3793,22 @@ fn and_reg64_imm8(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: i8) {
     buf.extend([rex, 0x83, 0xE0 | dst_mod, imm as u8]);
 }
 
+/// `ADC r/m64, imm8` -> Add imm8 (sign-extended) and the carry flag to r/m64.
+#[inline(always)]
+fn adc_reg64_imm8(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: i8) {
+    let rex = add_rm_extension(dst, REX_W);
+    let dst_mod = dst as u8 % 8;
+    buf.extend([rex, 0x83, 0xD0 | dst_mod, imm as u8]);
+}
+
+/// r/m64 XOR imm8 (sign-extended).
+#[inline(always)]
+fn xor_reg64_imm8(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: i8) {
+    let rex = add_rm_extension(dst, REX_W);
+    let dst_mod = dst as u8 % 8;
+    buf.extend([rex, 0x83, 0xF0 | dst_mod, imm as u8]);
+}
+
 /// `CMOVL r64,r/m64` -> Move if less (SF≠ OF).
 #[inline(always)]
 fn cmovl_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
@@ -3418,12 +3973,67 @@ fn xorps_freg32_freg32(buf: &mut Vec<'_, u8>, dst: X86_64FloatReg, src: X86_64Fl
 }
 
 /// `TEST r/m64,r64` -> AND r64 with r/m64; set SF, ZF, PF according to result.
-#[allow(dead_code)]
 #[inline(always)]
 fn test_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
     binop_reg64_reg64(0x85, buf, dst, src);
 }
 
+/// `TEST r/m,r` -> AND r with r/m of `register_width`; set SF, ZF, PF according to result.
+#[inline(always)]
+fn test_reg_reg(
+    buf: &mut Vec<'_, u8>,
+    register_width: RegisterWidth,
+    dst: X86_64GeneralReg,
+    src: X86_64GeneralReg,
+) {
+    match register_width {
+        RegisterWidth::W8 => binop_reg64_reg64(0x84, buf, dst, src),
+        RegisterWidth::W16 => binop_reg16_reg16(0x85, buf, dst, src),
+        RegisterWidth::W32 => binop_reg32_reg32(0x85, buf, dst, src),
+        RegisterWidth::W64 => binop_reg64_reg64(0x85, buf, dst, src),
+    }
+}
+
+/// `BSF r64,r/m64` -> Bit scan forward on r/m64. r64 is undefined if r/m64 is zero.
+#[inline(always)]
+fn bsf_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
+    // Like IMUL, the destination is the reg operand.
+    extended_binop_reg64_reg64(0x0F, 0xBC, buf, src, dst);
+}
+
+/// `BSR r64,r/m64` -> Bit scan reverse on r/m64. r64 is undefined if r/m64 is zero.
+#[inline(always)]
+fn bsr_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
+    // Like IMUL, the destination is the reg operand.
+    extended_binop_reg64_reg64(0x0F, 0xBD, buf, src, dst);
+}
+
+/// Emits the `BSR`/`BSF` based `scan` behind a zero check, since those leave `dst` undefined
+/// for a zero input. A zero input counts all 64 bits, like `LZCNT`/`TZCNT` would,
+/// but those are not part of baseline x86-64.
+#[inline(always)]
+fn bit_scan_help(
+    buf: &mut Vec<'_, u8>,
+    dst: X86_64GeneralReg,
+    src: X86_64GeneralReg,
+    scan: impl FnOnce(&mut Vec<'_, u8>),
+) {
+    test_reg64_reg64(buf, src, src);
+    jne_imm8(buf, 0);
+    let zero_start = buf.len();
+
+    mov_reg64_imm32(buf, dst, 64);
+    jmp_imm8(buf, 0);
+    let scan_start = buf.len();
+
+    scan(buf);
+
+    let end = buf.len();
+    debug_assert!(end - scan_start <= i8::MAX as usize);
+    buf[zero_start - 1] = (scan_start - zero_start) as u8;
+    buf[scan_start - 1] = (end - scan_start) as u8;
+}
+
 /// `IMUL r64,r/m64` -> Signed Multiply r/m64 to r64.
 #[inline(always)]
 fn imul_reg64_reg64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, src: X86_64GeneralReg) {
@@ -3486,6 +4096,24 @@ fn udiv_reg64_reg64(buf: &mut Vec<'_, u8>, src: X86_64GeneralReg) {
     buf.extend([rex, 0xF7, 0b1111_0000 | (src as u8 % 8)]);
 }
 
+/// Jump short, relative, RIP = RIP + 8-bit displacement sign extended to 64-bits.
+#[inline(always)]
+fn jmp_imm8(buf: &mut Vec<'_, u8>, imm: i8) {
+    buf.extend([0xEB, imm as u8]);
+}
+
+/// Jump short if parity (PF=1).
+#[inline(always)]
+fn jp_imm8(buf: &mut Vec<'_, u8>, imm: i8) {
+    buf.extend([0x7A, imm as u8]);
+}
+
+/// Jump short if not equal (ZF=0).
+#[inline(always)]
+fn jne_imm8(buf: &mut Vec<'_, u8>, imm: i8) {
+    buf.extend([0x75, imm as u8]);
+}
+
 /// Jump near, relative, RIP = RIP + 32-bit displacement sign extended to 64-bits.
 #[inline(always)]
 fn jmp_imm32(buf: &mut Vec<'_, u8>, imm: i32) {
@@ -3509,6 +4137,33 @@ fn jmp_reg64_offset8(buf: &mut Vec<'_, u8>, base: X86_64GeneralReg, offset: i8)
     buf.extend(offset.to_le_bytes())
 }
 
+/// Jump near if overflow (OF=1).
+#[inline(always)]
+fn jo_imm32(buf: &mut Vec<'_, u8>, imm: i32) {
+    buf.reserve(6);
+    buf.push(0x0F);
+    buf.push(0x80);
+    buf.extend(imm.to_le_bytes());
+}
+
+/// Jump near if not overflow (OF=0).
+#[inline(always)]
+fn jno_imm32(buf: &mut Vec<'_, u8>, imm: i32) {
+    buf.reserve(6);
+    buf.push(0x0F);
+    buf.push(0x81);
+    buf.extend(imm.to_le_bytes());
+}
+
+/// Jump near if equal (ZF=1).
+#[inline(always)]
+fn je_imm32(buf: &mut Vec<'_, u8>, imm: i32) {
+    buf.reserve(6);
+    buf.push(0x0F);
+    buf.push(0x84);
+    buf.extend(imm.to_le_bytes());
+}
+
 /// Jump near if not equal (ZF=0).
 #[inline(always)]
 fn jne_imm32(buf: &mut Vec<'_, u8>, imm: i32) {
@@ -3528,6 +4183,18 @@ fn mov_reg64_imm32(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: i32) {
     buf.extend(imm.to_le_bytes());
 }
 
+/// `MOV r32, imm32` -> Move imm32 to r32. Unlike `XOR`, this leaves the flags untouched.
+#[inline(always)]
+fn mov_reg32_imm32(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: i32) {
+    let dst_mod = dst as u8 % 8;
+    buf.reserve(6);
+    if dst as u8 > 7 {
+        buf.push(add_opcode_extension(dst, REX));
+    }
+    buf.push(0xB8 | dst_mod);
+    buf.extend(imm.to_le_bytes());
+}
+
 /// `MOV r64, imm64` -> Move imm64 to r64.
 #[inline(always)]
 fn mov_reg64_imm64(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: i64) {
@@ -3587,6 +4254,103 @@ fn lea_reg64_offset8(
     buf.push(offset as u8);
 }
 
+/// `LEA r64, [base + index * scale]` -> Store `base + index * scale` in register r64.
+#[inline(always)]
+#[allow(clippy::unusual_byte_groupings)]
+fn lea_reg64_base_index_scale(
+    buf: &mut Vec<'_, u8>,
+    dst: X86_64GeneralReg,
+    base: X86_64GeneralReg,
+    index: X86_64GeneralReg,
+    scale: u8,
+) {
+    // An index of RSP in the SIB byte means "no index".
+    debug_assert_ne!(index, X86_64GeneralReg::RSP);
+
+    let scale_bits = match scale {
+        1 => 0b00,
+        2 => 0b01,
+        4 => 0b10,
+        8 => 0b11,
+        _ => internal_error!("lea can only scale by 1, 2, 4, or 8, not {scale}"),
+    };
+
+    let rex = add_rm_extension(base, REX_W);
+    let rex = add_reg_extension(dst, rex);
+    let rex = if index as u8 > 7 {
+        rex | REX_PREFIX_X
+    } else {
+        rex
+    };
+
+    let dst_mod = dst as u8 % 8;
+    let base_mod = base as u8 % 8;
+    let index_mod = index as u8 % 8;
+    let sib = (scale_bits << 6) | (index_mod << 3) | base_mod;
+
+    // A base of RBP or R13 without a displacement means "no base",
+    // so those use an explicit 8-bit displacement of 0 instead.
+    if base_mod == X86_64GeneralReg::RBP as u8 {
+        buf.extend([rex, 0x8d, 0b01_000_100 | (dst_mod << 3), sib, 0x00]);
+    } else {
+        buf.extend([rex, 0x8d, 0b00_000_100 | (dst_mod << 3), sib]);
+    }
+}
+
+// helper function for `lock`-prefixed read-modify-write instructions on a base + offset
+#[inline(always)]
+fn lock_base64_offset32_reg64_help(
+    op_code: u8,
+    buf: &mut Vec<'_, u8>,
+    base: X86_64GeneralReg,
+    offset: i32,
+    src: X86_64GeneralReg,
+) {
+    let rex = add_rm_extension(base, REX_W);
+    let rex = add_reg_extension(src, rex);
+    let src_mod = (src as u8 % 8) << 3;
+    buf.reserve(10);
+    buf.extend([LOCK, rex, 0x0F, op_code]);
+    base_offset_operand(buf, src_mod, base, offset);
+}
+
+/// `LOCK CMPXCHG r/m64,r64` -> Atomically compare RAX with r/m64. If equal, r64 is stored into r/m64. Else, load r/m64 into RAX.
+#[allow(dead_code)]
+#[inline(always)]
+fn lock_cmpxchg_base64_offset32_reg64(
+    buf: &mut Vec<'_, u8>,
+    base: X86_64GeneralReg,
+    offset: i32,
+    src: X86_64GeneralReg,
+) {
+    lock_base64_offset32_reg64_help(0xB1, buf, base, offset, src)
+}
+
+/// `LOCK XADD r/m64,r64` -> Atomically exchange r64 and r/m64; load sum into r/m64.
+#[allow(dead_code)]
+#[inline(always)]
+fn lock_xadd_base64_offset32_reg64(
+    buf: &mut Vec<'_, u8>,
+    base: X86_64GeneralReg,
+    offset: i32,
+    src: X86_64GeneralReg,
+) {
+    lock_base64_offset32_reg64_help(0xC1, buf, base, offset, src)
+}
+
+/// `MFENCE` -> Serializes all load and store operations that occurred prior to the MFENCE instruction.
+#[allow(dead_code)]
+#[inline(always)]
+fn mfence(buf: &mut Vec<'_, u8>) {
+    buf.extend([0x0F, 0xAE, 0xF0]);
+}
+
+/// `SFENCE` -> Serializes all store operations that occurred prior to the SFENCE instruction.
+#[inline(always)]
+fn sfence(buf: &mut Vec<'_, u8>) {
+    buf.extend([0x0F, 0xAE, 0xF8]);
+}
+
 fn raw_mov_reg_reg(
     buf: &mut Vec<'_, u8>,
     register_width: RegisterWidth,
@@ -3681,7 +4445,30 @@ fn mov_reg_reg(
     }
 }
 
-// The following base and stack based operations could be optimized based on how many bytes the offset actually is.
+/// Emits the ModRM byte, a SIB byte if `base` needs one, and the displacement for the memory
+/// operand `[base + offset]`. `reg_field` is the other operand, already shifted into place.
+/// Offsets that fit in a signed byte use the short disp8 form, all others a disp32.
+#[inline(always)]
+fn base_offset_operand(buf: &mut Vec<'_, u8>, reg_field: u8, base: X86_64GeneralReg, offset: i32) {
+    let base_mod = base as u8 % 8;
+    let disp8 = i8::try_from(offset).ok();
+
+    #[allow(clippy::unusual_byte_groupings)]
+    match disp8 {
+        Some(_) => buf.push(0b01_000_000 | reg_field | base_mod),
+        None => buf.push(0b10_000_000 | reg_field | base_mod),
+    }
+
+    // Using RSP or R12 requires a secondary index byte.
+    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
+        buf.push(0x24);
+    }
+
+    match disp8 {
+        Some(offset) => buf.push(offset as u8),
+        None => buf.extend(offset.to_le_bytes()),
+    }
+}
 
 #[inline(always)]
 fn mov_base_offset32_reg(
@@ -3710,35 +4497,41 @@ fn mov_base64_offset32_reg64(
     let rex = add_rm_extension(base, REX_W);
     let rex = add_reg_extension(src, rex);
     let src_mod = (src as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(8);
-    buf.extend([rex, 0x89, 0x80 | src_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    buf.extend([rex, 0x89]);
+    base_offset_operand(buf, src_mod, base, offset);
 }
 
-/// `MOV r/m32,r32` -> Move r32 to r/m32, where m32 references a base + offset.
+/// `MOVNTI m64,r64` -> Move r64 to m64 using a non-temporal hint, where m64 references a base + offset.
 #[inline(always)]
-fn mov_base32_offset32_reg32(
+fn movnti_base64_offset32_reg64(
     buf: &mut Vec<'_, u8>,
     base: X86_64GeneralReg,
     offset: i32,
     src: X86_64GeneralReg,
 ) {
-    let rex = add_rm_extension(base, REX);
+    let rex = add_rm_extension(base, REX_W);
     let rex = add_reg_extension(src, rex);
     let src_mod = (src as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
-    buf.reserve(8);
-    buf.extend([rex, 0x89, 0x80 | src_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    buf.reserve(9);
+    buf.extend([rex, 0x0F, 0xC3]);
+    base_offset_operand(buf, src_mod, base, offset);
+}
+
+/// `MOV r/m32,r32` -> Move r32 to r/m32, where m32 references a base + offset.
+#[inline(always)]
+fn mov_base32_offset32_reg32(
+    buf: &mut Vec<'_, u8>,
+    base: X86_64GeneralReg,
+    offset: i32,
+    src: X86_64GeneralReg,
+) {
+    let rex = add_rm_extension(base, REX);
+    let rex = add_reg_extension(src, rex);
+    let src_mod = (src as u8 % 8) << 3;
+    buf.reserve(8);
+    buf.extend([rex, 0x89]);
+    base_offset_operand(buf, src_mod, base, offset);
 }
 
 /// `MOV r/m16,r16` -> Move r16 to r/m16, where m16 references a base + offset.
@@ -3752,14 +4545,9 @@ fn mov_base16_offset32_reg16(
     let rex = add_rm_extension(base, REX);
     let rex = add_reg_extension(src, rex);
     let src_mod = (src as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(8);
-    buf.extend([GRP_4, rex, 0x89, 0x80 | src_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    buf.extend([GRP_4, rex, 0x89]);
+    base_offset_operand(buf, src_mod, base, offset);
 }
 
 /// `MOV r/m8,r8` -> Move r8 to r/m8, where m8 references a base + offset.
@@ -3773,14 +4561,9 @@ fn mov_base8_offset32_reg8(
     let rex = add_rm_extension(base, REX);
     let rex = add_reg_extension(src, rex);
     let src_mod = (src as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(8);
-    buf.extend([rex, 0x88, 0x80 | src_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    buf.extend([rex, 0x88]);
+    base_offset_operand(buf, src_mod, base, offset);
 }
 
 #[inline(always)]
@@ -3802,8 +4585,6 @@ fn mov_reg_base_offset32(
     let rex = add_reg_extension(dst, rex);
 
     let dst_mod = (dst as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
-    let operands = 0x80 | dst_mod | base_mod;
 
     buf.reserve(8);
 
@@ -3813,15 +4594,10 @@ fn mov_reg_base_offset32(
     };
 
     match register_width {
-        W16 => buf.extend([GRP_4, rex, instruction, operands]),
-        _ => buf.extend([rex, instruction, operands]),
+        W16 => buf.extend([GRP_4, rex, instruction]),
+        _ => buf.extend([rex, instruction]),
     };
-
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    base_offset_operand(buf, dst_mod, base, offset);
 }
 
 /// `MOV r64,r/m64` -> Move r/m64 to r64, where m64 references a base + offset.
@@ -3835,6 +4611,18 @@ fn mov_reg64_base64_offset32(
     mov_reg_base_offset32(buf, RegisterWidth::W64, dst, base, offset)
 }
 
+/// `MOV r64,r/m64` -> Move r/m64 to r64, where m64 is an absolute offset into the FS segment.
+#[inline(always)]
+fn mov_reg64_fs_offset32(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, offset: i32) {
+    let rex = add_reg_extension(dst, REX_W);
+    let dst_mod = (dst as u8 % 8) << 3;
+
+    buf.reserve(9);
+    // FS segment override, then a SIB byte with no base and no index for a bare disp32.
+    buf.extend([0x64, rex, 0x8B, dst_mod | 0b100, 0x25]);
+    buf.extend(offset.to_le_bytes());
+}
+
 /// `MOV r/m32,r32` -> Move r32 to r/m32.
 #[inline(always)]
 fn mov_reg32_base32_offset32(
@@ -3879,19 +4667,12 @@ fn movsx_reg64_base_offset32(
     let rex = add_rm_extension(base, REX_W);
     let rex = add_reg_extension(dst, rex);
     let dst_mod = (dst as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(9);
 
     // our output is a 64-bit value, so rex is always needed
     buf.push(rex);
     buf.extend(opcode);
-    buf.push(0x80 | dst_mod | base_mod);
-
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    base_offset_operand(buf, dst_mod, base, offset);
 }
 
 /// `MOVSX r64,r/m32` -> Move r/m32 with sign extention to r64, where m32 references a base + offset.
@@ -3938,14 +4719,9 @@ fn movzx_reg64_base_offset32(
     let rex = add_rm_extension(base, REX_W);
     let rex = add_reg_extension(dst, rex);
     let dst_mod = (dst as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(9);
-    buf.extend([rex, 0x0F, opcode, 0x80 | dst_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    buf.extend([rex, 0x0F, opcode]);
+    base_offset_operand(buf, dst_mod, base, offset);
 }
 
 /// `MOVZX r64,r/m8` -> Move r/m8 with zero extention to r64, where m8 references a base + offset.
@@ -4096,18 +4872,13 @@ fn movsd_base64_offset32_freg64(
     let rex = add_rm_extension(base, REX_W);
     let rex = add_reg_extension(src, rex);
     let src_mod = (src as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(10);
     buf.push(0xF2);
     if src as u8 > 7 || base as u8 > 7 {
         buf.push(rex);
     }
-    buf.extend([0x0F, 0x11, 0x80 | src_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    buf.extend([0x0F, 0x11]);
+    base_offset_operand(buf, src_mod, base, offset);
 }
 
 // `MOVSS r/m64,xmm1` -> Move xmm1 to r/m64. where m64 references the base pointer.
@@ -4121,18 +4892,125 @@ fn movss_base32_offset32_freg32(
     let rex = add_rm_extension(base, REX_W);
     let rex = add_reg_extension(src, rex);
     let src_mod = (src as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(10);
     buf.push(0xF3);
     if src as u8 > 7 || base as u8 > 7 {
         buf.push(rex);
     }
-    buf.extend([0x0F, 0x11, 0x80 | src_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
+    buf.extend([0x0F, 0x11]);
+    base_offset_operand(buf, src_mod, base, offset);
+}
+
+/// Emits a full-width SSE move between `reg` and `[base + offset]` with the given opcode.
+#[inline(always)]
+fn sse_move_freg128_base64_offset32(
+    buf: &mut Vec<'_, u8>,
+    opcode: u8,
+    reg: X86_64FloatReg,
+    base: X86_64GeneralReg,
+    offset: i32,
+) {
+    let rex = add_rm_extension(base, REX);
+    let rex = add_reg_extension(reg, rex);
+    let reg_mod = (reg as u8 % 8) << 3;
+    buf.reserve(9);
+    if reg as u8 > 7 || base as u8 > 7 {
+        buf.push(rex);
     }
-    buf.extend(offset.to_le_bytes());
+    buf.extend([0x0F, opcode]);
+    base_offset_operand(buf, reg_mod, base, offset);
+}
+
+/// `MOVAPS m128,xmm1` -> Move xmm1 to m128. Faults unless m128 is 16-byte aligned.
+#[inline(always)]
+fn movaps_base64_offset32_freg128(
+    buf: &mut Vec<'_, u8>,
+    base: X86_64GeneralReg,
+    offset: i32,
+    src: X86_64FloatReg,
+) {
+    sse_move_freg128_base64_offset32(buf, 0x29, src, base, offset)
+}
+
+/// `MOVUPS m128,xmm1` -> Move xmm1 to m128, which may be unaligned.
+#[inline(always)]
+fn movups_base64_offset32_freg128(
+    buf: &mut Vec<'_, u8>,
+    base: X86_64GeneralReg,
+    offset: i32,
+    src: X86_64FloatReg,
+) {
+    sse_move_freg128_base64_offset32(buf, 0x11, src, base, offset)
+}
+
+/// `MOVAPS xmm1,m128` -> Move m128 to xmm1. Faults unless m128 is 16-byte aligned.
+#[inline(always)]
+fn movaps_freg128_base64_offset32(
+    buf: &mut Vec<'_, u8>,
+    dst: X86_64FloatReg,
+    base: X86_64GeneralReg,
+    offset: i32,
+) {
+    sse_move_freg128_base64_offset32(buf, 0x28, dst, base, offset)
+}
+
+/// `MOVUPS xmm1,m128` -> Move m128 to xmm1, which may be unaligned.
+#[inline(always)]
+fn movups_freg128_base64_offset32(
+    buf: &mut Vec<'_, u8>,
+    dst: X86_64FloatReg,
+    base: X86_64GeneralReg,
+    offset: i32,
+) {
+    sse_move_freg128_base64_offset32(buf, 0x10, dst, base, offset)
+}
+
+/// `MOVDQA m128,xmm1` -> Move the integer data in xmm1 to m128. Faults unless m128 is 16-byte aligned.
+#[inline(always)]
+fn movdqa_base64_offset32_freg128(
+    buf: &mut Vec<'_, u8>,
+    base: X86_64GeneralReg,
+    offset: i32,
+    src: X86_64FloatReg,
+) {
+    buf.push(0x66);
+    sse_move_freg128_base64_offset32(buf, 0x7F, src, base, offset)
+}
+
+/// `MOVDQU m128,xmm1` -> Move the integer data in xmm1 to m128, which may be unaligned.
+#[inline(always)]
+fn movdqu_base64_offset32_freg128(
+    buf: &mut Vec<'_, u8>,
+    base: X86_64GeneralReg,
+    offset: i32,
+    src: X86_64FloatReg,
+) {
+    buf.push(0xF3);
+    sse_move_freg128_base64_offset32(buf, 0x7F, src, base, offset)
+}
+
+/// `MOVDQA xmm1,m128` -> Move the integer data in m128 to xmm1. Faults unless m128 is 16-byte aligned.
+#[inline(always)]
+fn movdqa_freg128_base64_offset32(
+    buf: &mut Vec<'_, u8>,
+    dst: X86_64FloatReg,
+    base: X86_64GeneralReg,
+    offset: i32,
+) {
+    buf.push(0x66);
+    sse_move_freg128_base64_offset32(buf, 0x6F, dst, base, offset)
+}
+
+/// `MOVDQU xmm1,m128` -> Move the integer data in m128 to xmm1, which may be unaligned.
+#[inline(always)]
+fn movdqu_freg128_base64_offset32(
+    buf: &mut Vec<'_, u8>,
+    dst: X86_64FloatReg,
+    base: X86_64GeneralReg,
+    offset: i32,
+) {
+    buf.push(0xF3);
+    sse_move_freg128_base64_offset32(buf, 0x6F, dst, base, offset)
 }
 
 /// `MOVSD xmm1,r/m64` -> Move r/m64 to xmm1. where m64 references the base pointer.
@@ -4146,18 +5024,13 @@ fn movsd_freg64_base64_offset32(
     let rex = add_rm_extension(base, REX_W);
     let rex = add_reg_extension(dst, rex);
     let dst_mod = (dst as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(10);
     buf.push(0xF2);
     if dst as u8 > 7 || base as u8 > 7 {
         buf.push(rex);
     }
-    buf.extend([0x0F, 0x10, 0x80 | dst_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    buf.extend([0x0F, 0x10]);
+    base_offset_operand(buf, dst_mod, base, offset);
 }
 
 /// `MOVSS xmm1,r/m32` -> Move r/m32 to xmm1. where m64 references the base pointer.
@@ -4171,18 +5044,13 @@ fn movss_freg32_base32_offset32(
     let rex = add_rm_extension(base, REX_W);
     let rex = add_reg_extension(dst, rex);
     let dst_mod = (dst as u8 % 8) << 3;
-    let base_mod = base as u8 % 8;
     buf.reserve(10);
     buf.push(0xF3);
     if dst as u8 > 7 || base as u8 > 7 {
         buf.push(rex);
     }
-    buf.extend([0x0F, 0x10, 0x80 | dst_mod | base_mod]);
-    // Using RSP or R12 requires a secondary index byte.
-    if base == X86_64GeneralReg::RSP || base == X86_64GeneralReg::R12 {
-        buf.push(0x24);
-    }
-    buf.extend(offset.to_le_bytes());
+    buf.extend([0x0F, 0x10]);
+    base_offset_operand(buf, dst_mod, base, offset);
 }
 
 /// `NEG r/m64` -> Two's complement negate r/m64.
@@ -4193,12 +5061,52 @@ fn neg_reg64(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
     buf.extend([rex, 0xF7, 0xD8 | reg_mod]);
 }
 
+/// `NEG r/m8` -> Two's complement negate r/m8.
+#[inline(always)]
+fn neg_reg8(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
+    use X86_64GeneralReg::*;
+
+    let reg_mod = reg as u8 % 8;
+    // Without a REX prefix, SPL, BPL, SIL and DIL would be AH, CH, DH and BH.
+    if reg as u8 > 7 || matches!(reg, RSP | RBP | RSI | RDI) {
+        buf.push(add_rm_extension(reg, REX));
+    }
+    buf.extend([0xF6, 0xD8 | reg_mod]);
+}
+
+/// `INC r/m64` -> Increment r/m64 by 1. The carry flag is not affected.
+#[inline(always)]
+fn inc_reg64(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
+    let rex = add_rm_extension(reg, REX_W);
+    let reg_mod = reg as u8 % 8;
+    buf.extend([rex, 0xFF, 0xC0 | reg_mod]);
+}
+
+/// `DEC r/m64` -> Decrement r/m64 by 1. The carry flag is not affected.
+#[inline(always)]
+fn dec_reg64(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
+    let rex = add_rm_extension(reg, REX_W);
+    let reg_mod = reg as u8 % 8;
+    buf.extend([rex, 0xFF, 0xC8 | reg_mod]);
+}
+
 // helper function for `set*` instructions
 #[inline(always)]
 fn set_reg64_help(op_code: u8, buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
     // XOR needs 3 bytes, actual SETE instruction need 3 or 4 bytes
     buf.reserve(7);
 
+    set_reg8_help(op_code, buf, reg);
+
+    // We and reg with 1 because the SETE instruction only applies
+    // to the lower bits of the register
+    and_reg64_imm8(buf, reg, 1);
+}
+
+// helper function for `set*` instructions that only write the low byte.
+// The caller is responsible for the upper bits of the register.
+#[inline(always)]
+fn set_reg8_help(op_code: u8, buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
     // Actually apply the SETE instruction
     let reg_mod = reg as u8 % 8;
     use X86_64GeneralReg::*;
@@ -4209,10 +5117,20 @@ fn set_reg64_help(op_code: u8, buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
             buf.extend([REX | 1, 0x0F, op_code, 0xC0 | reg_mod])
         }
     }
+}
 
-    // We and reg with 1 because the SETE instruction only applies
-    // to the lower bits of the register
-    and_reg64_imm8(buf, reg, 1);
+/// Emits the instructions of `ordered` behind a `JP` that skips them when the preceding
+/// `UCOMISS`/`UCOMISD` was unordered (PF=1), i.e. when one of the operands was NaN.
+#[inline(always)]
+fn unless_unordered(buf: &mut Vec<'_, u8>, ordered: impl FnOnce(&mut Vec<'_, u8>)) {
+    jp_imm8(buf, 0);
+    let start = buf.len();
+
+    ordered(buf);
+
+    let skip = buf.len() - start;
+    debug_assert!(skip <= i8::MAX as usize);
+    buf[start - 1] = skip as u8;
 }
 
 #[inline(always)]
@@ -4251,6 +5169,12 @@ fn sete_reg64(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
     set_reg64_help(0x94, buf, reg);
 }
 
+/// `SETE r/m8` -> Set Byte on Condition - zero/equal (ZF=1), leaving the upper bits untouched.
+#[inline(always)]
+fn sete_reg8(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
+    set_reg8_help(0x94, buf, reg);
+}
+
 /// `CVTSS2SD xmm` -> Convert one single-precision floating-point value in xmm/m32 to one double-precision floating-point value in xmm.
 #[inline(always)]
 fn cvtss2sd_freg64_freg32(buf: &mut Vec<'_, u8>, dst: X86_64FloatReg, src: X86_64FloatReg) {
@@ -4289,6 +5213,18 @@ fn setne_reg64(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
     set_reg64_help(0x95, buf, reg);
 }
 
+/// `SETNE r/m8` -> Set byte if not equal (ZF=0), leaving the upper bits untouched.
+#[inline(always)]
+fn setne_reg8(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
+    set_reg8_help(0x95, buf, reg);
+}
+
+/// `SETS r/m64` -> Set byte if sign (SF=1).
+#[inline(always)]
+fn sets_reg64(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
+    set_reg64_help(0x98, buf, reg);
+}
+
 /// `SETL r/m64` -> Set byte if less (SF≠ OF).
 #[inline(always)]
 fn setl_reg64(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
@@ -4349,12 +5285,46 @@ fn setp_reg64(buf: &mut Vec<'_, u8>, reg: X86_64GeneralReg) {
     set_reg64_help(0x9A, buf, reg);
 }
 
+/// `SYSCALL` -> Fast call to the kernel, with the system call number in RAX.
+#[inline(always)]
+fn syscall(buf: &mut Vec<'_, u8>) {
+    buf.extend([0x0F, 0x05]);
+}
+
 /// `RET` -> Near return to calling procedure.
 #[inline(always)]
 fn ret(buf: &mut Vec<'_, u8>) {
     buf.push(0xC3);
 }
 
+/// `RET imm16` -> Near return to calling procedure and pop imm16 bytes from stack.
+#[allow(dead_code)]
+#[inline(always)]
+fn ret_near_imm16(buf: &mut Vec<'_, u8>, imm: u16) {
+    buf.push(0xC2);
+    buf.extend(imm.to_le_bytes());
+}
+
+/// The `emit_return` of a convention that sets `CALLEE_POPS_STACK_ARGS` (stdcall).
+#[allow(dead_code)]
+#[inline(always)]
+fn ret_popping_stack_args(buf: &mut Vec<'_, u8>, stack_args_size: u32) {
+    if stack_args_size == 0 {
+        ret(buf);
+    } else {
+        let imm = u16::try_from(stack_args_size).unwrap_or_else(|_| {
+            internal_error!("{stack_args_size} bytes of stack arguments can't be popped by ret")
+        });
+        ret_near_imm16(buf, imm);
+    }
+}
+
+/// `UD2` -> Raise invalid opcode exception.
+#[inline(always)]
+fn ud2(buf: &mut Vec<'_, u8>) {
+    buf.extend([0x0F, 0x0B]);
+}
+
 /// `SUB r/m64, imm32` -> Subtract imm32 sign-extended to 64-bits from r/m64.
 #[inline(always)]
 fn sub_reg64_imm32(buf: &mut Vec<'_, u8>, dst: X86_64GeneralReg, imm: i32) {
@@ -4633,6 +5603,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_xor_reg32_reg32() {
+        disassembler_test!(
+            xor_reg32_reg32,
+            |reg1: X86_64GeneralReg, reg2: X86_64GeneralReg| format!(
+                "xor {}, {}",
+                reg1.low_32bits_string(),
+                reg2.low_32bits_string()
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
     #[test]
     fn test_shl_reg64_reg64() {
         disassembler_test!(
@@ -4680,6 +5664,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_adc_reg64_imm8() {
+        disassembler_test!(
+            adc_reg64_imm8,
+            |reg, imm| format!("adc {reg}, 0x{imm:x}"),
+            ALL_GENERAL_REGS,
+            [0x3fi8]
+        );
+    }
+
+    #[test]
+    fn test_xor_reg64_imm8() {
+        disassembler_test!(
+            xor_reg64_imm8,
+            |reg, imm| format!("xor {reg}, 0x{imm:x}"),
+            ALL_GENERAL_REGS,
+            [0x3fi8]
+        );
+    }
+
+    #[test]
+    fn test_bsf_reg64_reg64() {
+        disassembler_test!(
+            bsf_reg64_reg64,
+            |reg1, reg2| format!("bsf {reg1}, {reg2}"),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
+    #[test]
+    fn test_bsr_reg64_reg64() {
+        disassembler_test!(
+            bsr_reg64_reg64,
+            |reg1, reg2| format!("bsr {reg1}, {reg2}"),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
     #[test]
     fn test_imul_reg64_reg64() {
         disassembler_test!(
@@ -4768,16 +5792,86 @@ mod tests {
     }
 
     #[test]
-    fn test_jmp_reg64_offset8() {
+    fn test_shl_reg64_imm8() {
         disassembler_test!(
-            jmp_reg64_offset8,
-            |base, offset| if offset < 0x10 {
-                format!("jmp qword ptr [{base} + {offset:x}]")
-            } else {
-                format!("jmp qword ptr [{base} + 0x{offset:x}]")
-            },
+            shl_reg64_imm8,
+            |reg, imm| format!("shl {reg}, {imm}"),
             ALL_GENERAL_REGS,
-            [0x8, 0x10]
+            [1u8, 8]
+        );
+    }
+
+    #[test]
+    fn test_shr_reg64_imm8() {
+        disassembler_test!(
+            shr_reg64_imm8,
+            |reg, imm| format!("shr {reg}, {imm}"),
+            ALL_GENERAL_REGS,
+            [1u8, 8]
+        );
+    }
+
+    #[test]
+    fn test_jmp_imm8() {
+        const INST_SIZE: i8 = 2;
+        disassembler_test!(
+            jmp_imm8,
+            |imm: i8| format!("jmp 0x{:x}", imm as i64 + INST_SIZE as i64),
+            [0x10i8]
+        );
+    }
+
+    #[test]
+    fn test_jne_imm8() {
+        const INST_SIZE: i8 = 2;
+        disassembler_test!(
+            jne_imm8,
+            |imm: i8| format!("jne 0x{:x}", imm as i64 + INST_SIZE as i64),
+            [0x10i8]
+        );
+    }
+
+    #[test]
+    fn test_jmp_reg64_offset8() {
+        disassembler_test!(
+            jmp_reg64_offset8,
+            |base, offset| if offset < 0x10 {
+                format!("jmp qword ptr [{base} + {offset:x}]")
+            } else {
+                format!("jmp qword ptr [{base} + 0x{offset:x}]")
+            },
+            ALL_GENERAL_REGS,
+            [0x8, 0x10]
+        );
+    }
+
+    #[test]
+    fn test_jo_imm32() {
+        const INST_SIZE: i32 = 6;
+        disassembler_test!(
+            jo_imm32,
+            |imm| format!("jo 0x{:x}", imm + INST_SIZE),
+            [TEST_I32]
+        );
+    }
+
+    #[test]
+    fn test_jno_imm32() {
+        const INST_SIZE: i32 = 6;
+        disassembler_test!(
+            jno_imm32,
+            |imm| format!("jno 0x{:x}", imm + INST_SIZE),
+            [TEST_I32]
+        );
+    }
+
+    #[test]
+    fn test_je_imm32() {
+        const INST_SIZE: i32 = 6;
+        disassembler_test!(
+            je_imm32,
+            |imm| format!("je 0x{:x}", imm + INST_SIZE),
+            [TEST_I32]
         );
     }
 
@@ -4801,6 +5895,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mov_reg32_imm32() {
+        disassembler_test!(
+            mov_reg32_imm32,
+            |reg: X86_64GeneralReg, imm| format!("mov {}, 0x{imm:x}", reg.low_32bits_string()),
+            ALL_GENERAL_REGS,
+            [TEST_I32]
+        );
+    }
+
     #[test]
     fn test_mov_reg64_imm64() {
         disassembler_test!(
@@ -4843,6 +5947,246 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lea_reg64_base_index_scale() {
+        // RSP cannot be used as an index.
+        let index_regs: std::vec::Vec<_> = ALL_GENERAL_REGS
+            .iter()
+            .copied()
+            .filter(|reg| *reg != X86_64GeneralReg::RSP)
+            .collect();
+
+        disassembler_test!(
+            lea_reg64_base_index_scale,
+            |dst, base, index, scale| {
+                if scale == 1 {
+                    format!("lea {dst}, [{base} + {index}]")
+                } else {
+                    format!("lea {dst}, [{base} + {index}*{scale}]")
+                }
+            },
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            index_regs,
+            [1u8, 2, 4, 8]
+        );
+    }
+
+    #[test]
+    fn test_lock_cmpxchg_base64_offset32_reg64() {
+        disassembler_test!(
+            lock_cmpxchg_base64_offset32_reg64,
+            |reg1, imm, reg2| format!("lock cmpxchg qword ptr [{reg1} + 0x{imm:x}], {reg2}"),
+            ALL_GENERAL_REGS,
+            [TEST_I32],
+            ALL_GENERAL_REGS
+        );
+    }
+
+    #[test]
+    fn test_lock_xadd_base64_offset32_reg64() {
+        disassembler_test!(
+            lock_xadd_base64_offset32_reg64,
+            |reg1, imm, reg2| format!("lock xadd qword ptr [{reg1} + 0x{imm:x}], {reg2}"),
+            ALL_GENERAL_REGS,
+            [TEST_I32],
+            ALL_GENERAL_REGS
+        );
+    }
+
+    #[test]
+    fn test_mfence() {
+        disassembler_test!(mfence, || "mfence");
+    }
+
+    #[test]
+    fn test_sfence() {
+        disassembler_test!(sfence, || "sfence");
+    }
+
+    #[test]
+    fn windows_unwind_info_describes_the_prologue() {
+        use X86_64FloatReg::XMM6;
+        use X86_64GeneralReg::{RBX, RSI};
+
+        let arena = bumpalo::Bump::new();
+        let mut prologue = bumpalo::vec![in &arena];
+        let aligned_stack_size =
+            X86_64WindowsFastcall::setup_stack(&mut prologue, &[RBX, RSI], &[XMM6], 8, 32, true);
+        assert_eq!(aligned_stack_size, 80);
+
+        let info =
+            X86_64WindowsFastcall::unwind_info(&prologue, 0, &[RBX, RSI], &[XMM6], 80, 32).unwrap();
+
+        #[rustfmt::skip]
+        let expected = [
+            // version 1, prologue size, 8 slots of codes, no frame register
+            0x01, prologue.len() as u8, 8, 0x00,
+            // movaps [rbp - 0x20], xmm6 ends at 23: SAVE_XMM128 xmm6 at rsp + 3 * 16
+            23, 0x68, 3, 0,
+            // mov [rbp - 0x28], rsi ends at 19: SAVE_NONVOL rsi at rsp + 5 * 8
+            19, 0x64, 5, 0,
+            // mov [rbp - 0x30], rbx ends at 15: SAVE_NONVOL rbx at rsp + 4 * 8
+            15, 0x34, 4, 0,
+            // sub rsp, 0x50 ends at 11: ALLOC_SMALL of (9 + 1) * 8
+            11, 0x92,
+            // push rbp ends at 1: PUSH_NONVOL rbp
+            1, 0x50,
+        ];
+        assert_eq!(prologue.len(), 23);
+        assert_eq!(info, expected);
+    }
+
+    #[test]
+    fn stack_offset_beyond_a_byte_uses_a_disp32() {
+        use X86_64GeneralReg::RDI;
+
+        let arena = bumpalo::Bump::new();
+        let mut buf = bumpalo::vec![in &arena];
+
+        X86_64Assembler::mov_base32_reg64(&mut buf, -0x80, RDI);
+        X86_64Assembler::mov_base32_reg64(&mut buf, -0x88, RDI);
+        X86_64Assembler::mov_reg64_base32(&mut buf, RDI, -0x80);
+        X86_64Assembler::mov_reg64_base32(&mut buf, RDI, -0x88);
+
+        #[rustfmt::skip]
+        let expected = [
+            // mov qword ptr [rbp - 0x80], rdi
+            0x48, 0x89, 0x7d, 0x80,
+            // mov qword ptr [rbp - 0x88], rdi
+            0x48, 0x89, 0xbd, 0x78, 0xff, 0xff, 0xff,
+            // mov rdi, qword ptr [rbp - 0x80]
+            0x48, 0x8b, 0x7d, 0x80,
+            // mov rdi, qword ptr [rbp - 0x88]
+            0x48, 0x8b, 0xbd, 0x78, 0xff, 0xff, 0xff,
+        ];
+        assert_eq!(&buf[..], expected);
+    }
+
+    #[test]
+    fn system_v_prologue_and_epilogue() {
+        use X86_64GeneralReg::RBX;
+
+        let arena = bumpalo::Bump::new();
+        let mut buf = bumpalo::vec![in &arena];
+
+        let aligned_stack_size = X86_64SystemV::emit_prologue(&mut buf, &[RBX], &[], 8, 0, true);
+        assert_eq!(aligned_stack_size, 16);
+        X86_64SystemV::emit_epilogue(&mut buf, &[RBX], &[], aligned_stack_size, 0, true, 0);
+
+        #[rustfmt::skip]
+        let expected = [
+            // push rbp
+            0x55,
+            // mov rbp, rsp
+            0x48, 0x89, 0xe5,
+            // sub rsp, 0x10
+            0x48, 0x81, 0xec, 0x10, 0x00, 0x00, 0x00,
+            // mov qword ptr [rbp - 0x10], rbx
+            0x48, 0x89, 0x5d, 0xf0,
+            // mov rbx, qword ptr [rbp - 0x10]
+            0x48, 0x8b, 0x5d, 0xf0,
+            // add rsp, 0x10
+            0x48, 0x81, 0xc4, 0x10, 0x00, 0x00, 0x00,
+            // pop rbp
+            0x5d,
+            // ret
+            0xc3,
+        ];
+        assert_eq!(&buf[..], expected);
+    }
+
+    #[test]
+    fn windows_frame_reserves_the_shadow_space() {
+        let arena = bumpalo::Bump::new();
+        let mut buf = bumpalo::vec![in &arena];
+        let shadow_space = X86_64WindowsFastcall::SHADOW_SPACE_SIZE as i32;
+
+        // Calls don't move the stack pointer, so the frame holds the shadow space for all of them.
+        let aligned_stack_size =
+            X86_64WindowsFastcall::setup_stack(&mut buf, &[], &[], 0, shadow_space, true);
+        X86_64WindowsFastcall::cleanup_stack(
+            &mut buf,
+            &[],
+            &[],
+            aligned_stack_size,
+            shadow_space,
+            true,
+        );
+
+        #[rustfmt::skip]
+        let expected = [
+            // push rbp
+            0x55,
+            // mov rbp, rsp
+            0x48, 0x89, 0xe5,
+            // sub rsp, 0x20
+            0x48, 0x81, 0xec, 0x20, 0x00, 0x00, 0x00,
+            // add rsp, 0x20
+            0x48, 0x81, 0xc4, 0x20, 0x00, 0x00, 0x00,
+            // pop rbp
+            0x5d,
+        ];
+        assert_eq!(&buf[..], expected);
+    }
+
+    #[test]
+    fn windows_unwind_info_counts_from_the_start_of_the_proc() {
+        use X86_64GeneralReg::RBX;
+
+        let arena = bumpalo::Bump::new();
+        let mut prologue = bumpalo::vec![in &arena];
+        let mut relocs = bumpalo::vec![in &arena];
+
+        // Like the profiling hook, a call that comes before the frame is set up.
+        X86_64Assembler::call(&mut prologue, &mut relocs, "__fentry__".to_string());
+        let frame_start = prologue.len();
+        let aligned_stack_size =
+            X86_64WindowsFastcall::setup_stack(&mut prologue, &[RBX], &[], 8, 0, true);
+        assert_eq!(aligned_stack_size, 16);
+
+        let info =
+            X86_64WindowsFastcall::unwind_info(&prologue, frame_start, &[RBX], &[], 16, 0).unwrap();
+
+        #[rustfmt::skip]
+        let expected = [
+            // version 1, prologue size, 4 slots of codes, no frame register
+            0x01, 20, 4, 0x00,
+            // mov [rbp - 0x10], rbx ends at 20: SAVE_NONVOL rbx at rsp + 0 * 8
+            20, 0x34, 0, 0,
+            // sub rsp, 0x10 ends at 16: ALLOC_SMALL of (1 + 1) * 8
+            16, 0x12,
+            // push rbp ends at 6: PUSH_NONVOL rbp
+            6, 0x50,
+        ];
+        assert_eq!(prologue.len(), 20);
+        assert_eq!(info, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not set up the frame")]
+    fn windows_unwind_info_rejects_a_prologue_it_does_not_describe() {
+        roc_error_macros::set_panic_not_exit(true); // so the error can be caught
+        let arena = bumpalo::Bump::new();
+        let mut prologue = bumpalo::vec![in &arena];
+        X86_64WindowsFastcall::setup_stack(&mut prologue, &[], &[], 8, 0, true);
+
+        // The frame doesn't start where the unwind info is told it does.
+        X86_64WindowsFastcall::unwind_info(&prologue, 1, &[], &[], 16, 0);
+    }
+
+    #[test]
+    fn windows_unwind_info_pads_to_an_even_number_of_codes() {
+        let arena = bumpalo::Bump::new();
+        let mut prologue = bumpalo::vec![in &arena];
+        X86_64WindowsFastcall::setup_stack(&mut prologue, &[], &[], 0, 0, true);
+
+        let info = X86_64WindowsFastcall::unwind_info(&prologue, 0, &[], &[], 0, 0).unwrap();
+
+        // Only the push of RBP, plus an empty slot.
+        assert_eq!(info, [0x01, 4, 1, 0x00, 1, 0x50, 0, 0]);
+    }
+
     #[test]
     fn test_mov_reg64_reg64() {
         disassembler_test!(
@@ -4962,6 +6306,153 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_movaps_base64_offset32_freg128() {
+        disassembler_test!(
+            movaps_base64_offset32_freg128,
+            |reg1, imm, reg2| format!("movaps xmmword ptr [{reg1} + 0x{imm:x}], {reg2}"),
+            ALL_GENERAL_REGS,
+            [TEST_I32],
+            ALL_FLOAT_REGS
+        );
+    }
+
+    #[test]
+    fn test_movups_base64_offset32_freg128() {
+        disassembler_test!(
+            movups_base64_offset32_freg128,
+            |reg1, imm, reg2| format!("movups xmmword ptr [{reg1} + 0x{imm:x}], {reg2}"),
+            ALL_GENERAL_REGS,
+            [TEST_I32],
+            ALL_FLOAT_REGS
+        );
+    }
+
+    #[test]
+    fn test_movaps_freg128_base64_offset32() {
+        disassembler_test!(
+            movaps_freg128_base64_offset32,
+            |reg1, reg2, imm| format!("movaps {reg1}, xmmword ptr [{reg2} + 0x{imm:x}]"),
+            ALL_FLOAT_REGS,
+            ALL_GENERAL_REGS,
+            [TEST_I32]
+        );
+    }
+
+    #[test]
+    fn test_movups_freg128_base64_offset32() {
+        disassembler_test!(
+            movups_freg128_base64_offset32,
+            |reg1, reg2, imm| format!("movups {reg1}, xmmword ptr [{reg2} + 0x{imm:x}]"),
+            ALL_FLOAT_REGS,
+            ALL_GENERAL_REGS,
+            [TEST_I32]
+        );
+    }
+
+    #[test]
+    fn test_movdqa_base64_offset32_freg128() {
+        disassembler_test!(
+            movdqa_base64_offset32_freg128,
+            |reg1, imm, reg2| format!("movdqa xmmword ptr [{reg1} + 0x{imm:x}], {reg2}"),
+            ALL_GENERAL_REGS,
+            [TEST_I32],
+            ALL_FLOAT_REGS
+        );
+    }
+
+    #[test]
+    fn test_movdqu_base64_offset32_freg128() {
+        disassembler_test!(
+            movdqu_base64_offset32_freg128,
+            |reg1, imm, reg2| format!("movdqu xmmword ptr [{reg1} + 0x{imm:x}], {reg2}"),
+            ALL_GENERAL_REGS,
+            [TEST_I32],
+            ALL_FLOAT_REGS
+        );
+    }
+
+    #[test]
+    fn test_movdqa_freg128_base64_offset32() {
+        disassembler_test!(
+            movdqa_freg128_base64_offset32,
+            |reg1, reg2, imm| format!("movdqa {reg1}, xmmword ptr [{reg2} + 0x{imm:x}]"),
+            ALL_FLOAT_REGS,
+            ALL_GENERAL_REGS,
+            [TEST_I32]
+        );
+    }
+
+    #[test]
+    fn test_movdqu_freg128_base64_offset32() {
+        disassembler_test!(
+            movdqu_freg128_base64_offset32,
+            |reg1, reg2, imm| format!("movdqu {reg1}, xmmword ptr [{reg2} + 0x{imm:x}]"),
+            ALL_FLOAT_REGS,
+            ALL_GENERAL_REGS,
+            [TEST_I32]
+        );
+    }
+
+    #[test]
+    fn struct_copies_are_aligned_only_between_aligned_slots() {
+        let arena = bumpalo::Bump::new();
+        let mnemonics = |to_offset, from_offset| {
+            let (mut buf, cs) = setup_capstone_and_arena(&arena);
+            X86_64Assembler::mov_base32_base32_128(
+                &mut buf,
+                X86_64FloatReg::XMM0,
+                to_offset,
+                from_offset,
+            );
+            let instructions = cs.disasm_all(&buf, 0).expect("Failed to disassemble");
+            instructions
+                .iter()
+                .map(|inst| inst.mnemonic().unwrap().to_string())
+                .collect::<std::vec::Vec<_>>()
+        };
+
+        assert_eq!(mnemonics(-32, -16), ["movdqa", "movdqa"]);
+        assert_eq!(mnemonics(-40, -16), ["movdqu", "movdqu"]);
+        assert_eq!(mnemonics(-32, -24), ["movdqu", "movdqu"]);
+    }
+
+    #[test]
+    fn saved_float_regs_use_aligned_moves_only_in_aligned_slots() {
+        let arena = bumpalo::Bump::new();
+        let float_moves = |saved_general_regs: &[X86_64GeneralReg]| {
+            let (mut buf, cs) = setup_capstone_and_arena(&arena);
+            let size = x86_64_generic_setup_stack(
+                &mut buf,
+                saved_general_regs,
+                &[X86_64FloatReg::XMM6],
+                0,
+                0,
+                true,
+            );
+            x86_64_generic_cleanup_stack(
+                &mut buf,
+                saved_general_regs,
+                &[X86_64FloatReg::XMM6],
+                size,
+                0,
+                true,
+            );
+
+            let instructions = cs.disasm_all(&buf, 0).expect("Failed to disassemble");
+            instructions
+                .iter()
+                .map(|inst| inst.mnemonic().unwrap().to_string())
+                .filter(|mnemonic| mnemonic.starts_with("mov") && mnemonic.ends_with("ps"))
+                .collect::<std::vec::Vec<_>>()
+        };
+
+        // With no general registers before it, the slot is at the 16-byte aligned stack size.
+        assert_eq!(float_moves(&[]), ["movaps", "movaps"]);
+        // One saved general register pushes it 8 bytes off of alignment.
+        assert_eq!(float_moves(&[X86_64GeneralReg::RBX]), ["movups", "movups"]);
+    }
+
     #[test]
     fn test_movss_base64_offset32_freg64() {
         disassembler_test!(
@@ -4984,6 +6475,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mov_reg64_fs_offset32() {
+        disassembler_test!(
+            mov_reg64_fs_offset32,
+            |reg, imm| format!("mov {reg}, qword ptr fs:[0x{imm:x}]"),
+            ALL_GENERAL_REGS,
+            [0x28]
+        );
+    }
+
     #[test]
     fn test_mov_reg32_base32_offset32() {
         disassembler_test!(
@@ -5043,6 +6544,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_base_offset_displacement_size() {
+        use X86_64GeneralReg::{RAX, RBP, RSP};
+
+        let arena = bumpalo::Bump::new();
+        let mut buf = bumpalo::vec![in &arena];
+
+        // Offsets in -128..=127 get a disp8, anything further away needs a disp32.
+        for (base, offset, expected) in [
+            (RBP, -8, &[0x48, 0x89, 0x45, 0xF8][..]),
+            (RBP, -128, &[0x48, 0x89, 0x45, 0x80]),
+            (RBP, -136, &[0x48, 0x89, 0x85, 0x78, 0xFF, 0xFF, 0xFF]),
+            (RBP, 128, &[0x48, 0x89, 0x85, 0x80, 0x00, 0x00, 0x00]),
+            (RSP, 8, &[0x48, 0x89, 0x44, 0x24, 0x08]),
+            (RSP, 256, &[0x48, 0x89, 0x84, 0x24, 0x00, 0x01, 0x00, 0x00]),
+        ] {
+            buf.clear();
+            mov_base64_offset32_reg64(&mut buf, base, offset, RAX);
+            assert_eq!(&buf[..], expected, "[{base:?} + {offset}]");
+        }
+    }
+
+    #[test]
+    fn test_movnti_base64_offset32_reg64() {
+        disassembler_test!(
+            movnti_base64_offset32_reg64,
+            |reg1, imm, reg2| format!("movnti qword ptr [{reg1} + 0x{imm:x}], {reg2}"),
+            ALL_GENERAL_REGS,
+            [TEST_I32],
+            ALL_GENERAL_REGS
+        );
+    }
+
     #[test]
     fn test_mov_base32_offset32_reg32() {
         disassembler_test!(
@@ -5211,6 +6745,25 @@ mod tests {
         disassembler_test!(neg_reg64, |reg| format!("neg {reg}"), ALL_GENERAL_REGS);
     }
 
+    #[test]
+    fn test_neg_reg8() {
+        disassembler_test!(
+            neg_reg8,
+            |reg: X86_64GeneralReg| format!("neg {}", reg.low_8bits_string()),
+            ALL_GENERAL_REGS
+        );
+    }
+
+    #[test]
+    fn test_inc_reg64() {
+        disassembler_test!(inc_reg64, |reg| format!("inc {reg}"), ALL_GENERAL_REGS);
+    }
+
+    #[test]
+    fn test_dec_reg64() {
+        disassembler_test!(dec_reg64, |reg| format!("dec {reg}"), ALL_GENERAL_REGS);
+    }
+
     #[test]
     fn test_cvtsi2_help() {
         const CVTSI2SS_CODE: u8 = 0x2A;
@@ -5249,11 +6802,178 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sets_reg64() {
+        disassembler_test!(
+            sets_reg64,
+            |reg: X86_64GeneralReg| format!("sets {}\nand {}, 1", reg.low_8bits_string(), reg),
+            ALL_GENERAL_REGS
+        );
+    }
+
+    #[test]
+    fn test_test_reg_reg() {
+        disassembler_test!(
+            |buf, r1, r2| test_reg_reg(buf, RegisterWidth::W8, r1, r2),
+            |r1: X86_64GeneralReg, r2: X86_64GeneralReg| format!(
+                "test {}, {}",
+                r1.low_8bits_string(),
+                r2.low_8bits_string()
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+        disassembler_test!(
+            |buf, r1, r2| test_reg_reg(buf, RegisterWidth::W16, r1, r2),
+            |r1: X86_64GeneralReg, r2: X86_64GeneralReg| format!(
+                "test {}, {}",
+                r1.low_16bits_string(),
+                r2.low_16bits_string()
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+        disassembler_test!(
+            |buf, r1, r2| test_reg_reg(buf, RegisterWidth::W32, r1, r2),
+            |r1: X86_64GeneralReg, r2: X86_64GeneralReg| format!(
+                "test {}, {}",
+                r1.low_32bits_string(),
+                r2.low_32bits_string()
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+        disassembler_test!(
+            |buf, r1, r2| test_reg_reg(buf, RegisterWidth::W64, r1, r2),
+            |r1, r2| format!("test {r1}, {r2}"),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
+    #[test]
+    fn test_eq_freg_freg_reg64_skips_sete_when_unordered() {
+        disassembler_test!(
+            |buf: &mut Vec<'_, u8>| {
+                X86_64Assembler::eq_freg_freg_reg64(
+                    buf,
+                    X86_64GeneralReg::RAX,
+                    X86_64FloatReg::XMM0,
+                    X86_64FloatReg::XMM1,
+                    FloatWidth::F64,
+                )
+            },
+            || {
+                // xor is 2 bytes, ucomisd 4, jp 2 and sete 3.
+                format!(
+                    "xor eax, eax\nucomisd xmm0, xmm1\njp 0x{:x}\nsete al",
+                    2 + 4 + 2 + 3
+                )
+            }
+        );
+    }
+
+    #[test]
+    fn test_neq_freg_freg_reg64_skips_setne_when_unordered() {
+        disassembler_test!(
+            |buf: &mut Vec<'_, u8>| {
+                X86_64Assembler::neq_freg_freg_reg64(
+                    buf,
+                    X86_64GeneralReg::R8,
+                    X86_64FloatReg::XMM2,
+                    X86_64FloatReg::XMM3,
+                    FloatWidth::F64,
+                )
+            },
+            || {
+                // mov is 6 bytes, ucomisd 4, jp 2 and setne 4.
+                format!(
+                    "mov r8d, 1\nucomisd xmm2, xmm3\njp 0x{:x}\nsetne r8b",
+                    6 + 4 + 2 + 4
+                )
+            }
+        );
+    }
+
+    #[test]
+    fn test_syscall() {
+        disassembler_test!(syscall, || "syscall");
+    }
+
     #[test]
     fn test_ret() {
         disassembler_test!(ret, || "ret");
     }
 
+    #[test]
+    fn test_ret_near_imm16() {
+        disassembler_test!(
+            ret_near_imm16,
+            |imm| format!("ret 0x{imm:x}"),
+            [0x8u16, 0x18, 0x1234]
+        );
+    }
+
+    #[test]
+    fn data_pointer_adds_its_offset_after_the_got_load() {
+        let arena = bumpalo::Bump::new();
+        let mut buf = bumpalo::vec![in &arena];
+        let mut relocs = bumpalo::vec![in &arena];
+
+        X86_64Assembler::data_pointer(
+            &mut buf,
+            &mut relocs,
+            "config".to_string(),
+            16,
+            X86_64GeneralReg::RAX,
+        );
+
+        #[rustfmt::skip]
+        let expected = [
+            // lea rax, [rip + config@GOTPCREL]
+            0x48, 0x8D, 0x05, 0x00, 0x00, 0x00, 0x00,
+            // mov rax, [rax]
+            0x48, 0x8B, 0x40, 0x00,
+            // add rax, 0x10
+            0x48, 0x81, 0xC0, 0x10, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(&buf[..], expected);
+
+        match &relocs[..] {
+            [Relocation::LinkedData {
+                offset: 3,
+                name,
+                addend: 0,
+            }] => assert_eq!(name, "config"),
+            other => panic!("expected a GOT reference to config, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn callee_cleanup_return_pops_the_stack_args() {
+        let arena = bumpalo::Bump::new();
+        let mut buf = bumpalo::vec![in &arena];
+
+        ret_popping_stack_args(&mut buf, 0);
+        ret_popping_stack_args(&mut buf, 24);
+        assert_eq!(&buf[..], [0xC3, 0xC2, 0x18, 0x00]);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be popped by ret")]
+    fn callee_cleanup_return_rejects_more_than_fits_in_the_immediate() {
+        roc_error_macros::set_panic_not_exit(true); // so the error can be caught
+
+        let arena = bumpalo::Bump::new();
+        let mut buf = bumpalo::vec![in &arena];
+        ret_popping_stack_args(&mut buf, 0x1_0000);
+    }
+
+    #[test]
+    fn test_ud2() {
+        disassembler_test!(ud2, || "ud2");
+    }
+
     #[test]
     fn test_sub_reg64_imm32() {
         disassembler_test!(
@@ -5,12 +5,10 @@
 // See github.com/roc-lang/roc/issues/800 for discussion of the large_enum_variant check.
 #![allow(clippy::large_enum_variant, clippy::upper_case_acronyms)]
 
-use std::collections::hash_map::Entry;
-
 use bumpalo::{collections::Vec, Bump};
 use roc_builtins::bitcode::{self, FloatWidth, IntWidth};
 use roc_collections::all::{MutMap, MutSet};
-use roc_error_macros::{internal_error, todo_lambda_erasure};
+use roc_error_macros::{internal_error, todo_lambda_erasure, user_error};
 use roc_module::ident::ModuleName;
 use roc_module::low_level::{LowLevel, LowLevelWrapperType};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
@@ -27,7 +25,7 @@ use roc_mono::list_element_layout;
 
 mod generic64;
 mod object_builder;
-pub use object_builder::build_module;
+pub use object_builder::{build_module, supports};
 use roc_target::Target;
 mod run_roc;
 
@@ -67,14 +65,128 @@ impl AssemblyBackendMode {
     }
 }
 
+/// How the default integer arithmetic operations behave when the result does not fit.
+/// The explicit `*Wrap`, `*Checked` and `*Saturated` operations are not affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Panic on overflow, as the language specifies, whatever the opt level
+    Trap,
+    /// Wrap around on overflow, like the `*Wrap` operations
+    Wrap,
+}
+
+/// Parts of the language to ask the backend about with `supports` before building a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Floats,
+    Decimals,
+    Calls,
+    TagUnions,
+    Lists,
+    Strings,
+    Dbg,
+    Expect,
+    ErasedLambdas,
+}
+
 pub struct Env<'a> {
     pub arena: &'a Bump,
     pub module_id: ModuleId,
     pub exposed_to_host: MutSet<Symbol>,
     pub lazy_literals: bool,
     pub mode: AssemblyBackendMode,
+    pub options: DevOptions,
+}
+
+/// Options for the code the backend generates. The defaults suit a plain build.
+#[derive(Debug, Clone)]
+pub struct DevOptions {
+    pub overflow_mode: OverflowMode,
+    /// A literal that is live across a call and costs at most this much to load again
+    /// is loaded again after the call instead of being spilled. 0 spills every literal.
+    /// Loading a small integer costs 1, a large integer or float costs 2.
+    /// This only applies with `lazy_literals`.
+    pub rematerialize_cost: u32,
+    /// Names of the procs that must not use the red zone, such as ones that may run as signal
+    /// handlers, where the kernel may clobber it.
+    pub no_red_zone: MutSet<String>,
+    /// Calls `__fentry__` on entry to every proc, like `-pg -mfentry`, for gprof or perf.
+    /// The call comes before the prologue, so the arguments are still in their registers,
+    /// and `__fentry__` preserves them. Only x86_64 Linux supports this.
+    pub profiling: bool,
+    /// How the register allocator picks which value to spill when it runs out of registers.
+    pub spill_heuristic: SpillHeuristic,
+    /// Which procs check a canary on the way out for stack smashing, like `-fstack-protector`.
+    pub stack_protector: StackProtector,
+    /// Generates a `_start` that calls this proc with argc and argv and exits with the result,
+    /// so the executable runs without a host.
+    pub standalone_main: Option<String>,
+    /// Zeroes the destination of an int to float conversion first. `cvtsi2sd` only writes the
+    /// low lane, so otherwise it has to wait for whatever last wrote the rest of the register.
+    pub break_false_dependencies: bool,
+    /// Follows code that never falls through, like a `ret` or a call to `roc_panic`, with an
+    /// instruction that traps, so falling through by mistake faults instead of running garbage.
+    /// This is up to the build and does not depend on the opt level.
+    pub trap_unreachable: bool,
+    /// List literals of words with at least this many bytes of data are written with
+    /// non-temporal stores, so filling them doesn't evict everything else from the cache.
+    /// This is a hint from whoever knows the list won't be read again soon. The IR says
+    /// nothing about how a list is used later, so `None`, the default, never streams.
+    pub streaming_write_min_bytes: Option<u64>,
+}
+
+impl Default for DevOptions {
+    fn default() -> Self {
+        Self {
+            overflow_mode: OverflowMode::Trap,
+            rematerialize_cost: DEFAULT_REMATERIALIZE_COST,
+            no_red_zone: MutSet::default(),
+            profiling: false,
+            spill_heuristic: SpillHeuristic::default(),
+            stack_protector: StackProtector::default(),
+            standalone_main: None,
+            break_false_dependencies: false,
+            trap_unreachable: false,
+            streaming_write_min_bytes: None,
+        }
+    }
+}
+
+/// Which procs get stack smashing protection.
+/// A protected proc stores a canary from the C runtime's guard at the top of its frame, where an
+/// overflowing stack buffer would overwrite it, and calls `__stack_chk_fail` if it changed.
+/// Only x86_64 Linux keeps the guard where the backend can find it, other targets ignore this.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum StackProtector {
+    #[default]
+    Off,
+    /// Procs that keep an aggregate (e.g. a struct or a string) on the stack, like `-fstack-protector`.
+    Buffers,
+    /// Every proc, like `-fstack-protector-all`.
+    All,
+}
+
+/// How the register allocator picks a register to spill when every register is in use.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SpillHeuristic {
+    /// Spill the register that was loaded longest ago.
+    #[default]
+    LeastRecentlyLoaded,
+    /// Spill the register whose value is used again furthest in the future, or never again.
+    /// This is Belady's algorithm, with the next uses found by scanning the proc body.
+    FurthestNextUse,
 }
 
+/// The hook called on entry to every proc when profiling.
+pub const PROFILING_HOOK: &str = "__fentry__";
+
+/// Called by a protected proc that finds its stack canary overwritten. It never returns.
+pub const STACK_CHK_FAIL: &str = "__stack_chk_fail";
+
+/// Spilling a value costs a store and a load, so by default only literals that
+/// are no more expensive than that to load again are rematerialized.
+pub const DEFAULT_REMATERIALIZE_COST: u32 = 2;
+
 // These relocations likely will need a length.
 // They may even need more definition, but this should be at least good enough for how we will use elf.
 #[derive(Debug, Clone)]
@@ -84,6 +196,8 @@ pub enum Relocation {
         // This should probably technically be a bumpalo::Vec.
         // The problem is that it currently is built in a place that can't access the arena.
         data: std::vec::Vec<u8>,
+        /// The alignment, in bytes, the data must be placed at in the data section.
+        align: u64,
     },
     LinkedFunction {
         offset: u64,
@@ -92,6 +206,8 @@ pub enum Relocation {
     LinkedData {
         offset: u64,
         name: String,
+        /// Added to the address of `name`, e.g. the offset of a field within it.
+        addend: i64,
     },
     JmpToReturn {
         inst_loc: u64,
@@ -100,11 +216,138 @@ pub enum Relocation {
     },
 }
 
+/// Where the code of a finalized proc is written, e.g. an arena `Vec` or a caller's own buffer.
+pub trait CodeSink {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn write(&mut self, bytes: &[u8]);
+}
+
+impl CodeSink for Vec<'_, u8> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+impl CodeSink for std::vec::Vec<u8> {
+    fn len(&self) -> usize {
+        std::vec::Vec::len(self)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
 #[repr(u8)]
 enum UpdateMode {
     Immutable = 0,
 }
 
+/// An operand or result whose layout doesn't fit the op it belongs to. Type checking rules
+/// this out, so it always means an earlier pass has a bug.
+#[derive(Debug, PartialEq)]
+struct LayoutMismatch<'a> {
+    lowlevel: LowLevel,
+    expected: &'static str,
+    actual: LayoutRepr<'a>,
+}
+
+impl std::fmt::Display for LayoutMismatch<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} expects {} operands, but got {:?}",
+            self.lowlevel, self.expected, self.actual
+        )
+    }
+}
+
+/// The kind of value a binary lowlevel takes.
+#[derive(Clone, Copy, Debug)]
+enum OperandClass {
+    Int,
+    Frac,
+    Num,
+    Bool,
+}
+
+impl OperandClass {
+    /// What a binary lowlevel takes, and whether it returns the same kind of value.
+    /// `None` for lowlevels that don't take two numbers or Bools.
+    fn of_binary(lowlevel: LowLevel) -> Option<(Self, bool)> {
+        use LowLevel::*;
+
+        match lowlevel {
+            NumAddWrap | NumSubWrap | NumMulWrap | NumDivTruncUnchecked | NumDivCeilUnchecked
+            | NumRemUnchecked | NumPowInt | NumBitwiseAnd | NumBitwiseXor | NumBitwiseOr
+            | NumShiftLeftBy | NumShiftRightBy | NumShiftRightZfBy => Some((Self::Int, true)),
+            NumIsMultipleOf => Some((Self::Int, false)),
+            NumDivFrac | NumPow => Some((Self::Frac, true)),
+            NumAdd | NumSub | NumMul | NumAddSaturated | NumSubSaturated | NumMulSaturated => {
+                Some((Self::Num, true))
+            }
+            NumAddChecked | NumSubChecked | NumMulChecked | NumGt | NumGte | NumLt | NumLte
+            | NumCompare => Some((Self::Num, false)),
+            And | Or => Some((Self::Bool, true)),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Int => "integer",
+            Self::Frac => "fraction",
+            Self::Num => "number",
+            Self::Bool => "Bool",
+        }
+    }
+
+    fn fits(self, repr: LayoutRepr) -> bool {
+        match (self, repr) {
+            (Self::Int | Self::Num, LayoutRepr::Builtin(Builtin::Int(_))) => true,
+            (Self::Frac | Self::Num, LayoutRepr::Builtin(Builtin::Float(_) | Builtin::Decimal)) => {
+                true
+            }
+            (Self::Bool, LayoutRepr::Builtin(Builtin::Bool)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Checks the operands of a binary numeric or Bool lowlevel, and its result if it is the same
+/// kind of value. Any other lowlevel is let through.
+fn check_binary_layouts<'a>(
+    interner: &STLayoutInterner<'a>,
+    lowlevel: LowLevel,
+    arg_layouts: &[InLayout<'a>],
+    ret_layout: InLayout<'a>,
+) -> Result<(), LayoutMismatch<'a>> {
+    let Some((class, same_result)) = OperandClass::of_binary(lowlevel) else {
+        return Ok(());
+    };
+    let result = same_result.then_some(&ret_layout);
+    for layout in arg_layouts.iter().chain(result) {
+        let actual = interner.get_repr(*layout);
+        if !class.fits(actual) {
+            return Err(LayoutMismatch {
+                lowlevel,
+                expected: class.name(),
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
 struct ListArgument<'a> {
     element_layout: InLayout<'a>,
 
@@ -113,8 +356,12 @@ struct ListArgument<'a> {
     element_refcounted: Symbol,
 }
 
-// Track when a variable is last used (and hence when it can be disregarded). This is non-trivial
-// in the presence of join points. Consider this example:
+// Track where each variable stops being live (and hence where it can be disregarded).
+// This is a backwards liveness analysis over the statement tree, so it is exact for branches:
+// a symbol that is only used in one arm of a switch is freed on entry to every other arm,
+// and a symbol used in several arms is freed after its last use in each of them.
+//
+// Join points still need care. Consider this example:
 //
 // let len = 3
 //
@@ -126,182 +373,693 @@ struct ListArgument<'a> {
 // in
 //     ...
 //
-// we have to keep `len` alive until after the joinpoint goes out of scope!
-#[derive(Debug, Default)]
-struct LastSeenMap<'a> {
-    last_seen: MutMap<Symbol, *const Stmt<'a>>,
-    join_map: MutMap<JoinPointId, &'a [Param<'a>]>,
+// The body of a join point is built once but entered from every jump to it, so everything it
+// uses from outside (and its parameters) is pinned: we have to keep `len` alive until after the
+// joinpoint goes out of scope!
+#[derive(Debug)]
+struct Liveness<'a> {
+    arena: &'a Bump,
+    /// The symbols that are dead after a statement has run.
+    free_map: MutMap<*const Stmt<'a>, Vec<'a, Symbol>>,
+    /// The symbols that are live into a switch but dead on entry to one of its branches.
+    entry_free_map: MutMap<*const Stmt<'a>, Vec<'a, Symbol>>,
+    /// The symbols a join point body uses from outside of it.
+    join_live: MutMap<JoinPointId, MutSet<Symbol>>,
+    /// The symbols bound by a `Let` whose value is never used.
+    unused_results: MutSet<Symbol>,
 }
 
-impl<'a> LastSeenMap<'a> {
-    fn set_last_seen(&mut self, symbol: Symbol, stmt: &'a Stmt<'a>) {
-        self.last_seen.insert(symbol, stmt);
-    }
+impl<'a> Liveness<'a> {
+    /// scan_ast runs through the ast and computes where every symbol can be freed.
+    fn scan_ast(arena: &'a Bump, root: &'a Stmt<'a>) -> Self {
+        let mut this = Liveness {
+            arena,
+            free_map: MutMap::default(),
+            entry_free_map: MutMap::default(),
+            join_live: MutMap::default(),
+            unused_results: MutSet::default(),
+        };
 
-    /// scan_ast runs through the ast and fill the last seen map.
-    /// This must iterate through the ast in the same way that build_stmt does. i.e. then before else.
-    fn scan_ast(root: &'a Stmt<'a>) -> MutMap<Symbol, *const Stmt<'a>> {
-        let mut this: Self = Default::default();
+        this.live_in(root, &MutSet::default(), true);
 
-        this.scan_ast_help(root);
+        this
+    }
 
-        this.last_seen
+    fn free_after(&mut self, stmt: &'a Stmt<'a>, symbol: Symbol) {
+        let arena = self.arena;
+        let symbols = self
+            .free_map
+            .entry(stmt)
+            .or_insert_with(|| bumpalo::vec![in arena]);
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
     }
 
-    fn scan_ast_help(&mut self, stmt: &'a Stmt<'a>) {
+    fn free_on_entry(&mut self, stmt: &'a Stmt<'a>, symbol: Symbol) {
+        let arena = self.arena;
+        let symbols = self
+            .entry_free_map
+            .entry(stmt)
+            .or_insert_with(|| bumpalo::vec![in arena]);
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+
+    /// Returns the symbols that are live on entry to `stmt`.
+    /// Symbols in `pinned` are kept alive by an enclosing join point and are never freed here.
+    /// With `record` unset this only computes the live set, e.g. to find what a join point body uses.
+    fn live_in(
+        &mut self,
+        stmt: &'a Stmt<'a>,
+        pinned: &MutSet<Symbol>,
+        record: bool,
+    ) -> MutSet<Symbol> {
         match stmt {
             Stmt::Let(sym, expr, _, following) => {
-                self.set_last_seen(*sym, stmt);
-                match expr {
-                    Expr::Literal(_) => {}
-                    Expr::NullPointer => {}
+                let mut live = self.live_in(following, pinned, record);
 
-                    Expr::Call(call) => self.scan_ast_call(call, stmt),
+                let mut used = std::vec::Vec::new();
+                Self::expr_uses(expr, &mut used);
 
-                    Expr::Tag {
-                        arguments, reuse, ..
-                    } => {
-                        if let Some(ru) = reuse {
-                            self.set_last_seen(ru.symbol, stmt);
+                if record {
+                    for symbol in used.iter().chain([sym]) {
+                        if !live.contains(symbol) && !pinned.contains(symbol) {
+                            self.free_after(stmt, *symbol);
                         }
-
-                        for sym in *arguments {
-                            self.set_last_seen(*sym, stmt);
-                        }
-                    }
-                    Expr::ErasedMake { value, callee } => {
-                        value.map(|v| self.set_last_seen(v, stmt));
-                        self.set_last_seen(*callee, stmt);
                     }
-                    Expr::ErasedLoad { symbol, field: _ } => {
-                        self.set_last_seen(*symbol, stmt);
-                    }
-                    Expr::Struct(syms) => {
-                        for sym in *syms {
-                            self.set_last_seen(*sym, stmt);
-                        }
-                    }
-                    Expr::StructAtIndex { structure, .. } => {
-                        self.set_last_seen(*structure, stmt);
-                    }
-                    Expr::GetTagId { structure, .. } => {
-                        self.set_last_seen(*structure, stmt);
-                    }
-                    Expr::UnionAtIndex { structure, .. } => {
-                        self.set_last_seen(*structure, stmt);
-                    }
-                    Expr::GetElementPointer { structure, .. } => {
-                        self.set_last_seen(*structure, stmt);
-                    }
-                    Expr::Array { elems, .. } => {
-                        for elem in *elems {
-                            if let ListLiteralElement::Symbol(sym) = elem {
-                                self.set_last_seen(*sym, stmt);
-                            }
-                        }
-                    }
-                    Expr::Reset { symbol, .. } | Expr::ResetRef { symbol, .. } => {
-                        self.set_last_seen(*symbol, stmt);
-                    }
-                    Expr::Alloca { initializer, .. } => {
-                        if let Some(initializer) = initializer {
-                            self.set_last_seen(*initializer, stmt);
-                        }
+
+                    if !live.contains(sym) && !pinned.contains(sym) {
+                        self.unused_results.insert(*sym);
                     }
-                    Expr::FunctionPointer { .. } => todo_lambda_erasure!(),
-                    Expr::EmptyArray => {}
                 }
-                self.scan_ast_help(following);
+
+                live.remove(sym);
+                live.extend(used);
+                live
+            }
+            Stmt::Refcounting(modify, following) => {
+                let mut live = self.live_in(following, pinned, record);
+                let sym = modify.get_symbol();
+
+                if record && !live.contains(&sym) && !pinned.contains(&sym) {
+                    self.free_after(stmt, sym);
+                }
+
+                live.insert(sym);
+                live
             }
+            Stmt::Ret(sym) | Stmt::Crash(sym, _) => {
+                if record && !pinned.contains(sym) {
+                    self.free_after(stmt, *sym);
+                }
 
+                MutSet::from_iter([*sym])
+            }
             Stmt::Switch {
                 cond_symbol,
                 branches,
                 default_branch,
                 ..
             } => {
-                self.set_last_seen(*cond_symbol, stmt);
-                for (_, _, branch) in *branches {
-                    self.scan_ast_help(branch);
+                let arms: std::vec::Vec<(&'a Stmt<'a>, MutSet<Symbol>)> = branches
+                    .iter()
+                    .map(|(_, _, branch)| branch)
+                    .chain([default_branch.1])
+                    .map(|branch| (branch, self.live_in(branch, pinned, record)))
+                    .collect();
+
+                let mut live = MutSet::default();
+                for (_, arm_live) in arms.iter() {
+                    live.extend(arm_live.iter().copied());
                 }
-                self.scan_ast_help(default_branch.1);
-            }
-            Stmt::Ret(sym) => {
-                self.set_last_seen(*sym, stmt);
-            }
-            Stmt::Refcounting(modify, following) => {
-                let sym = modify.get_symbol();
 
-                self.set_last_seen(sym, stmt);
-                self.scan_ast_help(following);
+                if record {
+                    // The condition is only freed after the whole switch, its register is used to
+                    // pick the arm.
+                    if !live.contains(cond_symbol) && !pinned.contains(cond_symbol) {
+                        self.free_after(stmt, *cond_symbol);
+                    }
+
+                    for (branch, arm_live) in arms.iter() {
+                        for symbol in live.iter() {
+                            if !arm_live.contains(symbol)
+                                && !pinned.contains(symbol)
+                                && symbol != cond_symbol
+                            {
+                                self.free_on_entry(*branch, *symbol);
+                            }
+                        }
+                    }
+                }
+
+                live.insert(*cond_symbol);
+                live
             }
             Stmt::Join {
                 parameters,
-                body: continuation,
+                body,
                 remainder,
-                id: JoinPointId(sym),
-                ..
+                id,
             } => {
-                self.set_last_seen(*sym, stmt);
-                self.join_map.insert(JoinPointId(*sym), parameters);
-                self.scan_ast_help(remainder);
-
-                for (symbol, symbol_stmt) in Self::scan_ast(continuation) {
-                    match self.last_seen.entry(symbol) {
-                        Entry::Occupied(mut occupied) => {
-                            // lives for the joinpoint
-                            occupied.insert(stmt);
-                        }
-                        Entry::Vacant(vacant) => {
-                            // lives for some time within the continuation
-                            vacant.insert(symbol_stmt);
+                let mut owned = MutSet::from_iter(parameters.iter().map(|param| param.symbol));
+                owned.insert(id.0);
+
+                let mut body_live = self.live_in(body, pinned, false);
+                body_live.retain(|symbol| !owned.contains(symbol));
+                self.join_live.insert(*id, body_live.clone());
+
+                let mut join_pinned = pinned.clone();
+                join_pinned.extend(body_live.iter().copied());
+                join_pinned.extend(owned.iter().copied());
+
+                let mut live = self.live_in(remainder, &join_pinned, record);
+                if record {
+                    self.live_in(body, &join_pinned, true);
+
+                    for symbol in body_live.iter().chain(owned.iter()) {
+                        if !pinned.contains(symbol) {
+                            self.free_after(stmt, *symbol);
                         }
                     }
                 }
 
-                for param in *parameters {
-                    self.set_last_seen(param.symbol, stmt);
-                }
+                live.extend(body_live);
+                live.retain(|symbol| !owned.contains(symbol));
+                live
             }
-            Stmt::Jump(JoinPointId(sym), symbols) => {
-                if let Some(parameters) = self.join_map.get(&JoinPointId(*sym)) {
-                    // Keep the parameters around. They will be overwritten when jumping.
-                    for param in *parameters {
-                        self.set_last_seen(param.symbol, stmt);
+            Stmt::Jump(id, symbols) => {
+                if record {
+                    for sym in *symbols {
+                        if !pinned.contains(sym) {
+                            self.free_after(stmt, *sym);
+                        }
                     }
                 }
-                for sym in *symbols {
-                    self.set_last_seen(*sym, stmt);
+
+                let mut live = MutSet::from_iter(symbols.iter().copied());
+                if let Some(body_live) = self.join_live.get(id) {
+                    live.extend(body_live.iter().copied());
                 }
+                live
             }
 
-            Stmt::Dbg { .. } => todo!("dbg not implemented in the dev backend"),
+            Stmt::Dbg {
+                symbol, remainder, ..
+            } => {
+                let mut live = self.live_in(remainder, pinned, record);
+
+                if record && !live.contains(symbol) && !pinned.contains(symbol) {
+                    self.free_after(stmt, *symbol);
+                }
+
+                live.insert(*symbol);
+                live
+            }
             Stmt::Expect { .. } => todo!("expect is not implemented in the dev backend"),
+        }
+    }
+
+    fn expr_uses(expr: &Expr<'a>, used: &mut std::vec::Vec<Symbol>) {
+        match expr {
+            Expr::Literal(_) => {}
+            Expr::NullPointer => {}
+
+            Expr::Call(call) => used.extend(call.arguments.iter().copied()),
+
+            Expr::Tag {
+                arguments, reuse, ..
+            } => {
+                if let Some(ru) = reuse {
+                    used.push(ru.symbol);
+                }
 
-            Stmt::Crash(msg, _crash_tag) => {
-                self.set_last_seen(*msg, stmt);
+                used.extend(arguments.iter().copied());
+            }
+            Expr::ErasedMake { value, callee } => {
+                used.extend(*value);
+                used.push(*callee);
+            }
+            Expr::ErasedLoad { symbol, field: _ } => {
+                used.push(*symbol);
+            }
+            Expr::Struct(syms) => {
+                used.extend(syms.iter().copied());
+            }
+            Expr::StructAtIndex { structure, .. }
+            | Expr::GetTagId { structure, .. }
+            | Expr::UnionAtIndex { structure, .. }
+            | Expr::GetElementPointer { structure, .. } => {
+                used.push(*structure);
+            }
+            Expr::Array { elems, .. } => {
+                for elem in *elems {
+                    if let ListLiteralElement::Symbol(sym) = elem {
+                        used.push(*sym);
+                    }
+                }
             }
+            Expr::Reset { symbol, .. } | Expr::ResetRef { symbol, .. } => {
+                used.push(*symbol);
+            }
+            Expr::Alloca { initializer, .. } => {
+                used.extend(*initializer);
+            }
+            Expr::FunctionPointer { .. } | Expr::EmptyArray => {}
         }
     }
+}
 
-    fn scan_ast_call(&mut self, call: &roc_mono::ir::Call, stmt: &'a roc_mono::ir::Stmt<'a>) {
-        let roc_mono::ir::Call {
-            call_type,
-            arguments,
-        } = call;
+/// Where each statement and each use of a symbol falls in a proc, numbered in the order the
+/// statements are built. This is what [`SpillHeuristic::FurthestNextUse`] spills by.
+///
+/// Every arm of a switch is numbered after the ones before it, so a use in a later arm counts as
+/// further away even though that arm may never run.
+#[derive(Debug, Default)]
+pub struct UsePositions<'a> {
+    stmts: MutMap<*const Stmt<'a>, usize>,
+    uses: MutMap<Symbol, std::vec::Vec<usize>>,
+}
 
-        for sym in *arguments {
-            self.set_last_seen(*sym, stmt);
+impl<'a> UsePositions<'a> {
+    fn scan(root: &'a Stmt<'a>) -> Self {
+        let mut this = Self::default();
+        this.number(root);
+        this
+    }
+
+    fn number(&mut self, stmt: &'a Stmt<'a>) {
+        let position = self.stmts.len();
+        self.stmts.insert(stmt, position);
+
+        let mut used = std::vec::Vec::new();
+        match stmt {
+            Stmt::Let(_, expr, _, following) => {
+                Liveness::expr_uses(expr, &mut used);
+                self.note_uses(&used, position);
+                self.number(following);
+            }
+            Stmt::Refcounting(modify, following) => {
+                self.note_uses(&[modify.get_symbol()], position);
+                self.number(following);
+            }
+            Stmt::Ret(sym) | Stmt::Crash(sym, _) => self.note_uses(&[*sym], position),
+            Stmt::Switch {
+                cond_symbol,
+                branches,
+                default_branch,
+                ..
+            } => {
+                self.note_uses(&[*cond_symbol], position);
+                for (_, _, branch) in branches.iter() {
+                    self.number(branch);
+                }
+                self.number(default_branch.1);
+            }
+            Stmt::Join {
+                body, remainder, ..
+            } => {
+                self.number(remainder);
+                self.number(body);
+            }
+            Stmt::Jump(_, symbols) => self.note_uses(symbols, position),
+            Stmt::Dbg {
+                symbol, remainder, ..
+            } => {
+                self.note_uses(&[*symbol], position);
+                self.number(remainder);
+            }
+            Stmt::Expect { .. } => {}
         }
+    }
 
-        match call_type {
-            CallType::ByName { .. } => {}
-            CallType::ByPointer { .. } => {}
-            CallType::LowLevel { .. } => {}
-            CallType::HigherOrder { .. } => {}
-            CallType::Foreign { .. } => {}
+    fn note_uses(&mut self, symbols: &[Symbol], position: usize) {
+        for sym in symbols {
+            self.uses.entry(*sym).or_default().push(position);
         }
     }
+
+    /// Returns the position of `stmt`, if it is part of the scanned body.
+    fn position(&self, stmt: &Stmt<'a>) -> Option<usize> {
+        self.stmts.get(&(stmt as *const Stmt<'a>)).copied()
+    }
+
+    /// Returns the position of the first use of `sym` at or after `position`, if there is one.
+    fn next_use(&self, sym: &Symbol, position: usize) -> Option<usize> {
+        let uses = self.uses.get(sym)?;
+        uses.get(uses.partition_point(|used| *used < position))
+            .copied()
+    }
+}
+
+/// Mono compiles a proc that is entirely a runtime error to `let msg = "..." in crash msg`.
+/// If the body has that shape, this returns the message.
+fn runtime_error_message<'a>(body: &Stmt<'a>) -> Option<&'a str> {
+    match body {
+        Stmt::Let(
+            sym,
+            Expr::Literal(Literal::Str(msg)),
+            _,
+            Stmt::Crash(crash_sym, CrashTag::Roc),
+        ) if sym == crash_sym => Some(*msg),
+        _ => None,
+    }
+}
+
+/// Checked arithmetic whose overflow field feeds a switch has the shape
+/// `let result = op a b in let flag = result.index in switch flag ...`.
+struct CheckedOverflowSwitch<'a, 'b> {
+    op: LowLevel,
+    arguments: &'a [Symbol],
+    flag: Symbol,
+    flag_layout: InLayout<'a>,
+    flag_index: u64,
+    field_layouts: &'a [InLayout<'a>],
+    switch: &'b Stmt<'a>,
+}
+
+fn checked_overflow_switch<'a, 'b>(stmt: &'b Stmt<'a>) -> Option<CheckedOverflowSwitch<'a, 'b>> {
+    match stmt {
+        Stmt::Let(
+            result,
+            Expr::Call(roc_mono::ir::Call {
+                call_type:
+                    CallType::LowLevel {
+                        op: op @ (LowLevel::NumAddChecked | LowLevel::NumSubChecked),
+                        ..
+                    },
+                arguments,
+            }),
+            _,
+            Stmt::Let(
+                flag,
+                Expr::StructAtIndex {
+                    index,
+                    field_layouts,
+                    structure,
+                },
+                flag_layout,
+                switch @ Stmt::Switch {
+                    cond_symbol,
+                    branches,
+                    ..
+                },
+            ),
+        ) if structure == result && cond_symbol == flag && !branches.is_empty() => {
+            Some(CheckedOverflowSwitch {
+                op: *op,
+                arguments: *arguments,
+                flag: *flag,
+                flag_layout: *flag_layout,
+                flag_index: *index,
+                field_layouts: *field_layouts,
+                switch,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// `x + (if cond then 1 else 0)` has the shape
+/// `joinpoint j = \v -> let result = x + v in following in switch cond { 1 -> jump j 1; _ -> jump j 0 }`,
+/// with each literal bound right before its jump.
+struct ConditionalIncrement<'a, 'b> {
+    result: Symbol,
+    value: Symbol,
+    cond: Symbol,
+    layout: InLayout<'a>,
+    add: &'b Stmt<'a>,
+    following: &'b Stmt<'a>,
+    switch: &'b Stmt<'a>,
+}
+
+fn conditional_increment<'a, 'b>(stmt: &'b Stmt<'a>) -> Option<ConditionalIncrement<'a, 'b>> {
+    let Stmt::Join {
+        id,
+        parameters: [param],
+        body: add @ Stmt::Let(result, Expr::Call(call), layout, following),
+        remainder:
+            switch @ Stmt::Switch {
+                cond_symbol,
+                cond_layout: Layout::BOOL,
+                branches: [(1, _, then_branch)],
+                default_branch: (_, else_branch),
+                ..
+            },
+    } = stmt
+    else {
+        return None;
+    };
+
+    let is_add = call_low_level(call) == Some(LowLevel::NumAdd);
+    let value = match call.arguments {
+        [a, b] if *b == param.symbol && *a != param.symbol => *a,
+        [a, b] if *a == param.symbol && *b != param.symbol => *b,
+        _ => return None,
+    };
+
+    let jumps_with = |branch: &Stmt<'a>, n: i128| {
+        matches!(
+            branch,
+            Stmt::Let(sym, Expr::Literal(Literal::Int(bytes)), _, Stmt::Jump(target, [arg]))
+                if target == id && arg == sym && *bytes == n.to_ne_bytes()
+        )
+    };
+
+    let increments = is_add
+        && param.layout == *layout
+        && jumps_with(then_branch, 1)
+        && jumps_with(else_branch, 0)
+        && !stmt_uses(following, param.symbol)
+        && !stmt_jumps_to(following, *id);
+
+    increments.then_some(ConditionalIncrement {
+        result: *result,
+        value,
+        cond: *cond_symbol,
+        layout: *layout,
+        add,
+        following,
+        switch,
+    })
+}
+
+/// `a < b && b < c` has the shape
+/// `let first = a < b in let second = b < c in let result = first && second in following`,
+/// where the two comparisons share their middle operand `b`.
+struct ChainedComparison<'a, 'b> {
+    result: Symbol,
+    ops: [LowLevel; 2],
+    operands: [Symbol; 3],
+    comparisons: [&'b Stmt<'a>; 3],
+    following: &'b Stmt<'a>,
+}
+
+fn chained_comparison<'a, 'b>(stmt: &'b Stmt<'a>) -> Option<ChainedComparison<'a, 'b>> {
+    let Stmt::Let(first, Expr::Call(first_call), _, second_stmt) = stmt else {
+        return None;
+    };
+    let Stmt::Let(second, Expr::Call(second_call), _, and_stmt) = second_stmt else {
+        return None;
+    };
+    let Stmt::Let(result, Expr::Call(and_call), _, following) = and_stmt else {
+        return None;
+    };
+
+    let is_comparison = |op: LowLevel| {
+        matches!(
+            op,
+            LowLevel::NumLt | LowLevel::NumLte | LowLevel::NumGt | LowLevel::NumGte
+        )
+    };
+    let (Some(first_op), Some(second_op)) =
+        (call_low_level(first_call), call_low_level(second_call))
+    else {
+        return None;
+    };
+    let ([a, b], [shared, c]) = (first_call.arguments, second_call.arguments) else {
+        return None;
+    };
+    let combines = match and_call.arguments {
+        [x, y] => (x, y) == (first, second) || (x, y) == (second, first),
+        _ => false,
+    };
+
+    let chained = is_comparison(first_op)
+        && is_comparison(second_op)
+        && b == shared
+        && call_low_level(and_call) == Some(LowLevel::And)
+        && combines
+        && !stmt_uses(following, *first)
+        && !stmt_uses(following, *second);
+
+    chained.then_some(ChainedComparison {
+        result: *result,
+        ops: [first_op, second_op],
+        operands: [*a, *b, *c],
+        comparisons: [stmt, second_stmt, and_stmt],
+        following,
+    })
+}
+
+/// Returns the low level a call performs, either directly or through a wrapper mono can replace.
+fn call_low_level(call: &roc_mono::ir::Call) -> Option<LowLevel> {
+    match call.call_type {
+        CallType::LowLevel { op, .. } => Some(op),
+        CallType::ByName { name, .. } => match LowLevelWrapperType::from_symbol(name.name()) {
+            LowLevelWrapperType::CanBeReplacedBy(op) => Some(op),
+            LowLevelWrapperType::NotALowLevelWrapper => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns whether `stmt` jumps to the join point `id` anywhere, such as a loop back to it.
+fn stmt_jumps_to(stmt: &Stmt, id: JoinPointId) -> bool {
+    match stmt {
+        Stmt::Let(_, _, _, following) | Stmt::Refcounting(_, following) => {
+            stmt_jumps_to(following, id)
+        }
+        Stmt::Dbg { remainder, .. } => stmt_jumps_to(remainder, id),
+        Stmt::Ret(_) | Stmt::Crash(..) => false,
+        Stmt::Switch {
+            branches,
+            default_branch,
+            ..
+        } => {
+            branches
+                .iter()
+                .any(|(_, _, branch)| stmt_jumps_to(branch, id))
+                || stmt_jumps_to(default_branch.1, id)
+        }
+        Stmt::Join {
+            body, remainder, ..
+        } => stmt_jumps_to(body, id) || stmt_jumps_to(remainder, id),
+        Stmt::Jump(target, _) => *target == id,
+        // Expect is not built by the dev backend, so there is no need to look closer.
+        Stmt::Expect { .. } => true,
+    }
+}
+
+/// Returns whether `symbol` is used anywhere in `stmt`.
+fn stmt_uses(stmt: &Stmt, symbol: Symbol) -> bool {
+    match stmt {
+        Stmt::Let(_, expr, _, following) => {
+            let mut used = std::vec::Vec::new();
+            Liveness::expr_uses(expr, &mut used);
+            used.contains(&symbol) || stmt_uses(following, symbol)
+        }
+        Stmt::Refcounting(modify, following) => {
+            modify.get_symbol() == symbol || stmt_uses(following, symbol)
+        }
+        Stmt::Ret(sym) | Stmt::Crash(sym, _) => *sym == symbol,
+        Stmt::Switch {
+            cond_symbol,
+            branches,
+            default_branch,
+            ..
+        } => {
+            *cond_symbol == symbol
+                || branches
+                    .iter()
+                    .any(|(_, _, branch)| stmt_uses(branch, symbol))
+                || stmt_uses(default_branch.1, symbol)
+        }
+        Stmt::Join {
+            body, remainder, ..
+        } => stmt_uses(body, symbol) || stmt_uses(remainder, symbol),
+        Stmt::Jump(_, symbols) => symbols.contains(&symbol),
+        Stmt::Dbg {
+            symbol: sym,
+            remainder,
+            ..
+        } => *sym == symbol || stmt_uses(remainder, symbol),
+        // Expect is not built by the dev backend, so there is no need to look closer.
+        Stmt::Expect { .. } => true,
+    }
+}
+
+/// Returns the branch a switch always takes, if it can only ever take one:
+/// either it has no cases, or its condition is zero-sized and so always 0.
+fn only_switch_branch<'a, 'b>(
+    interner: &STLayoutInterner<'a>,
+    cond_layout: InLayout<'a>,
+    branches: &'b [(u64, BranchInfo<'a>, Stmt<'a>)],
+    default_branch: &'b Stmt<'a>,
+) -> Option<&'b Stmt<'a>> {
+    match branches {
+        [] => Some(default_branch),
+        [(value, _, stmt)] if interner.stack_size(cond_layout) == 0 => {
+            Some(if *value == 0 { stmt } else { default_branch })
+        }
+        _ => None,
+    }
+}
+
+/// Returns the op and arguments of `let result = op a b in ret result`,
+/// for checked arithmetic whose result is returned straight away.
+fn checked_arithmetic_return<'a>(stmt: &Stmt<'a>) -> Option<(LowLevel, &'a [Symbol])> {
+    match stmt {
+        Stmt::Let(
+            result,
+            Expr::Call(roc_mono::ir::Call {
+                call_type:
+                    CallType::LowLevel {
+                        op: op @ (LowLevel::NumAddChecked | LowLevel::NumSubChecked),
+                        ..
+                    },
+                arguments,
+            }),
+            _,
+            Stmt::Ret(returned),
+        ) if returned == result => Some((*op, *arguments)),
+        _ => None,
+    }
+}
+
+/// Returns the proc, arguments and layouts of `let result = f args in ret result`,
+/// for a call whose result is returned straight away.
+#[allow(clippy::type_complexity)]
+fn tail_call<'a>(
+    stmt: &Stmt<'a>,
+) -> Option<(
+    LambdaName<'a>,
+    &'a [Symbol],
+    &'a [InLayout<'a>],
+    InLayout<'a>,
+)> {
+    match stmt {
+        Stmt::Let(
+            result,
+            Expr::Call(roc_mono::ir::Call {
+                call_type:
+                    CallType::ByName {
+                        name,
+                        arg_layouts,
+                        ret_layout,
+                        ..
+                    },
+                arguments,
+            }),
+            _,
+            Stmt::Ret(returned),
+        ) if returned == result => Some((*name, *arguments, *arg_layouts, *ret_layout)),
+        _ => None,
+    }
+}
+
+/// Whether `expr` calls a proc that returns the empty tag union `[]`.
+/// No value of that type exists, so such a call never returns, e.g. because it always crashes.
+fn diverging_call(expr: &Expr, layout: &InLayout) -> bool {
+    *layout == Layout::VOID
+        && matches!(
+            expr,
+            Expr::Call(roc_mono::ir::Call {
+                call_type: CallType::ByName { .. } | CallType::Foreign { .. },
+                ..
+            })
+        )
 }
 
 trait Backend<'a> {
@@ -312,6 +1070,21 @@ trait Backend<'a> {
     fn relocations_mut(&mut self) -> &mut Vec<'a, Relocation>;
     fn target(&self) -> Target;
 
+    /// The name of the calling convention the backend generates code for, e.g. "System V".
+    fn calling_convention(&self) -> &'static str;
+
+    /// A human-readable name for the backend, e.g. "x86_64 System V dev backend".
+    fn name(&self) -> String {
+        format!(
+            "{} {} dev backend",
+            self.target().architecture(),
+            self.calling_convention()
+        )
+    }
+
+    /// supports returns whether the backend can build programs using `feature`.
+    fn supports(&self, feature: Feature) -> bool;
+
     fn interner_mut(&mut self) -> &mut STLayoutInterner<'a> {
         self.module_interns_helpers_mut().1
     }
@@ -346,6 +1119,9 @@ trait Backend<'a> {
         &mut Vec<'a, CallerProc<'a>>,
     );
 
+    /// lambda_name_to_string returns the name of the specialization of `name` for these layouts.
+    /// Procs are defined under this name, and calls are relocated against it, so a call must pass
+    /// exactly the layouts the IR gives for the specialization it calls.
     fn lambda_name_to_string<'b, I>(
         &self,
         name: LambdaName,
@@ -360,7 +1136,7 @@ trait Backend<'a> {
 
         let symbol = name.name();
 
-        // NOTE: due to randomness, this will not be consistent between runs
+        // The hasher is not randomly seeded, so a definition and its calls always agree on the name.
         let mut state = roc_collections::all::BuildHasher::default().build_hasher();
         for a in arguments {
             a.hash(&mut state);
@@ -513,12 +1289,29 @@ trait Backend<'a> {
     /// It also passes basic procedure information to the builder for setup of the next function.
     fn reset(&mut self, name: String, is_self_recursive: SelfRecursive);
 
+    /// proc_lambda_name gets the name, niche included, of the procedure being built, if any.
+    fn proc_lambda_name(&self) -> Option<LambdaName<'a>>;
+
+    /// set_proc_lambda_name sets the name of the procedure being built, after it was reset.
+    fn set_proc_lambda_name(&mut self, lambda_name: LambdaName<'a>);
+
+    /// Makes the current proc allocate a real frame even if it could keep it in the red zone.
+    fn disable_red_zone(&mut self);
+
+    /// Sets aside the stack canary of the current proc, if the stack protector covers it.
+    /// Nothing else may have claimed stack yet, so the canary ends up at the top of the frame.
+    fn claim_canary_slot(&mut self, args: &[(InLayout<'a>, Symbol)], body: &Stmt<'a>);
+
     /// finalize does any setup and cleanup that should happen around the procedure.
     /// finalize does setup because things like stack size and jump locations are not know until the function is written.
     /// For example, this can store the frame pointer and setup stack space.
     /// finalize is run at the end of build_proc when all internal code is finalized.
     fn finalize(&mut self) -> (Vec<u8>, Vec<Relocation>);
 
+    /// The unwind info of the proc finalized last, for targets whose objects keep it in a table
+    /// of its own (e.g. `.xdata` on Windows), rather than deriving it from the code.
+    fn unwind_info(&self) -> Option<&[u8]>;
+
     // load_args is used to let the backend know what the args are.
     // The backend should track these args so it can use them as needed.
     fn load_args(&mut self, args: &'a [(InLayout<'a>, Symbol)], ret_layout: &InLayout<'a>);
@@ -530,6 +1323,10 @@ trait Backend<'a> {
     fn build_roc_setjmp(&mut self) -> &'a [u8];
     fn build_roc_longjmp(&mut self) -> &'a [u8];
     fn build_roc_panic(&mut self) -> (&'a [u8], Vec<'a, Relocation>);
+    /// build_roc_dbg builds a `roc_dbg` for tests and the repl, which have no host to print to.
+    fn build_roc_dbg(&mut self) -> &'a [u8];
+    /// build_entry_shim builds a `_start` that calls `main` and exits with its result.
+    fn build_entry_shim(&mut self, main: String) -> (&'a [u8], Vec<'a, Relocation>);
 
     /// build_proc creates a procedure and outputs it to the wrapped object writer.
     /// Returns the procedure bytes, its relocations, and the names of the refcounting functions it references.
@@ -548,13 +1345,23 @@ trait Backend<'a> {
         let body = self.env().arena.alloc(proc.body);
 
         self.reset(proc_name, proc.is_self_recursive);
+        self.set_proc_lambda_name(proc.name);
+        let name = proc.name.name().as_str(self.interns());
+        if self.env().options.no_red_zone.contains(name) {
+            self.disable_red_zone();
+        }
+        self.claim_canary_slot(proc.args, body);
         self.load_args(proc.args, &proc.ret_layout);
         for (layout, sym) in proc.args {
             self.set_layout_map(*sym, layout);
         }
-        self.scan_ast(body);
-        self.create_free_map();
-        self.build_stmt(layout_ids, body, &proc.ret_layout);
+        if let Some(msg) = runtime_error_message(body) {
+            // The whole body is a runtime error, there is nothing to scan or free.
+            self.build_runtime_error(msg);
+        } else {
+            self.scan_ast(body);
+            self.build_stmt(layout_ids, body, &proc.ret_layout);
+        }
 
         let mut helper_proc_names = bumpalo::vec![in self.env().arena];
         helper_proc_names.reserve(self.helper_proc_symbols().len());
@@ -587,8 +1394,96 @@ trait Backend<'a> {
         stmt: &Stmt<'a>,
         ret_layout: &InLayout<'a>,
     ) {
+        self.free_symbols_on_entry(stmt);
+        self.enter_stmt(stmt);
+
         match stmt {
             Stmt::Let(sym, expr, layout, following) => {
+                if let Some(checked) = checked_overflow_switch(stmt) {
+                    if self.build_checked_overflow_branch(
+                        checked.op,
+                        sym,
+                        checked.arguments,
+                        layout,
+                        checked.flag,
+                        checked.flag_index,
+                        checked.field_layouts,
+                    ) {
+                        self.set_layout_map(*sym, layout);
+                        self.set_layout_map(checked.flag, &checked.flag_layout);
+                        self.free_symbols(stmt);
+                        self.free_symbols(following);
+                        self.build_stmt(layout_ids, checked.switch, ret_layout);
+                        return;
+                    }
+                }
+
+                if let Some(chain) = chained_comparison(stmt) {
+                    let arg_layout = *self.layout_map().get(&chain.operands[1]).unwrap();
+                    if self.build_chained_comparison(
+                        &chain.result,
+                        chain.ops,
+                        &chain.operands,
+                        &arg_layout,
+                    ) {
+                        self.set_layout_map(chain.result, &Layout::BOOL);
+                        for comparison in chain.comparisons {
+                            self.free_symbols(comparison);
+                        }
+                        self.build_stmt(layout_ids, chain.following, ret_layout);
+                        return;
+                    }
+                }
+
+                if let Some((op, arguments)) = checked_arithmetic_return(stmt) {
+                    if layout == ret_layout
+                        && self.build_checked_arithmetic_return(op, arguments, layout)
+                    {
+                        self.free_symbols(stmt);
+                        self.free_symbols(following);
+                        return;
+                    }
+                }
+
+                if let Some((name, arguments, arg_layouts, call_ret_layout)) = tail_call(stmt) {
+                    let is_proc = !name.name().is_builtin()
+                        && matches!(
+                            LowLevelWrapperType::from_symbol(name.name()),
+                            LowLevelWrapperType::NotALowLevelWrapper
+                        );
+                    if is_proc && call_ret_layout == *ret_layout {
+                        let fn_name = self.lambda_name_to_string(
+                            name,
+                            arg_layouts.iter().copied(),
+                            None,
+                            call_ret_layout,
+                        );
+
+                        self.load_literal_symbols(arguments);
+                        self.pass_last_uses_in_registers(arguments);
+                        if self.build_tail_call(
+                            sym,
+                            fn_name,
+                            arguments,
+                            arg_layouts,
+                            &call_ret_layout,
+                        ) {
+                            self.free_symbols(stmt);
+                            self.free_symbols(following);
+                            return;
+                        }
+                    }
+                }
+
+                if diverging_call(expr, layout) {
+                    // Nothing after the call can run, so the rest of the body is not built.
+                    self.build_expr(sym, expr, layout);
+                    self.free_symbols(stmt);
+                    self.build_unreachable();
+                    self.mark_unreachable();
+                    return;
+                }
+
                 self.build_expr(sym, expr, layout);
                 self.set_layout_map(*sym, layout);
                 self.free_symbols(stmt);
@@ -599,6 +1494,17 @@ trait Backend<'a> {
                 self.return_symbol(sym, ret_layout);
                 self.free_symbols(stmt);
             }
+            Stmt::Dbg {
+                source_location,
+                source,
+                symbol,
+                remainder,
+                ..
+            } => {
+                self.build_dbg(source_location, source, *symbol);
+                self.free_symbols(stmt);
+                self.build_stmt(layout_ids, remainder, ret_layout)
+            }
             Stmt::Refcounting(ModifyRc::Free(symbol), following) => {
                 let dst = Symbol::DEV_TMP;
 
@@ -665,6 +1571,15 @@ trait Backend<'a> {
                 // this ret_layout inaccurate
                 ret_layout: _,
             } => {
+                // A switch that can only go one way needs no comparisons or jumps.
+                if let Some(taken) =
+                    only_switch_branch(self.interner(), *cond_layout, branches, default_branch.1)
+                {
+                    self.build_stmt(layout_ids, taken, ret_layout);
+                    self.free_symbols(stmt);
+                    return;
+                }
+
                 self.load_literal_symbols(&[*cond_symbol]);
                 self.build_switch(
                     layout_ids,
@@ -682,6 +1597,22 @@ trait Backend<'a> {
                 body,
                 remainder,
             } => {
+                if let Some(inc) = conditional_increment(stmt) {
+                    if self.build_conditional_increment(
+                        &inc.result,
+                        &inc.value,
+                        &inc.cond,
+                        &inc.layout,
+                    ) {
+                        self.set_layout_map(inc.result, &inc.layout);
+                        self.free_symbols(inc.switch);
+                        self.free_symbols(inc.add);
+                        self.build_stmt(layout_ids, inc.following, ret_layout);
+                        self.free_symbols(stmt);
+                        return;
+                    }
+                }
+
                 for param in parameters.iter() {
                     self.set_layout_map(param.symbol, &param.layout);
                 }
@@ -704,8 +1635,23 @@ trait Backend<'a> {
                 self.build_jump(id, args, arg_layouts.into_bump_slice(), ret_layout);
                 self.free_symbols(stmt);
             }
-            Stmt::Crash(msg, crash_tag) => self.roc_panic(*msg, *crash_tag),
-            x => todo!("the statement, {:?}", x),
+            Stmt::Crash(msg, crash_tag) => {
+                self.roc_panic(*msg, *crash_tag);
+
+                // roc_panic never returns. In development builds, trap if it somehow does.
+                if self.env().options.trap_unreachable {
+                    self.build_unreachable();
+                }
+                self.mark_unreachable();
+            }
+            x => {
+                debug_assert!(
+                    !self.supports(Feature::Expect),
+                    "{:?} is not actually supported",
+                    Feature::Expect
+                );
+                todo!("the statement, {:?}, in the {}", x, self.name())
+            }
         }
     }
 
@@ -741,6 +1687,120 @@ trait Backend<'a> {
         self.free_symbol(&Symbol::DEV_TMP2);
     }
 
+    /// build_dbg calls `roc_dbg` with the source location, the inspected value `msg`, and the source,
+    /// in the same order as the other backends. `msg` itself is left as it was.
+    fn build_dbg(&mut self, source_location: &'a str, source: &'a str, msg: Symbol) {
+        let location = self.debug_symbol("dbg_location");
+        let source_str = self.debug_symbol("dbg_source");
+        self.load_literal(&location, &Layout::STR, &Literal::Str(source_location));
+        self.load_literal(&source_str, &Layout::STR, &Literal::Str(source));
+        self.load_literal_symbols(&[msg]);
+
+        // roc_dbg takes each string by pointer, so each is copied to the stack.
+        let location_ptr = self.debug_symbol("dbg_location_ptr");
+        let msg_ptr = self.debug_symbol("dbg_msg_ptr");
+        let source_ptr = self.debug_symbol("dbg_source_ptr");
+        self.build_alloca(location_ptr, Some(location), Layout::STR);
+        self.build_alloca(msg_ptr, Some(msg), Layout::STR);
+        self.build_alloca(source_ptr, Some(source_str), Layout::STR);
+
+        let ignored = self.debug_symbol("ignored");
+        self.build_fn_call(
+            &ignored,
+            String::from("roc_dbg"),
+            &[location_ptr, msg_ptr, source_ptr],
+            &[Layout::U64, Layout::U64, Layout::U64],
+            &Layout::UNIT,
+        );
+
+        for sym in [
+            location,
+            source_str,
+            location_ptr,
+            msg_ptr,
+            source_ptr,
+            ignored,
+        ] {
+            self.free_symbol(&sym);
+        }
+    }
+
+    /// build_runtime_error compiles a body that is entirely a runtime error.
+    /// It panics with the given message and marks the rest of the procedure unreachable.
+    fn build_runtime_error(&mut self, msg: &'a str) {
+        let msg_symbol = self.debug_symbol("runtime_error_message");
+
+        self.load_literal(&msg_symbol, &Layout::STR, &Literal::Str(msg));
+        self.roc_panic(msg_symbol, CrashTag::Roc);
+        self.free_symbol(&msg_symbol);
+
+        self.build_unreachable();
+    }
+
+    /// build_unreachable marks the current location as impossible to reach.
+    /// Executing it will trap.
+    fn build_unreachable(&mut self);
+
+    /// mark_unreachable records that control never falls through to the code emitted next.
+    /// That code is dead until the next jump target, so backends may skip emitting it.
+    fn mark_unreachable(&mut self) {}
+
+    /// build_conditional_increment builds `dst = value + (if cond then 1 else 0)` for the Bool `cond`
+    /// without branching. It returns false if the backend can't do that for this layout,
+    /// in which case nothing is built.
+    fn build_conditional_increment(
+        &mut self,
+        _dst: &Symbol,
+        _value: &Symbol,
+        _cond: &Symbol,
+        _layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
+    /// build_chained_comparison builds `dst = a op0 b && b op1 c` for `operands` `[a, b, c]`,
+    /// loading the shared `b` only once. It returns false if the backend can't do that for
+    /// this layout, in which case nothing is built.
+    fn build_chained_comparison(
+        &mut self,
+        _dst: &Symbol,
+        _ops: [LowLevel; 2],
+        _operands: &[Symbol; 3],
+        _arg_layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
+    /// build_checked_overflow_branch builds the checked arithmetic `op` into the struct `result`,
+    /// leaving its overflow in the flags register for the switch on `flag` that directly follows.
+    /// It returns false if the backend can't do that for this layout, in which case nothing is built.
+    #[allow(clippy::too_many_arguments)]
+    fn build_checked_overflow_branch(
+        &mut self,
+        _op: LowLevel,
+        _result: &Symbol,
+        _args: &'a [Symbol],
+        _result_layout: &InLayout<'a>,
+        _flag: Symbol,
+        _flag_index: u64,
+        _field_layouts: &'a [InLayout<'a>],
+    ) -> bool {
+        false
+    }
+
+    /// build_checked_arithmetic_return builds the checked arithmetic `op` and returns its result
+    /// struct straight from the registers it was computed in.
+    /// It returns false if the backend can't do that for this layout, in which case nothing
+    /// is built.
+    fn build_checked_arithmetic_return(
+        &mut self,
+        _op: LowLevel,
+        _args: &'a [Symbol],
+        _result_layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
     // build_switch generates a instructions for a switch statement.
     fn build_switch(
         &mut self,
@@ -825,7 +1885,19 @@ trait Backend<'a> {
 
                         // Now that the arguments are needed, load them if they are literals.
                         self.load_literal_symbols(arguments);
-                        self.build_fn_call(sym, fn_name, arguments, arg_layouts, ret_layout)
+                        self.pass_last_uses_in_registers(arguments);
+                        // A diverging call has no result to move out of the return registers.
+                        if self.unused_results().contains(sym) || *ret_layout == Layout::VOID {
+                            self.build_fn_call_for_effect(
+                                sym,
+                                fn_name,
+                                arguments,
+                                arg_layouts,
+                                ret_layout,
+                            )
+                        } else {
+                            self.build_fn_call(sym, fn_name, arguments, arg_layouts, ret_layout)
+                        }
                     }
 
                     CallType::ByPointer { .. } => {
@@ -876,13 +1948,31 @@ trait Backend<'a> {
                         }
 
                         self.load_literal_symbols(arguments);
-                        self.build_fn_call(
-                            sym,
-                            foreign_symbol.as_str().to_string(),
+                        let (fn_args, fn_arg_layouts) = self.load_foreign_fn_pointers(
+                            foreign_symbol.as_str(),
                             arguments,
                             arg_layouts.into_bump_slice(),
-                            ret_layout,
                         );
+                        if *ret_layout == Layout::VOID {
+                            self.build_fn_call_for_effect(
+                                sym,
+                                foreign_symbol.as_str().to_string(),
+                                fn_args,
+                                fn_arg_layouts,
+                                ret_layout,
+                            );
+                        } else {
+                            self.build_fn_call(
+                                sym,
+                                foreign_symbol.as_str().to_string(),
+                                fn_args,
+                                fn_arg_layouts,
+                                ret_layout,
+                            );
+                        }
+                        for fn_ptr in fn_args.iter().filter(|arg| !arguments.contains(arg)) {
+                            self.free_symbol(fn_ptr);
+                        }
                     }
                 }
             }
@@ -952,6 +2042,11 @@ trait Backend<'a> {
             Expr::NullPointer => {
                 self.load_literal_i64(sym, 0);
             }
+            Expr::FunctionPointer { lambda_name }
+                if self.proc_lambda_name() == Some(*lambda_name) =>
+            {
+                self.build_self_pointer(sym);
+            }
             Expr::FunctionPointer { .. } => todo_lambda_erasure!(),
             Expr::ErasedMake { .. } => todo_lambda_erasure!(),
             Expr::ErasedLoad { .. } => todo_lambda_erasure!(),
@@ -961,6 +2056,10 @@ trait Backend<'a> {
                 // Expand the Refcounting statement into more detailed IR with a function call
                 // If this layout requires a new RC proc, we get enough info to create a linker symbol
                 // for it. Here we don't create linker symbols at this time, but in Wasm backend, we do.
+                //
+                // The reset proc checks that the allocation is unique. If it is, its children are
+                // decremented and it returns the allocation to be reused. If it is shared, it is
+                // decremented instead and the proc returns null, so the `Reuse` allocates fresh.
                 let (new_expr, new_specializations) = {
                     let (module_id, layout_interner, interns, rc_proc_gen, _) =
                         self.module_interns_helpers_mut();
@@ -973,7 +2072,7 @@ trait Backend<'a> {
                     self.helper_proc_symbols_mut().push(spec);
                 }
 
-                self.build_expr(sym, &new_expr, &Layout::BOOL)
+                self.build_expr(sym, &new_expr, &layout)
             }
             Expr::ResetRef { symbol, .. } => {
                 let layout = *self.layout_map().get(symbol).unwrap();
@@ -993,7 +2092,7 @@ trait Backend<'a> {
                     self.helper_proc_symbols_mut().push(spec);
                 }
 
-                self.build_expr(sym, &new_expr, &Layout::BOOL)
+                self.build_expr(sym, &new_expr, &layout)
             }
             Expr::Alloca {
                 initializer,
@@ -1004,6 +2103,77 @@ trait Backend<'a> {
         }
     }
 
+    /// Foreign functions take functions as plain function pointers, so a closure argument is
+    /// replaced by the address of its proc. That only works for a closure that is always the same
+    /// function and has no captures: a bare function pointer has nowhere to keep either the
+    /// captured values or which of several functions it is.
+    fn load_foreign_fn_pointers(
+        &mut self,
+        foreign_name: &str,
+        arguments: &'a [Symbol],
+        arg_layouts: &'a [InLayout<'a>],
+    ) -> (&'a [Symbol], &'a [InLayout<'a>]) {
+        let has_closure_arg = arg_layouts
+            .iter()
+            .any(|layout| matches!(self.interner().get_repr(*layout), LayoutRepr::LambdaSet(_)));
+        if !has_closure_arg {
+            return (arguments, arg_layouts);
+        }
+
+        let arena = self.env().arena;
+        let mut fn_args = bumpalo::vec![in arena];
+        let mut fn_arg_layouts = bumpalo::vec![in arena];
+        for (arg, layout) in arguments.iter().zip(arg_layouts) {
+            let lambda_set = match self.interner().get_repr(*layout) {
+                LayoutRepr::LambdaSet(lambda_set) => lambda_set,
+                _ => {
+                    fn_args.push(*arg);
+                    fn_arg_layouts.push(*layout);
+                    continue;
+                }
+            };
+
+            if lambda_set.len() > 1 {
+                user_error!(
+                    "The closure {:?} passed to the foreign function {} can be one of {} functions, but a function pointer can only point to one.",
+                    arg,
+                    foreign_name,
+                    lambda_set.len()
+                );
+            }
+            if lambda_set.is_represented(self.interner()).is_some() {
+                user_error!(
+                    "The closure {:?} passed to the foreign function {} captures values, but a function pointer has nowhere to keep them.",
+                    arg,
+                    foreign_name
+                );
+            }
+            let Some(lambda_name) = lambda_set.iter_set().next() else {
+                internal_error!("the closure {:?} has an empty lambda set", arg);
+            };
+            let fn_name = self.lambda_name_to_string(
+                lambda_name,
+                lambda_set.args().iter().copied(),
+                None,
+                lambda_set.ret(),
+            );
+            let fn_ptr = self.debug_symbol("foreign_fn_ptr");
+            self.build_fn_pointer(&fn_ptr, fn_name);
+            fn_args.push(fn_ptr);
+            fn_arg_layouts.push(Layout::OPAQUE_PTR);
+        }
+
+        (fn_args.into_bump_slice(), fn_arg_layouts.into_bump_slice())
+    }
+
+    /// The width of an integer layout that `check_binary_layouts` has already let through.
+    fn checked_int_width(&mut self, layout: &InLayout<'a>) -> IntWidth {
+        match self.interner().get_repr(*layout) {
+            LayoutRepr::Builtin(Builtin::Int(int_width)) => int_width,
+            repr => internal_error!("{:?} is not an integer layout", repr),
+        }
+    }
+
     /// build_run_low_level builds the low level operation and outputs to the specified symbol.
     /// The builder must keep track of the symbol because it may be referred to later.
     fn build_run_low_level(
@@ -1014,6 +2184,40 @@ trait Backend<'a> {
         arg_layouts: &[InLayout<'a>],
         ret_layout: &InLayout<'a>,
     ) {
+        if let Err(mismatch) =
+            check_binary_layouts(self.interner(), *lowlevel, arg_layouts, *ret_layout)
+        {
+            // No code fits operands of the wrong kind, so there is nothing to build.
+            internal_error!("{}", mismatch);
+        }
+
+        let wraps = self.env().options.overflow_mode == OverflowMode::Wrap;
+        let built_from_literal = match lowlevel {
+            LowLevel::NumAddWrap => {
+                self.build_num_add_wrap_literal(sym, &args[0], &args[1], ret_layout)
+            }
+            LowLevel::NumAdd if wraps => {
+                self.build_num_add_wrap_literal(sym, &args[0], &args[1], ret_layout)
+            }
+            LowLevel::NumSubWrap => {
+                self.build_num_sub_wrap_literal(sym, &args[0], &args[1], ret_layout)
+            }
+            LowLevel::NumSub if wraps => {
+                self.build_num_sub_wrap_literal(sym, &args[0], &args[1], ret_layout)
+            }
+            LowLevel::NumMulWrap => {
+                self.build_num_mul_wrap_literal(sym, &args[0], &args[1], ret_layout)
+            }
+            LowLevel::NumMul if wraps => {
+                self.build_num_mul_wrap_literal(sym, &args[0], &args[1], ret_layout)
+            }
+            LowLevel::NumLt => self.build_num_lt_literal(sym, &args[0], &args[1], &arg_layouts[0]),
+            _ => false,
+        };
+        if built_from_literal {
+            return;
+        }
+
         // Now that the arguments are needed, load them if they are literals.
         self.load_literal_symbols(args);
         match lowlevel {
@@ -1242,72 +2446,30 @@ trait Backend<'a> {
                 self.build_num_sub_checked(sym, &args[0], &args[1], &arg_layouts[0], ret_layout)
             }
             LowLevel::NumBitwiseAnd => {
-                if let LayoutRepr::Builtin(Builtin::Int(int_width)) =
-                    self.interner().get_repr(*ret_layout)
-                {
-                    self.build_int_bitwise_and(sym, &args[0], &args[1], int_width)
-                } else {
-                    internal_error!("bitwise and on a non-integer")
-                }
+                let int_width = self.checked_int_width(ret_layout);
+                self.build_int_bitwise_and(sym, &args[0], &args[1], int_width)
             }
             LowLevel::NumBitwiseOr => {
-                if let LayoutRepr::Builtin(Builtin::Int(int_width)) =
-                    self.interner().get_repr(*ret_layout)
-                {
-                    self.build_int_bitwise_or(sym, &args[0], &args[1], int_width)
-                } else {
-                    internal_error!("bitwise or on a non-integer")
-                }
+                let int_width = self.checked_int_width(ret_layout);
+                self.build_int_bitwise_or(sym, &args[0], &args[1], int_width)
             }
             LowLevel::NumBitwiseXor => {
-                if let LayoutRepr::Builtin(Builtin::Int(int_width)) =
-                    self.interner().get_repr(*ret_layout)
-                {
-                    self.build_int_bitwise_xor(sym, &args[0], &args[1], int_width)
-                } else {
-                    internal_error!("bitwise xor on a non-integer")
-                }
-            }
-            LowLevel::And => {
-                if let LayoutRepr::Builtin(Builtin::Bool) = self.interner().get_repr(*ret_layout) {
-                    self.build_int_bitwise_and(sym, &args[0], &args[1], IntWidth::U8)
-                } else {
-                    internal_error!("bitwise and on a non-integer")
-                }
-            }
-            LowLevel::Or => {
-                if let LayoutRepr::Builtin(Builtin::Bool) = self.interner().get_repr(*ret_layout) {
-                    self.build_int_bitwise_or(sym, &args[0], &args[1], IntWidth::U8)
-                } else {
-                    internal_error!("bitwise or on a non-integer")
-                }
+                let int_width = self.checked_int_width(ret_layout);
+                self.build_int_bitwise_xor(sym, &args[0], &args[1], int_width)
             }
+            LowLevel::And => self.build_int_bitwise_and(sym, &args[0], &args[1], IntWidth::U8),
+            LowLevel::Or => self.build_int_bitwise_or(sym, &args[0], &args[1], IntWidth::U8),
             LowLevel::NumShiftLeftBy => {
-                if let LayoutRepr::Builtin(Builtin::Int(int_width)) =
-                    self.interner().get_repr(*ret_layout)
-                {
-                    self.build_int_shift_left(sym, &args[0], &args[1], int_width)
-                } else {
-                    internal_error!("shift left on a non-integer")
-                }
+                let int_width = self.checked_int_width(ret_layout);
+                self.build_int_shift_left(sym, &args[0], &args[1], int_width)
             }
             LowLevel::NumShiftRightBy => {
-                if let LayoutRepr::Builtin(Builtin::Int(int_width)) =
-                    self.interner().get_repr(*ret_layout)
-                {
-                    self.build_int_shift_right(sym, &args[0], &args[1], int_width)
-                } else {
-                    internal_error!("shift right on a non-integer")
-                }
+                let int_width = self.checked_int_width(ret_layout);
+                self.build_int_shift_right(sym, &args[0], &args[1], int_width)
             }
             LowLevel::NumShiftRightZfBy => {
-                if let LayoutRepr::Builtin(Builtin::Int(int_width)) =
-                    self.interner().get_repr(*ret_layout)
-                {
-                    self.build_int_shift_right_zero_fill(sym, &args[0], &args[1], int_width)
-                } else {
-                    internal_error!("shift right zero-fill on a non-integer")
-                }
+                let int_width = self.checked_int_width(ret_layout);
+                self.build_int_shift_right_zero_fill(sym, &args[0], &args[1], int_width)
             }
             LowLevel::Eq => {
                 debug_assert_eq!(2, args.len(), "Eq: expected to have exactly two argument");
@@ -1889,14 +3051,10 @@ trait Backend<'a> {
                 self.build_fn_call(sym, intrinsic, args, arg_layouts, ret_layout);
             }
             LowLevel::NumCountLeadingZeroBits => {
-                let int_width = arg_layouts[0].try_int_width().unwrap();
-                let intrinsic = bitcode::NUM_COUNT_LEADING_ZERO_BITS[int_width].to_string();
-                self.build_fn_call(sym, intrinsic, args, arg_layouts, ret_layout);
+                self.build_num_count_leading_zero_bits(sym, &args[0], &arg_layouts[0], ret_layout)
             }
             LowLevel::NumCountTrailingZeroBits => {
-                let int_width = arg_layouts[0].try_int_width().unwrap();
-                let intrinsic = bitcode::NUM_COUNT_TRAILING_ZERO_BITS[int_width].to_string();
-                self.build_fn_call(sym, intrinsic, args, arg_layouts, ret_layout);
+                self.build_num_count_trailing_zero_bits(sym, &args[0], &arg_layouts[0], ret_layout)
             }
             LowLevel::NumCountOneBits => {
                 let int_width = arg_layouts[0].try_int_width().unwrap();
@@ -2216,7 +3374,7 @@ trait Backend<'a> {
                 self.build_fn_call(sym, intrinsic, args, arg_layouts, ret_layout)
             }
 
-            x => todo!("low level, {:?}", x),
+            x => todo!("low level, {:?}, in the {}", x, self.name()),
         }
     }
 
@@ -2317,9 +3475,38 @@ trait Backend<'a> {
         ret_layout: &InLayout<'a>,
     );
 
+    /// build_fn_call_for_effect creates a call site for a function whose result is never used.
+    /// It is the same as build_fn_call, except the returned value is left where the callee put it.
+    fn build_fn_call_for_effect(
+        &mut self,
+        dst: &Symbol,
+        fn_name: String,
+        args: &[Symbol],
+        arg_layouts: &[InLayout<'a>],
+        ret_layout: &InLayout<'a>,
+    );
+
+    /// build_tail_call calls a function whose result is returned straight away, by tearing down
+    /// the frame and jumping to it, so it returns to our caller directly.
+    /// It returns false if the backend can't do that for this call, in which case nothing is built.
+    fn build_tail_call(
+        &mut self,
+        _dst: &Symbol,
+        _fn_name: String,
+        _args: &[Symbol],
+        _arg_layouts: &[InLayout<'a>],
+        _ret_layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
     fn build_fn_pointer(&mut self, dst: &Symbol, fn_name: String);
     fn build_data_pointer(&mut self, dst: &Symbol, data_name: String);
 
+    /// build_self_pointer loads the address of the procedure being built into `dst`.
+    /// Its relocation refers to the procedure's own symbol, which is defined in this module.
+    fn build_self_pointer(&mut self, dst: &Symbol);
+
     /// Move a returned value into `dst`
     fn move_return_value(&mut self, dst: &Symbol, ret_layout: &InLayout<'a>);
 
@@ -2385,6 +3572,58 @@ trait Backend<'a> {
         layout: &InLayout<'a>,
     );
 
+    /// build_num_mul_wrap_literal stores the wrapping `src1 * src2` into dst
+    /// when one of the sources is a lazy literal the backend can multiply by without loading it.
+    /// It returns false if it can't, in which case nothing is built.
+    fn build_num_mul_wrap_literal(
+        &mut self,
+        _dst: &Symbol,
+        _src1: &Symbol,
+        _src2: &Symbol,
+        _layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
+    /// build_num_add_wrap_literal stores the wrapping `src1 + src2` into dst
+    /// when one of the sources is a lazy literal the backend can add without loading it.
+    /// It returns false if it can't, in which case nothing is built.
+    fn build_num_add_wrap_literal(
+        &mut self,
+        _dst: &Symbol,
+        _src1: &Symbol,
+        _src2: &Symbol,
+        _layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
+    /// build_num_sub_wrap_literal stores the wrapping `src1 - src2` into dst
+    /// when src2 is a lazy literal the backend can subtract without loading it.
+    /// It returns false if it can't, in which case nothing is built.
+    fn build_num_sub_wrap_literal(
+        &mut self,
+        _dst: &Symbol,
+        _src1: &Symbol,
+        _src2: &Symbol,
+        _layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
+    /// build_num_lt_literal stores `src1 < src2` into dst when src2 is a lazy literal
+    /// the backend can compare against without loading it.
+    /// It returns false if it can't, in which case nothing is built.
+    fn build_num_lt_literal(
+        &mut self,
+        _dst: &Symbol,
+        _src1: &Symbol,
+        _src2: &Symbol,
+        _arg_layout: &InLayout<'a>,
+    ) -> bool {
+        false
+    }
+
     fn build_num_mul_saturated(
         &mut self,
         dst: Symbol,
@@ -2419,6 +3658,24 @@ trait Backend<'a> {
     /// build_num_neg stores the negated value of src into dst.
     fn build_num_neg(&mut self, dst: &Symbol, src: &Symbol, layout: &InLayout<'a>);
 
+    /// build_num_count_leading_zero_bits stores the number of zero bits above the highest set bit of src into dst.
+    fn build_num_count_leading_zero_bits(
+        &mut self,
+        dst: &Symbol,
+        src: &Symbol,
+        layout: &InLayout<'a>,
+        ret_layout: &InLayout<'a>,
+    );
+
+    /// build_num_count_trailing_zero_bits stores the number of zero bits below the lowest set bit of src into dst.
+    fn build_num_count_trailing_zero_bits(
+        &mut self,
+        dst: &Symbol,
+        src: &Symbol,
+        layout: &InLayout<'a>,
+        ret_layout: &InLayout<'a>,
+    );
+
     /// build_num_sub stores the `src1 - src2` difference into dst.
     fn build_num_sub(&mut self, dst: &Symbol, src1: &Symbol, src2: &Symbol, layout: &InLayout<'a>);
 
@@ -2812,12 +4069,19 @@ trait Backend<'a> {
         }
     }
 
+    /// free_symbols_on_entry will free all symbols that are already dead when the given statement starts.
+    /// Only the branches of a switch have any, for the symbols that are just used by other branches.
+    fn free_symbols_on_entry(&mut self, stmt: &Stmt<'a>) {
+        if let Some(syms) = self.entry_free_map().remove(&(stmt as *const Stmt<'a>)) {
+            for sym in syms {
+                self.free_symbol(&sym);
+            }
+        }
+    }
+
     /// free_symbol frees any registers or stack space used to hold a symbol.
     fn free_symbol(&mut self, sym: &Symbol);
 
-    /// last_seen_map gets the map from symbol to when it is last seen in the function.
-    fn last_seen_map(&mut self) -> &mut MutMap<Symbol, *const Stmt<'a>>;
-
     /// set_layout_map sets the layout for a specific symbol.
     fn set_layout_map(&mut self, sym: Symbol, layout: &InLayout<'a>) {
         if let Some(old_layout) = self.layout_map().insert(sym, *layout) {
@@ -2837,27 +4101,117 @@ trait Backend<'a> {
     /// layout_map gets the map from symbol to layout.
     fn layout_map(&mut self) -> &mut MutMap<Symbol, InLayout<'a>>;
 
-    fn create_free_map(&mut self) {
-        let mut free_map = MutMap::default();
-        let arena = self.env().arena;
-        for (sym, stmt) in self.last_seen_map() {
-            let vals = free_map
-                .entry(*stmt)
-                .or_insert_with(|| bumpalo::vec![in arena]);
-            vals.push(*sym);
-        }
-        self.set_free_map(free_map);
-    }
-
     /// free_map gets the map statement to the symbols that are free after they run.
     fn free_map(&mut self) -> &mut MutMap<*const Stmt<'a>, Vec<'a, Symbol>>;
 
     /// set_free_map sets the free map to the given map.
     fn set_free_map(&mut self, map: MutMap<*const Stmt<'a>, Vec<'a, Symbol>>);
 
-    /// scan_ast runs through the ast and fill the last seen map.
-    /// This must iterate through the ast in the same way that build_stmt does. i.e. then before else.
+    /// entry_free_map gets the map from statement to the symbols that are free before they run.
+    fn entry_free_map(&mut self) -> &mut MutMap<*const Stmt<'a>, Vec<'a, Symbol>>;
+
+    /// set_entry_free_map sets the entry free map to the given map.
+    fn set_entry_free_map(&mut self, map: MutMap<*const Stmt<'a>, Vec<'a, Symbol>>);
+
+    /// unused_results gets the symbols bound to a value that is never used.
+    fn unused_results(&mut self) -> &mut MutSet<Symbol>;
+
+    /// set_unused_results sets the unused results to the given set.
+    fn set_unused_results(&mut self, set: MutSet<Symbol>);
+
+    /// set_use_positions sets where the statements and symbol uses of the current proc are.
+    fn set_use_positions(&mut self, positions: UsePositions<'a>);
+
+    /// enter_stmt records that `stmt` is the statement being built, so later uses can be told apart.
+    fn enter_stmt(&mut self, stmt: &Stmt<'a>);
+
+    /// pass_last_uses_in_registers records that the next call is the only call of the statement
+    /// being built, so the `args` that the statement uses for the last time are dead after it.
+    fn pass_last_uses_in_registers(&mut self, _args: &[Symbol]) {}
+
+    /// scan_ast runs a liveness analysis over the ast and fills the free maps.
     fn scan_ast(&mut self, stmt: &'a Stmt<'a>) {
-        *self.last_seen_map() = LastSeenMap::scan_ast(stmt);
+        let liveness = Liveness::scan_ast(self.env().arena, stmt);
+        self.set_free_map(liveness.free_map);
+        self.set_entry_free_map(liveness.entry_free_map);
+        self.set_unused_results(liveness.unused_results);
+
+        if self.env().options.spill_heuristic == SpillHeuristic::FurthestNextUse {
+            self.set_use_positions(UsePositions::scan(stmt));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_op_on_the_wrong_kind_of_number_is_a_layout_mismatch() {
+        let interner = STLayoutInterner::with_capacity(4, Target::LinuxX64);
+
+        assert_eq!(
+            check_binary_layouts(
+                &interner,
+                LowLevel::NumBitwiseAnd,
+                &[Layout::I64, Layout::F64],
+                Layout::I64
+            ),
+            Err(LayoutMismatch {
+                lowlevel: LowLevel::NumBitwiseAnd,
+                expected: "integer",
+                actual: LayoutRepr::F64,
+            })
+        );
+        assert_eq!(
+            check_binary_layouts(
+                &interner,
+                LowLevel::NumDivFrac,
+                &[Layout::I64, Layout::I64],
+                Layout::I64
+            ),
+            Err(LayoutMismatch {
+                lowlevel: LowLevel::NumDivFrac,
+                expected: "fraction",
+                actual: LayoutRepr::I64,
+            })
+        );
+        assert_eq!(
+            check_binary_layouts(
+                &interner,
+                LowLevel::NumAdd,
+                &[Layout::F64, Layout::F64],
+                Layout::I64
+            ),
+            Err(LayoutMismatch {
+                lowlevel: LowLevel::NumAdd,
+                expected: "number",
+                actual: LayoutRepr::I64,
+            })
+        );
+    }
+
+    #[test]
+    fn binary_op_on_the_right_kind_of_number_fits() {
+        let interner = STLayoutInterner::with_capacity(4, Target::LinuxX64);
+
+        assert_eq!(
+            check_binary_layouts(
+                &interner,
+                LowLevel::NumAdd,
+                &[Layout::DEC, Layout::DEC],
+                Layout::DEC
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_binary_layouts(
+                &interner,
+                LowLevel::NumLt,
+                &[Layout::F64, Layout::F64],
+                Layout::BOOL
+            ),
+            Ok(())
+        );
     }
 }
@@ -1,14 +1,14 @@
-use crate::generic64::{aarch64, new_backend_64bit, x86_64};
-use crate::{AssemblyBackendMode, Backend, Env, Relocation};
+use crate::generic64::{aarch64, new_backend_64bit, supports_feature, x86_64};
+use crate::{AssemblyBackendMode, Backend, Env, Feature, Relocation};
 use bumpalo::collections::Vec;
 use object::write::{self, SectionId, SymbolId};
 use object::write::{Object, StandardSection, StandardSegment, Symbol, SymbolSection};
 use object::{
-    Architecture, BinaryFormat, Endianness, RelocationEncoding, RelocationKind, SectionKind,
-    SymbolFlags, SymbolKind, SymbolScope,
+    Architecture, BinaryFormat, Endianness, RelocationEncoding, RelocationKind, SectionFlags,
+    SectionKind, SymbolFlags, SymbolKind, SymbolScope,
 };
 use roc_collections::all::MutMap;
-use roc_error_macros::internal_error;
+use roc_error_macros::{internal_error, user_error};
 use roc_module::symbol;
 use roc_module::symbol::Interns;
 use roc_mono::ir::{Call, CallSpecId, Expr, UpdateModeId};
@@ -16,9 +16,12 @@ use roc_mono::ir::{Proc, ProcLayout, Stmt};
 use roc_mono::layout::{LambdaName, Layout, LayoutIds, LayoutInterner, STLayoutInterner};
 use roc_target::Target;
 
-// This is used by some code below which is currently commented out.
-// See that code for more details!
-// const VERSION: &str = env!("CARGO_PKG_VERSION");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The producer recorded in the objects we write, so tools can tell which compiler made them.
+fn producer() -> String {
+    format!("roc dev backend version {VERSION}")
+}
 
 /// build_module is the high level builder/delegator.
 /// It takes the request to build a module and output the object file for the module.
@@ -44,6 +47,18 @@ pub fn build_module<'a, 'r>(
     module_object
 }
 
+/// supports returns whether build_module can build programs using `feature` for `target`.
+/// Drivers can use it to send programs the dev backend can't build to another backend instead.
+pub fn supports(target: Target, feature: Feature) -> bool {
+    let target_enabled = match target {
+        Target::LinuxX64 | Target::MacX64 | Target::WinX64 => cfg!(feature = "target-x86_64"),
+        Target::LinuxArm64 | Target::MacArm64 => cfg!(feature = "target-aarch64"),
+        _ => false,
+    };
+
+    target_enabled && supports_feature(feature)
+}
+
 fn build_module_help<'a, 'r>(
     env: &'r Env<'a>,
     interns: &'r mut Interns,
@@ -59,16 +74,11 @@ fn build_module_help<'a, 'r>(
                 x86_64::X86_64Assembler,
                 x86_64::X86_64SystemV,
             >(env, target, interns, layout_interner);
-            // Newer version of `ld` require `.note.GNU-stack` for security reasons.
-            // It specifies that we will not execute code stored on the stack.
-            let mut object =
-                Object::new(BinaryFormat::Elf, Architecture::X86_64, Endianness::Little);
-            object.add_section(
-                vec![],
-                b".note.GNU-stack".to_vec(),
-                SectionKind::Elf(object::elf::SHT_PROGBITS),
-            );
-            build_object(procedures, backend, object)
+            build_object(
+                procedures,
+                backend,
+                Object::new(BinaryFormat::Elf, Architecture::X86_64, Endianness::Little),
+            )
         }
         Target::MacX64 if cfg!(feature = "target-x86_64") => {
             let backend = new_backend_64bit::<
@@ -134,6 +144,35 @@ fn build_module_help<'a, 'r>(
     }
 }
 
+/// Adds a `.comment` section naming `producer`, like other ELF compilers do.
+/// Mach-O and COFF have no such section, so nothing is added for them
+/// (see https://github.com/roc-lang/roc/pull/1323).
+fn add_producer_comment(output: &mut Object, producer: &str) {
+    if output.format() != BinaryFormat::Elf {
+        return;
+    }
+
+    // The section is a list of null terminated strings, starting with an empty one.
+    let comment = output.add_section(vec![], b".comment".to_vec(), SectionKind::OtherString);
+    output.append_section_data(comment, format!("\0{producer}\0").as_bytes(), 1);
+}
+
+/// Adds an empty `.note.GNU-stack` section to ELF objects.
+/// Its flags say whether the code needs an executable stack, which ours never does.
+/// Without it, linkers assume an executable stack, and newer versions of `ld` warn about it.
+fn add_gnu_stack_note(output: &mut Object) {
+    if output.format() != BinaryFormat::Elf {
+        return;
+    }
+
+    let note = output.add_section(
+        vec![],
+        b".note.GNU-stack".to_vec(),
+        SectionKind::Elf(object::elf::SHT_PROGBITS),
+    );
+    output.section_mut(note).flags = SectionFlags::Elf { sh_flags: 0 };
+}
+
 fn define_setlongjmp_buffer(output: &mut Object) -> SymbolId {
     let bss_section = output.section_id(StandardSection::Data);
 
@@ -238,7 +277,11 @@ fn generate_roc_panic<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object)
 
     for r in relocs {
         let relocation = match r {
-            Relocation::LinkedData { offset, name } => {
+            Relocation::LinkedData {
+                offset,
+                name,
+                addend,
+            } => {
                 if let Some(sym_id) = output.symbol_id(name.as_bytes()) {
                     if cfg!(all(target_arch = "aarch64", target_os = "linux")) {
                         //     700: 90000001        adrp    x1, 0x0 <std.builtin.default_panic>
@@ -249,7 +292,7 @@ fn generate_roc_panic<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object)
                             kind: RelocationKind::Elf(object::elf::R_AARCH64_ADR_PREL_PG_HI21),
                             encoding: RelocationEncoding::Generic,
                             symbol: sym_id,
-                            addend: 0,
+                            addend,
                         };
 
                         output.add_relocation(text_section, relocation).unwrap();
@@ -262,7 +305,7 @@ fn generate_roc_panic<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object)
                             kind: RelocationKind::Elf(object::elf::R_AARCH64_ADD_ABS_LO12_NC),
                             encoding: RelocationEncoding::Generic,
                             symbol: sym_id,
-                            addend: 0,
+                            addend,
                         }
                     } else if cfg!(all(target_arch = "aarch64", target_os = "macos")) {
                         //     4dc: 90000001        adrp    x1, 0x0 <ltmp0>
@@ -276,7 +319,7 @@ fn generate_roc_panic<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object)
                             },
                             encoding: RelocationEncoding::Generic,
                             symbol: sym_id,
-                            addend: 0,
+                            addend,
                         };
 
                         output.add_relocation(text_section, relocation).unwrap();
@@ -292,17 +335,10 @@ fn generate_roc_panic<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object)
                             },
                             encoding: RelocationEncoding::Generic,
                             symbol: sym_id,
-                            addend: 0,
+                            addend,
                         }
                     } else {
-                        write::Relocation {
-                            offset: offset + proc_offset,
-                            size: 32,
-                            kind: RelocationKind::GotRelative,
-                            encoding: RelocationEncoding::Generic,
-                            symbol: sym_id,
-                            addend: -4,
-                        }
+                        x86_64_data_relocation(sym_id, offset + proc_offset, addend)
                     }
                 } else {
                     internal_error!("failed to find data symbol for {:?}", name);
@@ -319,7 +355,54 @@ fn generate_roc_panic<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object)
     }
 }
 
-fn generate_roc_dbg<'a, B: Backend<'a>>(_backend: &mut B, output: &mut Object) {
+/// A `_start` that calls `main` and exits with its result, for an executable without a host.
+/// `main` has to be defined by now, since the call to it is relocated right away.
+fn generate_entry_shim<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object, main: String) {
+    if backend.target() != Target::LinuxX64 {
+        user_error!(
+            "A program without a host can only be built for x86_64 Linux, not {}.",
+            backend.target()
+        );
+    }
+
+    let text_section = output.section_id(StandardSection::Text);
+    let proc_symbol = Symbol {
+        name: b"_start".to_vec(),
+        value: 0,
+        size: 0,
+        kind: SymbolKind::Text,
+        scope: SymbolScope::Dynamic,
+        weak: false,
+        section: SymbolSection::Section(text_section),
+        flags: SymbolFlags::None,
+    };
+    let proc_id = output.add_symbol(proc_symbol);
+    let (proc_data, relocs) = backend.build_entry_shim(main);
+
+    let proc_offset = output.add_symbol_data(proc_id, text_section, proc_data, 16);
+
+    for r in relocs {
+        let relocation = match r {
+            Relocation::LinkedFunction { offset, name } => {
+                if let Some(sym_id) = output.symbol_id(name.as_bytes()) {
+                    let external = is_external_symbol(output, sym_id);
+                    create_relocation(backend.target(), sym_id, offset + proc_offset, external)
+                } else {
+                    internal_error!("failed to find fn symbol for {:?}", name);
+                }
+            }
+            Relocation::LocalData { .. }
+            | Relocation::LinkedData { .. }
+            | Relocation::JmpToReturn { .. } => {
+                unreachable!("not currently created by build_entry_shim")
+            }
+        };
+
+        output.add_relocation(text_section, relocation).unwrap();
+    }
+}
+
+fn generate_roc_dbg<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object) {
     let text_section = output.section_id(StandardSection::Text);
     let proc_symbol = Symbol {
         name: "roc_dbg".as_bytes().to_vec(),
@@ -331,9 +414,10 @@ fn generate_roc_dbg<'a, B: Backend<'a>>(_backend: &mut B, output: &mut Object) {
         section: SymbolSection::Section(text_section),
         flags: SymbolFlags::None,
     };
-    let _proc_id = output.add_symbol(proc_symbol);
-    // TODO: Actually generate an impl instead of just an empty symbol.
-    // At a minimum, it should just be a ret.
+    let proc_id = output.add_symbol(proc_symbol);
+    let proc_data = backend.build_roc_dbg();
+
+    output.add_symbol_data(proc_id, text_section, proc_data, 16);
 }
 
 fn generate_wrapper<'a, B: Backend<'a>>(
@@ -371,7 +455,8 @@ fn generate_wrapper<'a, B: Backend<'a>>(
     };
     output.add_symbol(symbol);
     if let Some(sym_id) = output.symbol_id(name) {
-        let reloc = create_relocation(backend.target(), sym_id, offset + proc_offset);
+        let external = is_external_symbol(output, sym_id);
+        let reloc = create_relocation(backend.target(), sym_id, offset + proc_offset, external);
 
         match output.add_relocation(text_section, reloc) {
             Ok(obj) => obj,
@@ -382,7 +467,58 @@ fn generate_wrapper<'a, B: Backend<'a>>(
     }
 }
 
-fn create_relocation(target: Target, symbol: SymbolId, offset: u64) -> write::Relocation {
+/// A symbol is external if it may be resolved outside of the current linkage unit.
+/// This is the case for undefined symbols (zig builtins, libc, the platform)
+/// and for symbols we export dynamically (they can be interposed).
+fn is_external_symbol(output: &Object<'_>, symbol: SymbolId) -> bool {
+    let symbol = output.symbol(symbol);
+
+    matches!(symbol.section, SymbolSection::Undefined) || symbol.scope == SymbolScope::Dynamic
+}
+
+/// Data is reached through its GOT entry on x86_64, so it may live outside the linkage unit.
+/// A GOT entry only holds the address of the symbol itself, which is why `data_pointer` adds an
+/// offset into the data after loading the address instead of putting it in the relocation.
+fn x86_64_data_relocation(symbol: SymbolId, offset: u64, addend: i64) -> write::Relocation {
+    if addend != 0 {
+        internal_error!("a GOT relocation can't point {addend} bytes into its data");
+    }
+
+    x86_64_rip_relative(RelocationKind::GotRelative, symbol, offset, 0)
+}
+
+/// The displacement of a RIP-relative operand is relative to the end of its instruction, but a
+/// relocation is computed from the address of the displacement itself. Every RIP-relative operand
+/// we emit is the last 4 bytes of its instruction, so the end is always 4 bytes further.
+const RIP_DISPLACEMENT_TO_END: i64 = 4;
+
+/// Relocates the 32-bit RIP-relative displacement at `offset` to point at `symbol + addend`.
+/// This is `R_X86_64_PC32` for a `kind` of `Relative`, or `R_X86_64_GOTPCREL` for `GotRelative`.
+fn x86_64_rip_relative(
+    kind: RelocationKind,
+    symbol: SymbolId,
+    offset: u64,
+    addend: i64,
+) -> write::Relocation {
+    write::Relocation {
+        offset,
+        size: 32,
+        kind,
+        encoding: RelocationEncoding::Generic,
+        symbol,
+        addend: addend - RIP_DISPLACEMENT_TO_END,
+    }
+}
+
+/// Creates the relocation for a call or tail call to `symbol`.
+/// Calls to external symbols must go through the PLT (`R_X86_64_PLT32`).
+/// Calls to procs defined in this linkage unit can be direct (`R_X86_64_PC32`).
+fn create_relocation(
+    target: Target,
+    symbol: SymbolId,
+    offset: u64,
+    external: bool,
+) -> write::Relocation {
     let (encoding, size, addend, kind) = match target.architecture() {
         roc_target::Architecture::Aarch32 => todo!(),
         roc_target::Architecture::Aarch64 => {
@@ -407,12 +543,23 @@ fn create_relocation(target: Target, symbol: SymbolId, offset: u64) -> write::Re
         }
         roc_target::Architecture::Wasm32 => todo!(),
         roc_target::Architecture::X86_32 => todo!(),
-        roc_target::Architecture::X86_64 => (
-            RelocationEncoding::X86Branch,
-            32,
-            -4,
-            RelocationKind::PltRelative,
-        ),
+        roc_target::Architecture::X86_64 => {
+            if external {
+                (
+                    RelocationEncoding::X86Branch,
+                    32,
+                    -4,
+                    RelocationKind::PltRelative,
+                )
+            } else {
+                (
+                    RelocationEncoding::X86Branch,
+                    32,
+                    -4,
+                    RelocationKind::Relative,
+                )
+            }
+        }
     };
 
     write::Relocation {
@@ -434,15 +581,8 @@ fn build_object<'a, B: Backend<'a>>(
 
     let arena = backend.env().arena;
 
-    /*
-    // Commented out because we couldn't figure out how to get it to work on mac - see https://github.com/roc-lang/roc/pull/1323
-    let comment = output.add_section(vec![], b".comment".to_vec(), SectionKind::OtherString);
-    output.append_section_data(
-        comment,
-        format!("\0roc dev backend version {} \0", VERSION).as_bytes(),
-        1,
-    );
-    */
+    add_producer_comment(&mut output, &producer());
+    add_gnu_stack_note(&mut output);
 
     if backend.env().mode.generate_roc_panic() {
         define_panic_msg(&mut output);
@@ -623,13 +763,17 @@ fn build_object<'a, B: Backend<'a>>(
 
     // Build procedures from user code
     let mut relocations = bumpalo::vec![in arena];
+    let mut constant_pool = ConstantPool::default();
+    let mut unwind_sections = None;
     for (fn_name, section_id, proc_id, proc) in procs {
         build_proc(
             &mut output,
             &mut backend,
             &mut relocations,
             &mut layout_ids,
+            &mut constant_pool,
             data_section,
+            &mut unwind_sections,
             fn_name,
             section_id,
             proc_id,
@@ -720,7 +864,9 @@ fn build_object<'a, B: Backend<'a>>(
             &mut backend,
             &mut relocations,
             &mut layout_ids,
+            &mut constant_pool,
             data_section,
+            &mut unwind_sections,
             fn_name,
             section_id,
             proc_id,
@@ -735,6 +881,11 @@ fn build_object<'a, B: Backend<'a>>(
             Err(e) => internal_error!("{:?}", e),
         }
     }
+
+    if let Some(main) = backend.env().options.standalone_main.clone() {
+        generate_entry_shim(&mut backend, &mut output, main);
+    }
+
     output
 }
 
@@ -921,50 +1072,97 @@ fn build_proc_symbol<'a, B: Backend<'a>>(
     procs.push((fn_name, section_id, proc_id, proc));
 }
 
+/// The data-section symbols of the constants that procs load from memory, keyed by their bytes,
+/// along with the alignment each was placed at.
+/// It is shared by all procs in an object, so each distinct constant is only emitted once.
+type ConstantPool = MutMap<std::vec::Vec<u8>, (SymbolId, u64)>;
+
+/// Returns the data-section symbol holding `data` at an offset that is a multiple of `align`,
+/// adding one named after `fn_name` if needed.
+///
+/// The data section is padded as needed, so an entry's alignment holds regardless of the sizes
+/// of the entries before it. A constant that was already placed with a weaker alignment is
+/// placed again rather than reused.
+fn intern_local_data(
+    output: &mut Object,
+    constant_pool: &mut ConstantPool,
+    data_section: SectionId,
+    fn_name: &str,
+    data: &[u8],
+    align: u64,
+) -> SymbolId {
+    debug_assert!(align.is_power_of_two());
+
+    match constant_pool.get(data) {
+        Some((data_id, placed_align)) if *placed_align >= align => return *data_id,
+        _ => {}
+    }
+
+    // Every entry grows the section, so its current size keeps the names unique even when a
+    // constant is placed a second time.
+    let data_symbol = write::Symbol {
+        name: format!(
+            "{fn_name}.data{}",
+            output.section(data_section).data().len()
+        )
+        .as_bytes()
+        .to_vec(),
+        value: 0,
+        size: 0,
+        kind: SymbolKind::Data,
+        scope: SymbolScope::Compilation,
+        weak: false,
+        section: SymbolSection::Section(data_section),
+        flags: SymbolFlags::None,
+    };
+    let data_id = output.add_symbol(data_symbol);
+    output.add_symbol_data(data_id, data_section, data, align);
+    constant_pool.insert(data.to_vec(), (data_id, align));
+    data_id
+}
+
 #[allow(clippy::too_many_arguments)]
 fn build_proc<'a, B: Backend<'a>>(
     output: &mut Object,
     backend: &mut B,
     relocations: &mut Vec<'a, (SectionId, object::write::Relocation)>,
     layout_ids: &mut LayoutIds<'a>,
+    constant_pool: &mut ConstantPool,
     data_section: SectionId,
+    unwind_sections: &mut Option<(SectionId, SectionId)>,
     fn_name: String,
     section_id: SectionId,
     proc_id: SymbolId,
     proc: Proc<'a>,
 ) {
-    let mut local_data_index = 0;
     let target = backend.target();
     let (proc_data, relocs, rc_proc_names) = backend.build_proc(proc, layout_ids);
     let proc_offset = output.add_symbol_data(proc_id, section_id, &proc_data, 16);
+    if let Some(unwind_info) = backend.unwind_info() {
+        add_windows_unwind_entry(
+            output,
+            unwind_sections,
+            proc_id,
+            proc_data.len(),
+            unwind_info,
+        );
+    }
     for reloc in relocs.iter() {
         let elfreloc = match reloc {
-            Relocation::LocalData { offset, data } => {
-                let data_symbol = write::Symbol {
-                    name: format!("{fn_name}.data{local_data_index}")
-                        .as_bytes()
-                        .to_vec(),
-                    value: 0,
-                    size: 0,
-                    kind: SymbolKind::Data,
-                    scope: SymbolScope::Compilation,
-                    weak: false,
-                    section: SymbolSection::Section(data_section),
-                    flags: SymbolFlags::None,
-                };
-                local_data_index += 1;
-                let data_id = output.add_symbol(data_symbol);
-                output.add_symbol_data(data_id, data_section, data, 4);
-                write::Relocation {
-                    offset: offset + proc_offset,
-                    size: 32,
-                    kind: RelocationKind::Relative,
-                    encoding: RelocationEncoding::Generic,
-                    symbol: data_id,
-                    addend: -4,
-                }
+            Relocation::LocalData {
+                offset,
+                data,
+                align,
+            } => {
+                let data_id =
+                    intern_local_data(output, constant_pool, data_section, &fn_name, data, *align);
+                x86_64_rip_relative(RelocationKind::Relative, data_id, offset + proc_offset, 0)
             }
-            Relocation::LinkedData { offset, name } => {
+            Relocation::LinkedData {
+                offset,
+                name,
+                addend,
+            } => {
                 add_undefined_rc_proc(output, name, &rc_proc_names);
 
                 if let Some(sym_id) = output.symbol_id(name.as_bytes()) {
@@ -977,7 +1175,7 @@ fn build_proc<'a, B: Backend<'a>>(
                             kind: RelocationKind::Elf(object::elf::R_AARCH64_ADR_PREL_PG_HI21),
                             encoding: RelocationEncoding::Generic,
                             symbol: sym_id,
-                            addend: -4,
+                            addend: addend - 4,
                         };
 
                         relocations.push((section_id, r));
@@ -990,7 +1188,7 @@ fn build_proc<'a, B: Backend<'a>>(
                             kind: RelocationKind::Elf(object::elf::R_AARCH64_ADD_ABS_LO12_NC),
                             encoding: RelocationEncoding::Generic,
                             symbol: sym_id,
-                            addend: 0,
+                            addend: *addend,
                         }
                     } else if cfg!(all(target_arch = "aarch64", target_os = "macos")) {
                         //    4ed0: 90000000        adrp    x0, 0x4000 <_std.unicode.utf8Decode4+0x16c>
@@ -1004,7 +1202,7 @@ fn build_proc<'a, B: Backend<'a>>(
                             },
                             encoding: RelocationEncoding::Generic,
                             symbol: sym_id,
-                            addend: 0,
+                            addend: *addend,
                         };
 
                         relocations.push((section_id, r));
@@ -1020,17 +1218,10 @@ fn build_proc<'a, B: Backend<'a>>(
                             },
                             encoding: RelocationEncoding::Generic,
                             symbol: sym_id,
-                            addend: 0,
+                            addend: *addend,
                         }
                     } else {
-                        write::Relocation {
-                            offset: offset + proc_offset,
-                            size: 32,
-                            kind: RelocationKind::GotRelative,
-                            encoding: RelocationEncoding::Generic,
-                            symbol: sym_id,
-                            addend: -4,
-                        }
+                        x86_64_data_relocation(sym_id, offset + proc_offset, *addend)
                     }
                 } else {
                     internal_error!("failed to find data symbol for {:?}", name);
@@ -1055,7 +1246,8 @@ fn build_proc<'a, B: Backend<'a>>(
                 add_undefined_rc_proc(output, name, &rc_proc_names);
 
                 if let Some(sym_id) = output.symbol_id(name.as_bytes()) {
-                    create_relocation(target, sym_id, offset + proc_offset)
+                    let external = is_external_symbol(output, sym_id);
+                    create_relocation(target, sym_id, offset + proc_offset, external)
                 } else {
                     internal_error!("failed to find fn symbol for {:?}", name);
                 }
@@ -1066,6 +1258,49 @@ fn build_proc<'a, B: Backend<'a>>(
     }
 }
 
+/// Registers the unwind info of a proc with the Windows unwinder.
+/// The info itself goes in `.xdata`, and an entry in `.pdata` maps the proc's code to it.
+/// Both sections are added along with the first entry.
+fn add_windows_unwind_entry(
+    output: &mut Object,
+    unwind_sections: &mut Option<(SectionId, SectionId)>,
+    proc_id: SymbolId,
+    proc_size: usize,
+    unwind_info: &[u8],
+) {
+    let (pdata, xdata) = *unwind_sections.get_or_insert_with(|| {
+        (
+            output.add_section(vec![], b".pdata".to_vec(), SectionKind::ReadOnlyData),
+            output.add_section(vec![], b".xdata".to_vec(), SectionKind::ReadOnlyData),
+        )
+    });
+
+    let info_offset = output.append_section_data(xdata, unwind_info, 4);
+    let entry_offset = output.append_section_data(pdata, &[0; 12], 4);
+
+    // The entry holds the start and end of the proc and the location of its unwind info,
+    // each relative to the image base.
+    let xdata_symbol = output.section_symbol(xdata);
+    for (field, symbol, addend) in [
+        (0, proc_id, 0),
+        (4, proc_id, proc_size as i64),
+        (8, xdata_symbol, info_offset as i64),
+    ] {
+        let relocation = write::Relocation {
+            offset: entry_offset + field,
+            size: 32,
+            kind: RelocationKind::ImageOffset,
+            encoding: RelocationEncoding::Generic,
+            symbol,
+            addend,
+        };
+
+        if let Err(e) = output.add_relocation(pdata, relocation) {
+            internal_error!("{:?}", e);
+        }
+    }
+}
+
 fn add_undefined_rc_proc(
     output: &mut Object<'_>,
     name: &String,
@@ -1103,3 +1338,423 @@ fn format_symbol_name(sym: roc_module::symbol::Symbol) -> std::vec::Vec<u8> {
 
     name.as_bytes()[..length].to_vec()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_object() -> (Object<'static>, SymbolId, SymbolId) {
+        let mut output = Object::new(BinaryFormat::Elf, Architecture::X86_64, Endianness::Little);
+        let text_section = output.section_id(StandardSection::Text);
+
+        let libc_symbol = output.add_symbol(Symbol {
+            name: b"memcpy".to_vec(),
+            value: 0,
+            size: 0,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Dynamic,
+            weak: false,
+            section: SymbolSection::Undefined,
+            flags: SymbolFlags::None,
+        });
+
+        let proc_symbol = output.add_symbol(Symbol {
+            name: b"#UserApp_main".to_vec(),
+            value: 0,
+            size: 0,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Linkage,
+            weak: false,
+            section: SymbolSection::Section(text_section),
+            flags: SymbolFlags::None,
+        });
+
+        (output, libc_symbol, proc_symbol)
+    }
+
+    #[test]
+    fn external_call_goes_through_plt() {
+        let (output, libc_symbol, _) = test_object();
+
+        let external = is_external_symbol(&output, libc_symbol);
+        let reloc = create_relocation(Target::LinuxX64, libc_symbol, 8, external);
+
+        assert!(external);
+        assert_eq!(reloc.kind, RelocationKind::PltRelative);
+        assert_eq!(reloc.encoding, RelocationEncoding::X86Branch);
+        assert_eq!((reloc.offset, reloc.size, reloc.addend), (8, 32, -4));
+    }
+
+    #[test]
+    fn supports_reflects_what_is_built() {
+        for feature in [Feature::Floats, Feature::Calls, Feature::TagUnions] {
+            assert_eq!(
+                supports(Target::LinuxX64, feature),
+                cfg!(feature = "target-x86_64")
+            );
+            assert_eq!(
+                supports(Target::MacArm64, feature),
+                cfg!(feature = "target-aarch64")
+            );
+            assert!(!supports(Target::Wasm32, feature));
+        }
+
+        for feature in [Feature::Expect, Feature::ErasedLambdas] {
+            assert!(!supports(Target::LinuxX64, feature));
+        }
+    }
+
+    #[test]
+    fn backend_name_matches_its_target() {
+        let arena = bumpalo::Bump::new();
+        let env = Env {
+            arena: &arena,
+            module_id: symbol::ModuleId::ATTR,
+            exposed_to_host: Default::default(),
+            lazy_literals: false,
+            mode: AssemblyBackendMode::Test,
+            options: Default::default(),
+        };
+        let mut interns = Interns::default();
+
+        let target = Target::LinuxX64;
+        let mut layout_interner = STLayoutInterner::with_capacity(4, target);
+        let backend = new_backend_64bit::<
+            x86_64::X86_64GeneralReg,
+            x86_64::X86_64FloatReg,
+            x86_64::X86_64Assembler,
+            x86_64::X86_64SystemV,
+        >(&env, target, &mut interns, &mut layout_interner);
+        assert_eq!(backend.target(), target);
+        assert_eq!(backend.calling_convention(), "System V");
+        assert_eq!(backend.name(), "x86_64 System V dev backend");
+
+        let target = Target::WinX64;
+        let mut layout_interner = STLayoutInterner::with_capacity(4, target);
+        let backend = new_backend_64bit::<
+            x86_64::X86_64GeneralReg,
+            x86_64::X86_64FloatReg,
+            x86_64::X86_64Assembler,
+            x86_64::X86_64WindowsFastcall,
+        >(&env, target, &mut interns, &mut layout_interner);
+        assert_eq!(backend.target(), target);
+        assert_eq!(backend.calling_convention(), "Windows fastcall");
+        assert_eq!(backend.name(), "x86_64 Windows fastcall dev backend");
+
+        let target = Target::LinuxArm64;
+        let mut layout_interner = STLayoutInterner::with_capacity(4, target);
+        let backend = new_backend_64bit::<
+            aarch64::AArch64GeneralReg,
+            aarch64::AArch64FloatReg,
+            aarch64::AArch64Assembler,
+            aarch64::AArch64Call,
+        >(&env, target, &mut interns, &mut layout_interner);
+        assert_eq!(backend.target(), target);
+        assert_eq!(backend.calling_convention(), "AAPCS64");
+        assert_eq!(backend.name(), "aarch64 AAPCS64 dev backend");
+    }
+
+    #[test]
+    fn proc_taking_its_own_address_relocates_against_itself() {
+        let arena = bumpalo::Bump::new();
+        let env = Env {
+            arena: &arena,
+            module_id: symbol::ModuleId::ATTR,
+            exposed_to_host: Default::default(),
+            lazy_literals: false,
+            mode: AssemblyBackendMode::Test,
+            options: Default::default(),
+        };
+        let mut interns = Interns::default();
+        interns.all_ident_ids.get_or_insert(env.module_id);
+
+        let target = Target::LinuxX64;
+        let mut layout_interner = STLayoutInterner::with_capacity(4, target);
+        let mut backend = new_backend_64bit::<
+            x86_64::X86_64GeneralReg,
+            x86_64::X86_64FloatReg,
+            x86_64::X86_64Assembler,
+            x86_64::X86_64SystemV,
+        >(&env, target, &mut interns, &mut layout_interner);
+
+        let own = LambdaName::no_niche(backend.debug_symbol("own"));
+        backend.reset(
+            "own".to_string(),
+            roc_mono::ir::SelfRecursive::NotSelfRecursive,
+        );
+        backend.set_proc_lambda_name(own);
+        backend.build_expr(
+            &symbol::Symbol::DEV_TMP,
+            &Expr::FunctionPointer { lambda_name: own },
+            &Layout::OPAQUE_PTR,
+        );
+        let (_, relocs) = backend.finalize();
+
+        assert!(matches!(
+            &relocs[..],
+            [Relocation::LinkedFunction { name, .. }] if name == "own"
+        ));
+    }
+
+    #[test]
+    fn procs_share_identical_constants() {
+        let (mut output, _, _) = test_object();
+        let data_section = output.section_id(StandardSection::Data);
+        let mut constant_pool = ConstantPool::default();
+        let mut intern = |fn_name: &str, data: &str| {
+            intern_local_data(
+                &mut output,
+                &mut constant_pool,
+                data_section,
+                fn_name,
+                data.as_bytes(),
+                1,
+            )
+        };
+
+        let first = intern("first", "a string shared by two procs");
+        let second = intern("second", "a string shared by two procs");
+        let other = intern("second", "a string used by only one proc");
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_eq!(
+            output.section(data_section).data(),
+            b"a string shared by two procsa string used by only one proc"
+        );
+    }
+
+    #[test]
+    fn constants_land_on_their_alignment_regardless_of_earlier_entries() {
+        let (mut output, _, _) = test_object();
+        let data_section = output.section_id(StandardSection::Data);
+        let mut constant_pool = ConstantPool::default();
+
+        intern_local_data(
+            &mut output,
+            &mut constant_pool,
+            data_section,
+            "f",
+            &1.5f32.to_le_bytes(),
+            4,
+        );
+        intern_local_data(
+            &mut output,
+            &mut constant_pool,
+            data_section,
+            "f",
+            &2.5f64.to_le_bytes(),
+            8,
+        );
+        assert_eq!(output.section(data_section).data().len(), 16);
+
+        // A 32-byte AVX constant after 16 bytes of data still starts on a 32-byte boundary.
+        let avx_mask = [0xffu8; 32];
+        let mask_id = intern_local_data(
+            &mut output,
+            &mut constant_pool,
+            data_section,
+            "f",
+            &avx_mask,
+            32,
+        );
+        let mask_symbol = output.symbol(mask_id);
+        assert_eq!(mask_symbol.section, SymbolSection::Section(data_section));
+        assert_eq!(mask_symbol.value, 32);
+        assert_eq!(output.section(data_section).data()[32..], avx_mask);
+
+        // Asking for the same constant with a weaker alignment reuses it, but a stronger one
+        // places a fresh, suitably aligned copy.
+        let reused = intern_local_data(
+            &mut output,
+            &mut constant_pool,
+            data_section,
+            "g",
+            &avx_mask,
+            16,
+        );
+        assert_eq!(reused, mask_id);
+
+        let realigned = intern_local_data(
+            &mut output,
+            &mut constant_pool,
+            data_section,
+            "g",
+            &2.5f64.to_le_bytes(),
+            32,
+        );
+        assert_eq!(output.symbol(realigned).value, 64);
+    }
+
+    #[test]
+    fn elf_objects_name_their_producer_in_the_comment_section() {
+        use object::{Object as _, ObjectSection as _};
+
+        let (mut output, _, _) = test_object();
+        add_producer_comment(&mut output, &producer());
+
+        let bytes = output.write().unwrap();
+        let file = object::File::parse(&*bytes).unwrap();
+        let comment = file.section_by_name(".comment").unwrap();
+
+        let expected = format!("\0roc dev backend version {}\0", env!("CARGO_PKG_VERSION"));
+        assert_eq!(comment.data().unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn macho_objects_have_no_comment_section() {
+        use object::Object as _;
+
+        let mut output = Object::new(
+            BinaryFormat::MachO,
+            Architecture::Aarch64,
+            Endianness::Little,
+        );
+        add_producer_comment(&mut output, &producer());
+
+        let bytes = output.write().unwrap();
+        let file = object::File::parse(&*bytes).unwrap();
+        assert!(file.section_by_name(".comment").is_none());
+    }
+
+    #[test]
+    fn elf_objects_mark_the_stack_non_executable() {
+        use object::{Object as _, ObjectSection as _};
+
+        for architecture in [Architecture::X86_64, Architecture::Aarch64] {
+            let mut output = Object::new(BinaryFormat::Elf, architecture, Endianness::Little);
+            add_gnu_stack_note(&mut output);
+
+            let bytes = output.write().unwrap();
+            let file = object::File::parse(&*bytes).unwrap();
+            let note = file.section_by_name(".note.GNU-stack").unwrap();
+
+            assert_eq!(note.size(), 0);
+            match note.flags() {
+                SectionFlags::Elf { sh_flags } => {
+                    assert_eq!(sh_flags & u64::from(object::elf::SHF_EXECINSTR), 0)
+                }
+                flags => panic!("expected ELF section flags, got {flags:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn macho_objects_have_no_gnu_stack_note() {
+        use object::Object as _;
+
+        let mut output = Object::new(
+            BinaryFormat::MachO,
+            Architecture::X86_64,
+            Endianness::Little,
+        );
+        add_gnu_stack_note(&mut output);
+
+        let bytes = output.write().unwrap();
+        let file = object::File::parse(&*bytes).unwrap();
+        assert!(file.section_by_name(".note.GNU-stack").is_none());
+    }
+
+    #[test]
+    fn internal_call_is_pc_relative() {
+        let (output, _, proc_symbol) = test_object();
+
+        let external = is_external_symbol(&output, proc_symbol);
+        let reloc = create_relocation(Target::LinuxX64, proc_symbol, 8, external);
+
+        assert!(!external);
+        assert_eq!(reloc.kind, RelocationKind::Relative);
+        assert_eq!((reloc.offset, reloc.size, reloc.addend), (8, 32, -4));
+    }
+
+    #[test]
+    fn rip_relative_data_load_is_relative_to_the_end_of_the_instruction() {
+        use crate::generic64::Assembler;
+        use x86_64::{X86_64Assembler, X86_64FloatReg};
+
+        let arena = bumpalo::Bump::new();
+        let mut buf = bumpalo::vec![in &arena; 0x90u8];
+        let mut relocs = bumpalo::vec![in &arena];
+        X86_64Assembler::mov_freg64_imm64(&mut buf, &mut relocs, X86_64FloatReg::XMM1, 1.5);
+
+        let offset = match &relocs[..] {
+            [Relocation::LocalData { offset, .. }] => *offset,
+            other => panic!("expected a single local data relocation, got {other:?}"),
+        };
+        // The displacement is the tail of the instruction, so its end is the end of the code.
+        assert_eq!(offset + 4, buf.len() as u64);
+
+        let (mut output, _, _) = test_object();
+        let data_section = output.section_id(StandardSection::Data);
+        let data_id = intern_local_data(
+            &mut output,
+            &mut ConstantPool::default(),
+            data_section,
+            "f",
+            &1.5f64.to_le_bytes(),
+            8,
+        );
+
+        // S + A - P, with A = -4, is the distance from the end of the instruction to the data.
+        let reloc = x86_64_rip_relative(RelocationKind::Relative, data_id, offset, 0);
+        assert_eq!(reloc.kind, RelocationKind::Relative);
+        assert_eq!((reloc.offset, reloc.size, reloc.addend), (offset, 32, -4));
+    }
+
+    #[test]
+    fn linked_data_relocates_through_the_got() {
+        let (_, libc_symbol, _) = test_object();
+
+        let got = x86_64_data_relocation(libc_symbol, 8, 0);
+        assert_eq!(got.kind, RelocationKind::GotRelative);
+        assert_eq!(got.addend, -4);
+    }
+
+    #[test]
+    fn windows_procs_get_an_unwind_entry_each() {
+        use object::{Object as _, ObjectSection as _};
+
+        let mut output = Object::new(BinaryFormat::Coff, Architecture::X86_64, Endianness::Little);
+        let text_section = output.section_id(StandardSection::Text);
+        let mut unwind_sections = None;
+
+        let infos: [&[u8]; 2] = [
+            &[0x01, 4, 1, 0x00, 1, 0x50, 0, 0],
+            &[0x01, 11, 2, 0x00, 11, 0x12, 1, 0x50],
+        ];
+        for (i, info) in infos.iter().enumerate() {
+            let proc_id = output.add_symbol(Symbol {
+                name: format!("proc{i}").into_bytes(),
+                value: 0,
+                size: 0,
+                kind: SymbolKind::Text,
+                scope: SymbolScope::Linkage,
+                weak: false,
+                section: SymbolSection::Section(text_section),
+                flags: SymbolFlags::None,
+            });
+            output.add_symbol_data(proc_id, text_section, &[0xC3; 24], 16);
+            add_windows_unwind_entry(&mut output, &mut unwind_sections, proc_id, 24, info);
+        }
+
+        let bytes = output.write().unwrap();
+        let file = object::File::parse(&*bytes).unwrap();
+
+        let xdata = file.section_by_name(".xdata").unwrap();
+        assert_eq!(xdata.data().unwrap(), infos.concat());
+
+        // Every entry is a start, an end and an unwind info address, each relocated.
+        let pdata = file.section_by_name(".pdata").unwrap();
+        assert_eq!(pdata.data().unwrap().len(), 2 * 12);
+        let relocations: std::vec::Vec<_> = pdata
+            .relocations()
+            .map(|(offset, relocation)| (offset, relocation.kind()))
+            .collect();
+        assert_eq!(
+            relocations,
+            (0..6)
+                .map(|i| (4 * i, RelocationKind::ImageOffset))
+                .collect::<std::vec::Vec<_>>()
+        );
+    }
+}
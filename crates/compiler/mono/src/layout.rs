@@ -1525,6 +1525,17 @@ impl<'a> LambdaSet<'a> {
         })
     }
 
+    /// The layouts of the arguments the functions in this set are called with,
+    /// not including their captures.
+    pub fn args(&self) -> &'a [InLayout<'a>] {
+        self.args
+    }
+
+    /// The layout of what the functions in this set return.
+    pub fn ret(&self) -> InLayout<'a> {
+        self.ret
+    }
+
     #[inline(always)]
     pub fn len(&self) -> usize {
         self.set.len()
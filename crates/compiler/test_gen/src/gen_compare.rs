@@ -157,6 +157,28 @@ fn not_bool() {
     assert_evals_to!("!(!Bool.false)", false, bool);
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn and_bools_from_comparisons() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+                x = 5i64
+                y = 7i64
+
+                lt = x < y
+                gt = y > x
+                notLt = !lt
+                ne = x != y
+
+                lt && gt && !notLt && ne
+                "#
+        ),
+        true,
+        bool
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
 fn empty_record() {
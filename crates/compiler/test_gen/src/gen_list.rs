@@ -4,6 +4,9 @@ use crate::helpers::llvm::assert_evals_to;
 #[cfg(feature = "gen-dev")]
 use crate::helpers::dev::assert_evals_to;
 
+#[cfg(feature = "gen-dev")]
+use crate::helpers::dev::assert_evals_to_with_options;
+
 #[cfg(feature = "gen-wasm")]
 use crate::helpers::wasm::assert_evals_to;
 
@@ -50,6 +53,28 @@ fn int_list_literal() {
     );
 }
 
+#[test]
+#[cfg(feature = "gen-dev")]
+fn int_list_literal_with_streaming_stores() {
+    let options = roc_gen_dev::DevOptions {
+        streaming_write_min_bytes: Some(64),
+        ..Default::default()
+    };
+
+    assert_evals_to_with_options!(
+        "[12, 9]",
+        RocList::from_slice(&[12, 9]),
+        RocList<i64>,
+        options.clone()
+    );
+    assert_evals_to_with_options!(
+        "[1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1]",
+        RocList::from_slice(&[1i64; 23]),
+        RocList<i64>,
+        options
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn bool_list_literal() {
@@ -2311,6 +2336,23 @@ fn get_int_list_oob() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn get_int_list_runtime_index() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            list = [12, 9, 6, 3]
+            index = List.len list - 2
+
+            List.get list index
+            "
+        ),
+        RocResult::ok(6),
+        RocResult<i64, ()>
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn replace_unique_int_list() {
@@ -4287,3 +4329,56 @@ fn list_concat_utf8() {
         RocList<u8>
     )
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn list_len_of_an_argument() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            count : List I64 -> U64
+            count = \list -> List.len list
+
+            count [] + 10 * count [1, 2, 3]
+            "
+        ),
+        30,
+        u64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn list_get_at_an_argument_index() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            getAt : List I64, U64 -> I64
+            getAt = \list, i ->
+                when List.get list i is
+                    Ok x -> x
+                    Err _ -> -1
+
+            getAt [10, 20, 30, 40] 2 + getAt [10, 20, 30, 40] 4
+            "
+        ),
+        29,
+        i64
+    );
+    // Str elements are 24 bytes, which no addressing mode can scale by.
+    assert_evals_to!(
+        indoc!(
+            r#"
+            getAt : List Str, U64 -> Str
+            getAt = \list, i ->
+                when List.get list i is
+                    Ok x -> x
+                    Err _ -> ""
+
+            getAt ["a", "b", "c"] 1
+            "#
+        ),
+        RocStr::from("b"),
+        RocStr
+    );
+}
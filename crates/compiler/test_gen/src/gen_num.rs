@@ -4,6 +4,9 @@ use crate::helpers::llvm::assert_evals_to;
 #[cfg(feature = "gen-dev")]
 use crate::helpers::dev::assert_evals_to;
 
+#[cfg(feature = "gen-dev")]
+use crate::helpers::dev::assert_evals_to_with_options;
+
 #[cfg(feature = "gen-wasm")]
 use crate::helpers::wasm::assert_evals_to;
 
@@ -804,6 +807,22 @@ fn gen_div_dec_by_zero() {
     assert_evals_to!("1dec / 0", RocDec::from_str("-1").unwrap(), RocDec);
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn gen_div_ceil() {
+    assert_evals_to!("Num.divCeil 7 3", 3, i64);
+    assert_evals_to!("Num.divCeil 6 3", 2, i64);
+    assert_evals_to!("Num.divCeil 7u8 3", 3, u8);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn gen_div_ceil_negative() {
+    assert_evals_to!("Num.divCeil -7 3", -2, i64);
+    assert_evals_to!("Num.divCeil 7 -3", -2, i64);
+    assert_evals_to!("Num.divCeil -7 -3", 3, i64);
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 #[should_panic(expected = r#"Roc failed with message: "Integer division by 0!"#)]
@@ -1468,6 +1487,116 @@ fn gte_i64() {
     assert_evals_to!("0 >= 0", true, bool);
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn lt_u128() {
+    // only the low words differ
+    assert_evals_to!("1u128 < 2u128", true, bool);
+    assert_evals_to!("2u128 < 1u128", false, bool);
+    assert_evals_to!("1u128 < 9223372036854775808u128", true, bool);
+    // only the high words differ
+    assert_evals_to!("1u128 < 18446744073709551617u128", true, bool);
+    assert_evals_to!("18446744073709551617u128 < 1u128", false, bool);
+    assert_evals_to!(
+        "170141183460469231731687303715884105728u128 < 1u128",
+        false,
+        bool
+    );
+    assert_evals_to!("1u128 < 1u128", false, bool);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn lte_u128() {
+    assert_evals_to!("1u128 <= 1u128", true, bool);
+    assert_evals_to!("2u128 <= 1u128", false, bool);
+    assert_evals_to!(
+        "18446744073709551617u128 <= 18446744073709551617u128",
+        true,
+        bool
+    );
+    assert_evals_to!("18446744073709551617u128 <= 1u128", false, bool);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn gt_u128() {
+    assert_evals_to!("9223372036854775808u128 > 1u128", true, bool);
+    assert_evals_to!("1u128 > 2u128", false, bool);
+    assert_evals_to!(
+        "18446744073709551616u128 > 18446744073709551615u128",
+        true,
+        bool
+    );
+    assert_evals_to!(
+        "18446744073709551615u128 > 18446744073709551616u128",
+        false,
+        bool
+    );
+    assert_evals_to!("1u128 > 1u128", false, bool);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn gte_u128() {
+    assert_evals_to!("1u128 >= 1u128", true, bool);
+    assert_evals_to!("1u128 >= 2u128", false, bool);
+    assert_evals_to!("18446744073709551616u128 >= 1u128", true, bool);
+    assert_evals_to!("1u128 >= 18446744073709551616u128", false, bool);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn lt_i128() {
+    // only the low words differ
+    assert_evals_to!("1i128 < 2i128", true, bool);
+    assert_evals_to!("-2i128 < -1i128", true, bool);
+    assert_evals_to!("-1i128 < -2i128", false, bool);
+    // only the high words differ
+    assert_evals_to!("-18446744073709551615i128 < 1i128", true, bool);
+    assert_evals_to!("1i128 < -18446744073709551615i128", false, bool);
+    assert_evals_to!("1i128 < 18446744073709551617i128", true, bool);
+    assert_evals_to!("-1i128 < -1i128", false, bool);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn lte_i128() {
+    assert_evals_to!("-1i128 <= -1i128", true, bool);
+    assert_evals_to!("-1i128 <= -2i128", false, bool);
+    assert_evals_to!("-18446744073709551615i128 <= 1i128", true, bool);
+    assert_evals_to!("1i128 <= -18446744073709551615i128", false, bool);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn gt_i128() {
+    assert_evals_to!("-1i128 > -2i128", true, bool);
+    assert_evals_to!("-2i128 > -1i128", false, bool);
+    assert_evals_to!("1i128 > -18446744073709551615i128", true, bool);
+    assert_evals_to!("-18446744073709551615i128 > 1i128", false, bool);
+    assert_evals_to!("1i128 > 1i128", false, bool);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn gte_i128() {
+    assert_evals_to!("-1i128 >= -1i128", true, bool);
+    assert_evals_to!("-2i128 >= -1i128", false, bool);
+    assert_evals_to!("18446744073709551617i128 >= 1i128", true, bool);
+    assert_evals_to!("-18446744073709551617i128 >= 1i128", false, bool);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn eq_i128() {
+    assert_evals_to!("-1i128 == -1i128", true, bool);
+    // only the low words differ
+    assert_evals_to!("-1i128 == -2i128", false, bool);
+    // only the high words differ
+    assert_evals_to!("1i128 == 18446744073709551617i128", false, bool);
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn lt_f64() {
@@ -1612,6 +1741,26 @@ fn tail_call_elimination() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn tail_call_elimination_with_float_accumulator() {
+    assert_evals_to!(
+        indoc!(
+            r"
+                    sum : I64, F64 -> F64
+                    sum = \n, accum ->
+                        when n is
+                            0 -> accum
+                            _ -> sum (n - 1) (accum + Num.toF64 n * 0.5)
+
+                    sum 1_000 0
+                "
+        ),
+        250250.0,
+        f64
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn num_negate() {
@@ -1819,6 +1968,26 @@ fn frac_is_nan() {
     assert_evals_to!("Num.isNaN 42dec", false, bool);
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn frac_nan_compares_unordered() {
+    assert_evals_to!("Num.nanF64 == 1.0f64", false, bool);
+    assert_evals_to!("Num.nanF64 == Num.nanF64", false, bool);
+    assert_evals_to!("Num.nanF64 != 1.0f64", true, bool);
+    assert_evals_to!("Num.nanF64 != Num.nanF64", true, bool);
+    assert_evals_to!("Num.nanF64 < 1.0f64", false, bool);
+    assert_evals_to!("Num.nanF64 > 1.0f64", false, bool);
+    assert_evals_to!("Num.nanF64 <= 1.0f64", false, bool);
+    assert_evals_to!("Num.nanF64 >= 1.0f64", false, bool);
+    assert_evals_to!("1.0f64 < Num.nanF64", false, bool);
+    assert_evals_to!("1.0f64 >= Num.nanF64", false, bool);
+
+    assert_evals_to!("Num.nanF32 == 1.0f32", false, bool);
+    assert_evals_to!("Num.nanF32 != 1.0f32", true, bool);
+    assert_evals_to!("Num.nanF32 < 1.0f32", false, bool);
+    assert_evals_to!("Num.nanF32 >= 1.0f32", false, bool);
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn frac_is_infinite() {
@@ -1946,6 +2115,20 @@ fn int_add_overflow() {
     assert_evals_to!("9_223_372_036_854_775_807 + 1", 0, i64);
 }
 
+#[test]
+#[cfg(feature = "gen-dev")]
+fn int_add_overflow_wraps_in_wrap_mode() {
+    assert_evals_to_with_options!(
+        "9_223_372_036_854_775_807 + 1",
+        i64::MIN,
+        i64,
+        roc_gen_dev::DevOptions {
+            overflow_mode: roc_gen_dev::OverflowMode::Wrap,
+            ..Default::default()
+        }
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn int_add_checked_ok() {
@@ -1966,6 +2149,25 @@ fn int_add_checked_err() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn int_add_checked_branches_on_overflow() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            addOrZero = \a, b ->
+                when Num.addChecked a b is
+                    Ok v -> v
+                    Err Overflow -> 0
+
+            addOrZero 40 2 + addOrZero Num.maxI64 1
+            "
+        ),
+        42,
+        i64
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
 fn float_add_checked_pass() {
@@ -2703,12 +2905,12 @@ num_conversion_tests! {
         to_i64_sign_extend_i8, "-15i8", -15, ["gen-wasm", "gen-dev"]
         to_i64_sign_extend_i16, "-15i16", -15, ["gen-wasm", "gen-dev"]
         to_i64_sign_extend_i32, "-15i32", -15, ["gen-wasm", "gen-dev"]
-        to_i64_truncate, "115i128", 115
-        to_i64_truncate_wraps, "10_000_000_000_000_000_000i128", -8446744073709551616
+        to_i64_truncate, "115i128", 115, ["gen-dev"]
+        to_i64_truncate_wraps, "10_000_000_000_000_000_000i128", -8446744073709551616, ["gen-dev"]
     )
     "Num.toI128", i128, (
         to_i128_same_width, "15u128", 15, ["gen-dev"]
-        to_i128_extend, "15i8", 15
+        to_i128_extend, "15i8", 15, ["gen-dev"]
     )
     "Num.toU8", u8, (
         to_u8_same_width, "15i8", 15, ["gen-wasm", "gen-dev"]
@@ -2730,12 +2932,12 @@ num_conversion_tests! {
     "Num.toU64", u64, (
         to_u64_same_width, "15i64", 15, ["gen-wasm", "gen-dev"]
         to_u64_extend, "15i8", 15, ["gen-wasm", "gen-dev"]
-        to_u64_truncate, "115i128", 115
-        to_u64_truncate_wraps, "10_000_000_000_000_000_000_000i128", 1864712049423024128
+        to_u64_truncate, "115i128", 115, ["gen-dev"]
+        to_u64_truncate_wraps, "10_000_000_000_000_000_000_000i128", 1864712049423024128, ["gen-dev"]
     )
     "Num.toU128", u128, (
         to_u128_same_width, "15i128", 15, ["gen-dev"]
-        to_u128_extend, "15i8", 15
+        to_u128_extend, "15i8", 15, ["gen-dev"]
         to_u128_big, "11562537357600483583u64", 11562537357600483583, ["gen-dev"]
     )
     "Num.toF32", f32, (
@@ -3719,6 +3921,52 @@ fn num_count_trailing_zero_bits() {
     assert_evals_to!(r"Num.countTrailingZeroBits 0b0010_1111u64", 0, u8);
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn num_count_zero_bits_of_zero() {
+    assert_evals_to!(r"Num.countLeadingZeroBits 0u64", 64, u8);
+    assert_evals_to!(r"Num.countTrailingZeroBits 0u64", 64, u8);
+    assert_evals_to!(r"Num.countLeadingZeroBits 1i64", 63, u8);
+    assert_evals_to!(r"Num.countTrailingZeroBits Num.minI64", 63, u8);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn add_bool_as_int() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            addIf : I64, Bool -> I64
+            addIf = \x, cond -> x + (if cond then 1 else 0)
+
+            (addIf 41 Bool.true, addIf 41 Bool.false)
+            "
+        ),
+        (42, 41),
+        (i64, i64)
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn add_bool_as_int_in_a_loop() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            countEvens : List I64, U64, I64 -> I64
+            countEvens = \list, i, acc ->
+                when List.get list i is
+                    Ok x -> countEvens list (i + 1) (acc + (if x % 2 == 0 then 1 else 0))
+                    Err _ -> acc
+
+            countEvens [1, 2, 3, 4, 6, 7] 0 0
+            "
+        ),
+        3,
+        i64
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn num_count_one_bits() {
@@ -4114,3 +4362,368 @@ fn cast_signed_unsigned() {
     assert_evals_to!(r"Num.toU8 127i8", 127, u8);
     assert_evals_to!(r"Num.toI8 127u8", 127, i8);
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn return_narrow_int_literal() {
+    assert_evals_to!("-3i32", -3, i32);
+    assert_evals_to!("-1i16", -1, i16);
+    assert_evals_to!("200u8", 200, u8);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn return_narrow_int_argument() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            identity : I16 -> I16
+            identity = \x -> x
+
+            identity -7
+            "
+        ),
+        -7,
+        i16
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn return_narrow_int_field() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            getB : { a : U8, b : I16, c : U32 } -> I16
+            getB = \r -> r.b
+
+            getB { a: 1, b: -7, c: 3 }
+            "
+        ),
+        -7,
+        i16
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn return_float_literal() {
+    assert_evals_to!("1.5f32", 1.5, f32);
+    assert_evals_to!("-2.25f64", -2.25, f64);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn return_float_argument() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            identity : F32 -> F32
+            identity = \x -> x
+
+            identity 1.5
+            "
+        ),
+        1.5,
+        f32
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn return_float_field() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            getB : { a : U8, b : F32, c : F64 } -> F32
+            getB = \r -> r.b
+
+            getB { a: 1, b: 1.5, c: 3 }
+            "
+        ),
+        1.5,
+        f32
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn mul_by_small_constants() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            scale : I64 -> I64
+            scale = \x -> x * 3 + x * 5 * 100 + x * 9 * 10_000
+
+            scale 7
+            "
+        ),
+        633_521,
+        i64
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            scale : I64 -> I64
+            scale = \x -> x * 3 + x * 5 + x * 9
+
+            scale -4
+            "
+        ),
+        -68,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn int_add_checked_returned_from_a_proc() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            add : I64, I64 -> Result I64 [Overflow]
+            add = \a, b -> Num.addChecked a b
+
+            add 40 2
+            "
+        ),
+        RocResult::ok(42),
+        RocResult<i64, ()>
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            add : I64, I64 -> Result I64 [Overflow]
+            add = \a, b -> Num.addChecked a b
+
+            add Num.maxI64 1
+            "
+        ),
+        RocResult::err(()),
+        RocResult<i64, ()>
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn int_cast_of_an_argument() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            narrow : I64 -> U8
+            narrow = \x -> Num.intCast x
+
+            narrow 0x1234
+            "
+        ),
+        0x34,
+        u8
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            widen : U8 -> U64
+            widen = \x -> Num.intCast x
+
+            widen 200
+            "
+        ),
+        200,
+        u64
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            widen : I8 -> I64
+            widen = \x -> Num.intCast x
+
+            widen -3
+            "
+        ),
+        -3,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn i128_and_dec_live_across_a_call() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            double : I64 -> I64
+            double = \x -> x * 2
+
+            run : I128, Dec, I64 -> Dec
+            run = \a, b, n ->
+                d = double n
+                if a == 100_000_000_000_000_000_000 && d == 6 then b + b else 0
+
+            run 100_000_000_000_000_000_000 1.25dec 3
+            "
+        ),
+        RocDec::from_str("2.5").unwrap(),
+        RocDec
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn shift_by_a_count_while_the_fourth_argument_is_live() {
+    // On x86_64 the count has to be in RCX, where the System V convention passes `d`.
+    assert_evals_to!(
+        indoc!(
+            r"
+            f : I64, U8, I64, I64 -> I64
+            f = \a, count, c, d -> Num.shiftLeftBy a count + c + d
+
+            f 1 4 100 1000
+            "
+        ),
+        1116,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn chained_comparison_with_a_shared_operand() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            between : I64, I64, I64 -> Bool
+            between = \a, b, c -> a < b && b < c
+
+            one = if between 1 2 3 then 100 else 0
+            two = if between 1 3 2 then 10 else 0
+            three = if between 2 1 3 then 1 else 0
+
+            one + two + three
+            "
+        ),
+        100,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn comparison_result_used_three_times() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            pick : I64, I64 -> I64
+            pick = \a, b ->
+                less = a < b
+
+                if less then
+                    if less then 10 else 20
+                else if less then
+                    30
+                else
+                    40
+
+            pick 1 2 + pick 2 1
+            "
+        ),
+        50,
+        i64
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn int_to_f64_breaking_false_dependencies() {
+    assert_evals_to_with_options!(
+        indoc!(
+            r"
+            toF64 : I64 -> F64
+            toF64 = \n -> Num.toFrac n
+
+            toF64 -3 + toF64 10
+            "
+        ),
+        7.0,
+        f64,
+        roc_gen_dev::DevOptions {
+            break_false_dependencies: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn less_than_zero() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            countNegative : I64 -> I64
+            countNegative = \x -> if x < 0 then 1 else 0
+
+            countNegative -5 + countNegative 0 + countNegative 7 + countNegative Num.minI64
+            "
+        ),
+        2,
+        i64
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            countNegative : I8 -> I64
+            countNegative = \x -> if x < 0 then 1 else 0
+
+            countNegative -1 + countNegative 0 + countNegative Num.maxI8 + countNegative Num.minI8
+            "
+        ),
+        2,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn wrapping_step_by_one() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            next : I64 -> I64
+            next = \x -> Num.addWrap x 1
+
+            previous : I64 -> I64
+            previous = \x -> Num.subWrap x 1
+
+            back : I64 -> I64
+            back = \x -> Num.addWrap -1 x
+
+            next 41 + previous 10 + back 3
+            "
+        ),
+        53,
+        i64
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            next : I64 -> I64
+            next = \x -> Num.addWrap x 1
+
+            next Num.maxI64
+            "
+        ),
+        i64::MIN,
+        i64
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            previous : I64 -> I64
+            previous = \x -> Num.subWrap x 1
+
+            previous Num.minI64
+            "
+        ),
+        i64::MAX,
+        i64
+    );
+}
@@ -10,6 +10,9 @@ use crate::helpers::wasm::assert_evals_to;
 #[cfg(feature = "gen-dev")]
 use crate::helpers::dev::assert_evals_to;
 
+#[cfg(feature = "gen-dev")]
+use crate::helpers::dev::assert_evals_to_with_options;
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 #[should_panic = r#"User crash with message: "hello crash""#]
@@ -27,6 +30,47 @@ fn crash_literal() {
     );
 }
 
+#[test]
+#[cfg(feature = "gen-dev")]
+#[should_panic = r#"User crash with message: "hello crash""#]
+fn crash_literal_trapping_unreachable_code() {
+    assert_evals_to_with_options!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main = if Bool.true then crash "hello crash" else 1u8
+            "#
+        ),
+        1u8,
+        u8,
+        roc_gen_dev::DevOptions {
+            trap_unreachable: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn no_crash_trapping_unreachable_code() {
+    assert_evals_to_with_options!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main = if Bool.false then crash "hello crash" else 1u8
+            "#
+        ),
+        1u8,
+        u8,
+        roc_gen_dev::DevOptions {
+            trap_unreachable: true,
+            ..Default::default()
+        }
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 #[should_panic = r#"User crash with message: "hello crash""#]
@@ -86,3 +130,49 @@ fn crash_in_passed_closure() {
         RocList<u8>
     );
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+#[should_panic = r#"User crash with message: "it diverged""#]
+fn crash_in_call_returning_the_empty_type() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            fail : Str -> []
+            fail = \msg -> crash msg
+
+            main =
+                when fail "it diverged" is
+                    _ -> 1u8
+            "#
+        ),
+        1u8,
+        u8
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn call_returning_the_empty_type_in_a_branch_not_taken() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            fail : Str -> []
+            fail = \msg -> crash msg
+
+            main =
+                if Bool.false then
+                    when fail "it diverged" is
+                        _ -> 0u8
+                else
+                    1u8
+            "#
+        ),
+        1u8,
+        u8
+    );
+}
@@ -4,6 +4,9 @@ use crate::helpers::llvm::assert_evals_to;
 #[cfg(feature = "gen-dev")]
 use crate::helpers::dev::assert_evals_to;
 
+#[cfg(feature = "gen-dev")]
+use crate::helpers::dev::assert_evals_to_with_options;
+
 #[cfg(feature = "gen-wasm")]
 use crate::helpers::wasm::assert_evals_to;
 
@@ -55,6 +58,27 @@ fn branch_second_float() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn branch_on_float_cases_and_nan() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            classify : F64 -> I64
+            classify = \x ->
+                when x is
+                    1.0 -> 1
+                    2.0 -> 2
+                    _ -> 3
+
+            classify 1.0 + 10 * classify 2.0 + 100 * classify Num.nanF64 + 1000 * classify 4.0
+            "
+        ),
+        3_321,
+        i64
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn branch_third_float() {
@@ -1011,7 +1035,7 @@ fn a_crash() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 #[should_panic(expected = "Roc failed with message: ")]
 fn annotation_without_body() {
     assert_evals_to!(
@@ -4695,3 +4719,584 @@ fn issue_6139_prefixes() {
         RocList<RocStr>
     );
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn arguments_live_across_a_call() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            double : I64 -> I64
+            double = \x -> x * 2
+
+            sum : I64, I64, I64 -> I64
+            sum = \a, b, c ->
+                d = double a
+                a + b + c + d
+
+            sum 1 2 3
+            "
+        ),
+        8,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn literal_used_in_one_branch() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            pick : I64 -> I64
+            pick = \n ->
+                fallback = 42
+                if n > 0 then n else fallback
+
+            pick 5 + pick -5
+            "
+        ),
+        47,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn return_from_every_branch() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            classify : I64 -> I64
+            classify = \n ->
+                if n > 10 then
+                    1
+                else if n > 5 then
+                    2
+                else
+                    3
+
+            classify 11 + 10 * classify 6 + 100 * classify 0
+            "
+        ),
+        321,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn combine_negated_comparisons() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            same : I64, I64 -> Bool
+            same = \a, b ->
+                lt = a < b
+                gt = a > b
+                !lt && !gt && (lt || !gt)
+
+            [same 3 3, same 2 3, same 3 2]
+            "
+        ),
+        RocList::from_slice(&[true, false, false]),
+        RocList<bool>
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn when_with_only_a_default_branch() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            bump : I64 -> I64
+            bump = \n ->
+                when n is
+                    _ -> n + 1
+
+            bump 41
+            "
+        ),
+        42,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn many_values_live_across_calls() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            inc : I64 -> I64
+            inc = \x -> x + 1
+
+            mix : I64, I64, I64, I64, I64, I64 -> I64
+            mix = \a, b, c, d, e, f ->
+                g = inc a
+                h = inc g
+                a + 10 * b + 100 * c + 1_000 * d + 10_000 * e + 100_000 * f + g + h
+
+            mix 1 2 3 4 5 6
+            "
+        ),
+        654_326,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn tail_recursive_loop() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            sumTo : I64, I64 -> I64
+            sumTo = \n, acc ->
+                if n == 0 then
+                    acc
+                else
+                    sumTo (n - 1) (acc + n)
+
+            sumTo 100 0
+            "
+        ),
+        5050,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn value_used_in_one_branch_only() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            choose : I64, I64 -> I64
+            choose = \n, m ->
+                big = m * 1_000
+                when n is
+                    0 -> big + n
+                    _ ->
+                        small = n + 1
+                        small * 2
+
+            choose 0 7 + choose 4 7
+            "
+        ),
+        7_010,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn literal_and_computed_value_live_across_a_call() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            double : I64 -> I64
+            double = \x -> x * 2
+
+            run : I64 -> I64
+            run = \n ->
+                seven = 7
+                computed = n + 1
+                d = double n
+                seven * 100 + computed * 10 + d
+
+            run 3
+            "
+        ),
+        746,
+        i64
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn literal_live_across_a_call_without_rematerializing() {
+    let options = roc_gen_dev::DevOptions {
+        rematerialize_cost: 0,
+        ..Default::default()
+    };
+
+    assert_evals_to_with_options!(
+        indoc!(
+            r"
+            double : I64 -> I64
+            double = \x -> x * 2
+
+            run : I64 -> I64
+            run = \n ->
+                seven = 7
+                d = double n
+                seven * 100 + d
+
+            run 3
+            "
+        ),
+        706,
+        i64,
+        options
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn byte_tag_of_a_lambda_set_with_three_closures() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            pick : U8 -> (I64 -> I64)
+            pick = \n ->
+                when n is
+                    0 -> \x -> x + 1
+                    1 -> \x -> x * 2
+                    _ -> \x -> x - 3
+
+            (pick 0) 10 + (pick 1) 10 + (pick 2) 10
+            "
+        ),
+        38,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn high_byte_of_a_long_str_literal() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            bytes = Str.toUtf8 "a string long enough to live on the heap: é"
+
+            when List.last bytes is
+                Ok byte -> Num.toU64 byte + 1000
+                Err _ -> 0
+            "#
+        ),
+        1169,
+        u64
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn leaf_proc_without_the_red_zone() {
+    let options = roc_gen_dev::DevOptions {
+        no_red_zone: roc_collections::all::MutSet::from_iter(["handler".to_string()]),
+        ..Default::default()
+    };
+
+    assert_evals_to_with_options!(
+        indoc!(
+            r"
+            handler : I64 -> I64
+            handler = \x ->
+                r = { a: x, b: x + 1, c: x + 2 }
+                r.a * 100 + r.b * 10 + r.c
+
+            handler 1 + handler 2
+            "
+        ),
+        357,
+        i64,
+        options
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn reuse_only_unique_cells() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            LinkedList : [Nil, Cons I64 LinkedList]
+
+            bump : LinkedList -> LinkedList
+            bump = \list ->
+                when list is
+                    Nil -> Nil
+                    Cons x rest -> Cons (x + 1) (bump rest)
+
+            sum : LinkedList -> I64
+            sum = \list ->
+                when list is
+                    Nil -> 0
+                    Cons x rest -> x + sum rest
+
+            main =
+                shared = Cons 1 (Cons 2 Nil)
+                bumped = bump shared
+                unique = bump (Cons 10 (Cons 20 Nil))
+
+                sum shared * 10_000 + sum bumped * 100 + sum unique
+            "#
+        ),
+        30_532,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn return_the_accumulator_of_a_loop() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            sumTo : I64, I64 -> I64
+            sumTo = \n, acc ->
+                if n == 0 then acc else sumTo (n - 1) (acc + n)
+
+            sumTo 10 0
+            "
+        ),
+        55,
+        i64
+    );
+    assert_evals_to!(
+        indoc!(
+            r"
+            halve : F64, I64 -> F64
+            halve = \x, n ->
+                if n == 0 then x else halve (x / 2) (n - 1)
+
+            halve 10.0 2
+            "
+        ),
+        2.5,
+        f64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn call_a_top_level_thunk() {
+    // `answer` is not a literal, so it becomes a proc without arguments that `main` calls.
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            answer : U64
+            answer = List.len [1, 2, 3] + 39
+
+            main =
+                x = 1
+                answer + answer + x
+                "#
+        ),
+        85,
+        u64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn call_whose_result_is_never_used() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            double : I64 -> I64
+            double = \x -> x * 2
+
+            unused = double 3
+            kept = double 21
+
+            kept
+            "
+        ),
+        42,
+        i64
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn mutual_recursion_in_tail_position_does_not_grow_the_stack() {
+    // Each call jumps to the other proc, so a million of them fit in the test thread's stack.
+    assert_evals_to!(
+        indoc!(
+            r"
+            isEven : I64 -> Bool
+            isEven = \n ->
+                if n == 0 then Bool.true else isOdd (n - 1)
+
+            isOdd : I64 -> Bool
+            isOdd = \n ->
+                if n == 0 then Bool.false else isEven (n - 1)
+
+            isEven 1_000_000
+            "
+        ),
+        true,
+        bool
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn dbg_leaves_its_value_unchanged() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            x = 42
+            dbg x
+
+            s = "a string long enough to live on the heap"
+            dbg s
+
+            if Str.isEmpty s then 0 else x + 1
+            "#
+        ),
+        43,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn call_each_specialization_of_a_polymorphic_function() {
+    // `double` is built once for I64 and once for F64, and each call has to link to its own.
+    assert_evals_to!(
+        indoc!(
+            r"
+            double : Num a -> Num a
+            double = \x -> x + x
+
+            Num.toF64 (double 21i64) + double 1.5f64
+            "
+        ),
+        45.0,
+        f64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn if_on_a_bool_argument_takes_each_branch() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            choose : Bool, I64, I64 -> I64
+            choose = \cond, a, b -> if cond then a else b
+
+            choose Bool.true 1 2 * 10 + choose Bool.false 1 2
+            "
+        ),
+        12,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn call_with_its_arguments_swapped() {
+    // `b` has to be placed in the first argument register while that register still holds `a`.
+    assert_evals_to!(
+        indoc!(
+            r"
+            sub : I64, I64 -> I64
+            sub = \x, y -> x - y
+
+            flip : I64, I64 -> I64
+            flip = \a, b -> sub b a
+
+            flip 10 3
+            "
+        ),
+        -7,
+        i64
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn stack_protected_procs_return_their_results() {
+    let options = roc_gen_dev::DevOptions {
+        stack_protector: roc_gen_dev::StackProtector::All,
+        ..Default::default()
+    };
+
+    assert_evals_to_with_options!(
+        indoc!(
+            r"
+            sum : { a : I64, b : I64, c : I64 } -> I64
+            sum = \{ a, b, c } -> a + b + c
+
+            sum { a: 1, b: 20, c: 300 }
+            "
+        ),
+        321,
+        i64,
+        options
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn call_result_passed_straight_to_another_call() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            f : I64 -> I64
+            f = \x -> x * 3
+
+            g : I64 -> I64
+            g = \y -> y + 4
+
+            g (f 5)
+            "
+        ),
+        19,
+        i64
+    );
+}
+
+#[test]
+#[cfg(all(feature = "gen-dev", target_os = "linux", target_arch = "x86_64"))]
+fn standalone_program_exits_with_the_result_of_main() {
+    use crate::helpers::dev::run_standalone_main;
+
+    assert_eq!(run_standalone_main("40u8 + 2"), 42);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn loop_with_a_float_accumulator() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            sumOfSquares : F64, F64, I64 -> F64
+            sumOfSquares = \acc, x, n ->
+                if n == 0 then acc else sumOfSquares (acc + x * x) (x + 1.0) (n - 1)
+
+            sumOfSquares 0.0 1.0 4
+            "
+        ),
+        30.0,
+        f64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn loop_entered_with_the_arguments_of_its_proc() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            fib : I64, I64, I64 -> I64
+            fib = \n, a, b ->
+                if n == 0 then a else fib (n - 1) b (a + b)
+
+            fib 50 0 1
+            "
+        ),
+        12_586_269_025,
+        i64
+    );
+}
@@ -1182,3 +1182,205 @@ fn pass_record_of_u8s() {
         bool
     )
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn read_both_fields_of_a_returned_record() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            pair : I64, I64 -> { x : I64, y : I64 }
+            pair = \a, b -> { x: a, y: b }
+
+            p = pair 3 4
+            p.x * 10 + p.y
+            "
+        ),
+        34,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn nested_record_field_access() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            outer = { a: 1, b: { c: 2, d: { e: 3, f: 4 } } }
+
+            outer.b.d.f * 100 + outer.b.c * 10 + outer.a
+            "
+        ),
+        421,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn copy_a_sixteen_byte_record() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            swap : { a : I64, b : I64 } -> { a : I64, b : I64 }
+            swap = \r -> { a: r.b, b: r.a }
+
+            pick : Bool, { a : I64, b : I64 }, { a : I64, b : I64 } -> { a : I64, b : I64 }
+            pick = \first, x, y -> if first then x else y
+
+            r = { a: 1, b: 2 }
+            copy = pick Bool.false r (swap r)
+
+            copy.a * 10 + copy.b
+            "
+        ),
+        21,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn wrap_and_unwrap_a_single_field_record() {
+    use roc_std::RocStr;
+
+    assert_evals_to!(
+        indoc!(
+            r"
+            wrap : I64 -> { n : I64 }
+            wrap = \n -> { n }
+
+            unwrap : { n : I64 } -> I64
+            unwrap = \r -> r.n
+
+            unwrap (wrap 42)
+            "
+        ),
+        42,
+        i64
+    );
+    assert_evals_to!(
+        indoc!(
+            r#"
+            wrap : Str -> { name : Str }
+            wrap = \name -> { name }
+
+            unwrap : { name : Str } -> Str
+            unwrap = \r -> r.name
+
+            unwrap (wrap "a name long enough to live on the heap")
+            "#
+        ),
+        RocStr::from("a name long enough to live on the heap"),
+        RocStr
+    );
+}
+
+#[test]
+#[cfg(all(feature = "gen-dev", target_arch = "x86_64"))]
+fn small_float_records_are_returned_to_the_host_by_the_c_abi() {
+    use crate::helpers::dev::run_exposed_main;
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct FloatAndInt {
+        x: f64,
+        y: i64,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct IntAndTwoFloats {
+        a: i64,
+        b: f32,
+        c: f32,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct TwoFloats {
+        x: f64,
+        y: f64,
+    }
+
+    assert_eq!(
+        run_exposed_main::<FloatAndInt>("{ x: 1.5f64, y: 42i64 }"),
+        FloatAndInt { x: 1.5, y: 42 }
+    );
+    assert_eq!(
+        run_exposed_main::<IntAndTwoFloats>("{ a: 7i64, b: 0.25f32, c: -2.5f32 }"),
+        IntAndTwoFloats {
+            a: 7,
+            b: 0.25,
+            c: -2.5
+        }
+    );
+    assert_eq!(
+        run_exposed_main::<TwoFloats>("{ x: 0.5f64, y: 3.0f64 }"),
+        TwoFloats { x: 0.5, y: 3.0 }
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev", feature = "gen-wasm"))]
+fn read_fields_of_nested_records_of_an_argument() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            Point : { x : I64, y : I64 }
+
+            span : { from : Point, to : Point } -> I64
+            span = \line ->
+                from = line.from
+                to = line.to
+
+                (to.x - from.x) * 100 + (to.y - from.y)
+
+            span { from: { x: 1, y: 2 }, to: { x: 10, y: 20 } }
+            "
+        ),
+        918,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn loop_with_a_record_parameter() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            sumTo : { count : I64, acc : I64 } -> I64
+            sumTo = \state ->
+                if state.count == 0 then
+                    state.acc
+                else
+                    sumTo { count: state.count - 1, acc: state.acc + state.count }
+
+            sumTo { count: 10, acc: 1000 }
+            "
+        ),
+        1055,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn call_with_arguments_returning_a_large_record() {
+    assert_evals_to!(
+        indoc!(
+            r"
+            make : I64, I64 -> { a : I64, b : I64, c : I64 }
+            make = \x, y -> { a: x, b: y, c: x + y }
+
+            r = make 10 20
+
+            r.a + r.b * 2 + r.c * 3
+            "
+        ),
+        140,
+        i64
+    );
+}
@@ -365,6 +365,43 @@ fn small_str_zeroed_literal() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev"))]
+fn empty_str_literal() {
+    // The empty string is a small string of length 0, so only the small string bit is set.
+    let mut expected = [0; 24];
+    expected[23] = 0b1000_0000;
+
+    assert_llvm_evals_to!(r#""""#, expected, [u8; 24]);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev"))]
+fn ten_byte_str_literal_is_small() {
+    let mut expected = [0; 24];
+    expected[..10].copy_from_slice(b"hello roc!");
+    expected[23] = 0b1000_0000 | 10;
+
+    assert_llvm_evals_to!(r#""hello roc!""#, expected, [u8; 24]);
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev"))]
+fn thirty_byte_str_literal_is_big() {
+    assert_evals_to!(
+        r#""a string too long to be inline""#,
+        RocStr::from("a string too long to be inline"),
+        RocStr
+    );
+    // A big string keeps its length in the last word, so the small string bit is clear.
+    assert_evals_to!(
+        r#""a string too long to be inline""#,
+        0,
+        [u8; 24],
+        |bytes: [u8; 24]| bytes[23] & 0b1000_0000
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-dev"))]
 fn small_str_concat_empty_first_arg() {
@@ -1478,7 +1515,7 @@ fn str_to_u8() {
 }
 
 #[test]
-#[cfg(feature = "gen-llvm")]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev"))]
 fn str_to_f64() {
     assert_evals_to!(
         indoc!(
@@ -1495,7 +1532,7 @@ fn str_to_f64() {
 }
 
 #[test]
-#[cfg(feature = "gen-llvm")]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev"))]
 fn str_to_f32() {
     assert_evals_to!(
         indoc!(
@@ -2060,3 +2097,27 @@ fn str_drop_suffix() {
         RocStr
     );
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn str_local_live_across_a_call() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            double : I64 -> I64
+            double = \x -> x * 2
+
+            shout : Str, I64 -> Str
+            shout = \word, n ->
+                loud = Str.concat word "!"
+                times = double n
+
+                if times > 0 then loud else word
+
+            shout "hi" 1
+            "#
+        ),
+        RocStr::from("hi!"),
+        RocStr
+    );
+}
@@ -2327,3 +2327,69 @@ fn recursive_tag_id_in_allocation_eq() {
         bool
     );
 }
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn reuse_with_tag_change() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            Expr : [Val I64, Neg Expr]
+
+            # the `Neg` allocation is reset and reused to build the `Val`
+            simplify : Expr -> Expr
+            simplify = \e ->
+                when e is
+                    Neg (Val v) -> Val (-v)
+                    _ -> e
+
+            eval : Expr -> I64
+            eval = \e ->
+                when e is
+                    Val v -> v
+                    Neg inner -> -(eval inner)
+
+            main = eval (simplify (Neg (Val 42)))
+            "#
+        ),
+        -42,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
+fn reset_of_shared_value_allocates_fresh() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            Expr : [Val I64, Neg Expr]
+
+            # `e` is still used after `simplify`, so the `Neg` allocation is shared
+            # and the `Val` must be built in a fresh one
+            simplify : Expr -> Expr
+            simplify = \e ->
+                when e is
+                    Neg (Val v) -> Val (-v)
+                    _ -> e
+
+            eval : Expr -> I64
+            eval = \e ->
+                when e is
+                    Val v -> v
+                    Neg inner -> -(eval inner)
+
+            main =
+                e = Neg (Val 42)
+
+                eval (simplify e) * 1000 + eval e
+            "#
+        ),
+        -42042,
+        i64
+    );
+}
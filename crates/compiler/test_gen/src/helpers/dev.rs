@@ -36,6 +36,7 @@ pub fn helper(
     src: &str,
     _leak: bool,
     lazy_literals: bool,
+    options: roc_gen_dev::DevOptions,
 ) -> (String, Vec<roc_problem::can::Problem>, Library) {
     use std::path::PathBuf;
 
@@ -197,6 +198,7 @@ pub fn helper(
         exposed_to_host: exposed_to_host.top_level_values.keys().copied().collect(),
         lazy_literals,
         mode: roc_gen_dev::AssemblyBackendMode::Test,
+        options,
     };
 
     let target = target_lexicon::Triple::host().into();
@@ -314,6 +316,59 @@ pub(crate) fn run_test_main<T>(lib: &libloading::Library) -> Result<T, (String,
     result.into_result()
 }
 
+/// Calls the exposed `main` of `src` directly, the way a C host would, rather than through
+/// `test_main`. The value comes back however the C calling convention returns a `T`.
+#[allow(dead_code)]
+pub(crate) fn run_exposed_main<T>(src: &str) -> T {
+    let arena = bumpalo::Bump::new();
+    let (main_fn_name, errors, lib) =
+        helper(&arena, src, true, false, roc_gen_dev::DevOptions::default());
+
+    assert_eq!(errors, Vec::new(), "Encountered errors: {:?}", errors);
+
+    let main = get_raw_fn::<T>(&main_fn_name, &lib);
+
+    unsafe { main() }
+}
+
+/// Builds `src` with the `_start` of a program without a host, which calls the exposed `main`,
+/// runs that `_start` in a child process, and returns the status the child exited with.
+#[allow(dead_code)]
+pub(crate) fn run_standalone_main(src: &str) -> i32 {
+    // the exposed `main` of the module `promote_expr_to_module` makes
+    const MAIN_FN_NAME: &str = "roc__main_1_exposed";
+
+    let arena = bumpalo::Bump::new();
+    let options = roc_gen_dev::DevOptions {
+        standalone_main: Some(MAIN_FN_NAME.to_string()),
+        ..Default::default()
+    };
+    let (main_fn_name, errors, lib) = helper(&arena, src, true, false, options);
+
+    assert_eq!(errors, Vec::new(), "Encountered errors: {:?}", errors);
+    assert_eq!(main_fn_name, MAIN_FN_NAME);
+
+    let start = get_raw_fn::<()>("_start", &lib);
+
+    unsafe {
+        match libc::fork() {
+            -1 => panic!("failed to fork"),
+            0 => {
+                // `_start` exits the child itself, it never returns
+                start();
+                libc::_exit(-1)
+            }
+            child => {
+                let mut status = 0;
+                assert_eq!(libc::waitpid(child, &mut status, 0), child);
+                assert!(libc::WIFEXITED(status), "the child didn't exit: {status}");
+
+                libc::WEXITSTATUS(status)
+            }
+        }
+    }
+}
+
 impl<T: Sized> From<RocCallResult<T>> for Result<T, (String, CrashTag)> {
     fn from(call_result: RocCallResult<T>) -> Self {
         call_result.into_result()
@@ -327,6 +382,7 @@ pub(crate) fn asm_evals_to<T, U, F>(
     transform: F,
     leak: bool,
     lazy_literals: bool,
+    options: roc_gen_dev::DevOptions,
 ) where
     U: PartialEq + std::fmt::Debug,
     F: FnOnce(T) -> U,
@@ -335,7 +391,7 @@ pub(crate) fn asm_evals_to<T, U, F>(
 
     let arena = Bump::new();
     let (_main_fn_name, errors, lib) =
-        crate::helpers::dev::helper(&arena, src, leak, lazy_literals);
+        crate::helpers::dev::helper(&arena, src, leak, lazy_literals, options);
 
     let result = crate::helpers::dev::run_test_main::<T>(&lib);
 
@@ -393,9 +449,41 @@ macro_rules! assert_evals_to {
             $transform,
             $leak,
             $lazy_literals,
+            roc_gen_dev::DevOptions::default(),
         );
     };
 }
 
+/// Like `assert_evals_to`, but builds the program with the given `roc_gen_dev::DevOptions`.
+#[allow(unused_macros)]
+macro_rules! assert_evals_to_with_options {
+    ($src:expr, $expected:expr, $ty:ty, $options:expr) => {
+        // Run both with and without lazy literal optimization.
+        {
+            $crate::helpers::dev::asm_evals_to::<$ty, _, _>(
+                $src,
+                $expected,
+                $crate::helpers::dev::identity,
+                true,
+                false,
+                $options.clone(),
+            );
+        }
+        {
+            $crate::helpers::dev::asm_evals_to::<$ty, _, _>(
+                $src,
+                $expected,
+                $crate::helpers::dev::identity,
+                true,
+                true,
+                $options,
+            );
+        }
+    };
+}
+
 #[allow(unused_imports)]
 pub(crate) use assert_evals_to;
+
+#[allow(unused_imports)]
+pub(crate) use assert_evals_to_with_options;
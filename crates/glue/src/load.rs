@@ -59,6 +59,7 @@ pub fn generate(
                 emit_debug_info: false,
                 emit_llvm_ir: false,
                 fuzz: false,
+                dev_options: Default::default(),
             };
 
             let load_config = standard_load_config(
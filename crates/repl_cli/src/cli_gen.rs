@@ -313,6 +313,10 @@ fn mono_module_to_dylib_asm<'a>(
         exposed_to_host: exposed_to_host.top_level_values.keys().copied().collect(),
         lazy_literals,
         mode: roc_gen_dev::AssemblyBackendMode::Repl,
+        options: roc_gen_dev::DevOptions {
+            trap_unreachable: true,
+            ..Default::default()
+        },
     };
 
     let module_object =